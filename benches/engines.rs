@@ -0,0 +1,247 @@
+//! Compares the engines on the same grammar and input: the rules themselves and the
+//! program compiled from them, with and without building the tree. Run with `cargo bench`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use yaast::vm::Program;
+use yaast::Grammar;
+
+/// Counts allocations, to compare how many each way of building the tree makes.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+/// The number of allocations made by `f`, including those of what it returns.
+fn allocations<T>(f: impl FnOnce() -> T) -> usize {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let result = f();
+    let count = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    drop(result);
+    count
+}
+
+const VALUES: &str = r#"
+    Values <- Space (Value Space)* EOF ;
+    Value <- "null" / "true" / "false" / List / Number / Word ;
+    List <- '[' ^ Space (Value Space (',' Space Value Space)*)? must(']') ;
+    Number <- '-'? Digit+ ;
+    Digit <- '0' / '1' / '2' / '3' / '4' / '5' / '6' / '7' / '8' / '9' ;
+    Word <- Letter+ ;
+    Letter <- 'a' / 'b' / 'c' / 'd' / 'e' / 'f' / 'g' / 'h' / 'i' / 'j' / 'k' / 'l' / 'm' ;
+    Space <- (' ' / '\n')* ;
+"#;
+
+const TOKENS: &str = r#"
+    Tokens <- Space (Token Space)* EOF ;
+    Token <- "as" Space / "break" Space / "const" Space / "continue" Space / "crate" Space
+        / "else" Space / "enum" Space / "extern" Space / "false" Space / "fn" Space
+        / "for" Space / "if" Space / "impl" Space / "in" Space / "let" Space / "loop" Space
+        / "match" Space / "mod" Space / "move" Space / "mut" Space / "pub" Space
+        / "ref" Space / "return" Space / "self" Space / "static" Space / "struct" Space
+        / "super" Space / "trait" Space / "true" Space / "type" Space / "unsafe" Space
+        / "use" Space / "where" Space / "while" Space / Name / Punct ;
+    Name <- Letter+ ;
+    Letter <- 'a' / 'b' / 'c' / 'd' / 'e' / 'f' / 'g' / 'h' / 'i' / 'j' / 'k' / 'l' / 'm'
+        / 'n' / 'o' / 'p' / 'q' / 'r' / 's' / 't' / 'u' / 'v' / 'w' / 'x' / 'y' / 'z' / '_' ;
+    Punct <- '(' / ')' / '{' / '}' / ';' / ',' / '.' / '=' / '?' / '+' / '*' ;
+    Space <- (' ' / '\n')* ;
+"#;
+
+/// About 100 KB of nested lists.
+fn values() -> String {
+    let item = "[null, [12, -345, abc], [true, [false, [6789, deadbeef]]], face]\n";
+    item.repeat(100_000 / item.len())
+}
+
+/// About 100 KB of code, mostly names that are checked against every keyword first.
+fn tokens() -> String {
+    let item = "let value = parse_number(digits, radix)?; total = total + value * weight;\n";
+    item.repeat(100_000 / item.len())
+}
+
+fn engines(c: &mut Criterion) {
+    let input = values();
+    let grammar = Grammar::from_peg(VALUES).unwrap();
+    let program = Program::compile(&grammar.start).unwrap();
+    assert_eq!(
+        grammar.start.try_parse(&input).unwrap().content.len(),
+        input.len()
+    );
+
+    let mut group = c.benchmark_group("values");
+    group.bench_function("rules", |b| {
+        b.iter(|| grammar.start.try_parse(black_box(&input)).unwrap())
+    });
+    group.bench_function("vm", |b| b.iter(|| program.run(black_box(&input)).unwrap()));
+    group.bench_function("vm without tree", |b| {
+        b.iter(|| program.recognize(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+/// Most options of `Token` start with a different character than the input, so they can
+/// be skipped.
+fn keywords(c: &mut Criterion) {
+    let input = tokens();
+    let grammar = Grammar::from_peg(TOKENS).unwrap();
+    let program = Program::compile(&grammar.start).unwrap();
+    assert_eq!(program.recognize(&input).unwrap(), input.len());
+
+    let mut group = c.benchmark_group("keywords");
+    group.bench_function("rules", |b| {
+        b.iter(|| grammar.start.try_parse(black_box(&input)).unwrap())
+    });
+    yaast::rule::set_dispatch(false);
+    group.bench_function("rules trying every option", |b| {
+        b.iter(|| grammar.start.try_parse(black_box(&input)).unwrap())
+    });
+    yaast::rule::set_dispatch(true);
+    group.bench_function("vm without tree", |b| {
+        b.iter(|| program.recognize(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+/// Reserved words of SQL, checked one by one or with a trie.
+const SQL: [&str; 48] = [
+    "add",
+    "all",
+    "alter",
+    "and",
+    "any",
+    "as",
+    "asc",
+    "between",
+    "by",
+    "case",
+    "check",
+    "column",
+    "constraint",
+    "create",
+    "database",
+    "default",
+    "delete",
+    "desc",
+    "distinct",
+    "drop",
+    "exists",
+    "foreign",
+    "from",
+    "group",
+    "having",
+    "in",
+    "index",
+    "insert",
+    "into",
+    "is",
+    "join",
+    "key",
+    "left",
+    "like",
+    "limit",
+    "not",
+    "null",
+    "or",
+    "order",
+    "primary",
+    "right",
+    "select",
+    "set",
+    "table",
+    "union",
+    "unique",
+    "update",
+    "where",
+];
+
+fn literals(c: &mut Criterion) {
+    let input = SQL
+        .iter()
+        .rev()
+        .cycle()
+        .take(20_000)
+        .copied()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut choice: Vec<String> = SQL.iter().map(|word| format!("{:?}", word)).collect();
+    let trie = Grammar::from_peg(&format!(
+        "Words <- (Word ' '*)* EOF ; Word <- keywords({}) ;",
+        choice.join(", ")
+    ))
+    .unwrap();
+    // Longer words first, so that "in" does not stop "index" from matching.
+    choice.sort_by_key(|word| std::cmp::Reverse(word.len()));
+    let sor = Grammar::from_peg(&format!(
+        "Words <- (Word ' '*)* EOF ; Word <- {} ;",
+        choice.join(" / ")
+    ))
+    .unwrap();
+
+    let mut group = c.benchmark_group("literals");
+    group.bench_function("sor", |b| {
+        b.iter(|| sor.start.try_parse(black_box(&input)).unwrap())
+    });
+    group.bench_function("trie", |b| {
+        b.iter(|| trie.start.try_parse(black_box(&input)).unwrap())
+    });
+    let sor = Program::compile(&sor.start).unwrap();
+    let trie = Program::compile(&trie.start).unwrap();
+    group.bench_function("sor without tree", |b| {
+        b.iter(|| sor.recognize(black_box(&input)).unwrap())
+    });
+    group.bench_function("trie without tree", |b| {
+        b.iter(|| trie.recognize(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+/// Building a `Node` for every match against building all of them in one `Tree`.
+fn trees(c: &mut Criterion) {
+    let input = values();
+    let grammar = Grammar::from_peg(VALUES).unwrap();
+    let program = Program::compile(&grammar.start).unwrap();
+    eprintln!(
+        "allocations per parse: rules {}, rules into a tree {}, vm {}, vm into a tree {}",
+        allocations(|| grammar.start.try_parse(&input).unwrap()),
+        allocations(|| grammar.start.parse_tree(&input).unwrap()),
+        allocations(|| program.run(&input).unwrap()),
+        allocations(|| program.run_tree(&input).unwrap()),
+    );
+
+    let mut group = c.benchmark_group("trees");
+    group.bench_function("rules", |b| {
+        b.iter(|| grammar.start.try_parse(black_box(&input)).unwrap())
+    });
+    group.bench_function("rules into a tree", |b| {
+        b.iter(|| grammar.start.parse_tree(black_box(&input)).unwrap())
+    });
+    group.bench_function("vm", |b| b.iter(|| program.run(black_box(&input)).unwrap()));
+    group.bench_function("vm into a tree", |b| {
+        b.iter(|| program.run_tree(black_box(&input)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, engines, keywords, literals, trees);
+criterion_main!(benches);
@@ -0,0 +1,355 @@
+mod repl;
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use yaast::render::{RenderOptions, TreeOptions};
+use yaast::rule::{unnamed_ids, Rule, ERROR_ID};
+use yaast::{Filter, Grammar, Node};
+
+const USAGE: &str = "\
+usage: yaast <grammar.peg> [input...] [options]
+       yaast repl <grammar.peg> [options]
+
+Parses the input files, or the standard input if there are none, with a grammar
+in PEG notation and prints the trees. `repl` parses lines as they are typed and
+reloads the grammar whenever its file changes.
+
+options:
+  -s, --start <rule>     rule to parse with, the first one by default
+  -f, --format <format>  ascii (default), json, sexpr or svg
+      --named            only show the rules defined in the grammar
+      --keep <rules>     only show these rules, separated by commas
+      --hide <rules>     hide these rules, separated by commas
+  -h, --help             show this message
+";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    Json,
+    Sexpr,
+    Svg,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    grammar: String,
+    inputs: Vec<String>,
+    start: Option<String>,
+    format: Format,
+    named: bool,
+    keep: Vec<String>,
+    hide: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Command {
+    Parse(Options),
+    Repl(Options),
+    Help,
+}
+
+fn parse_args(args: &[String]) -> Result<Command, String> {
+    if args.first().map(String::as_str) == Some("repl") {
+        return match parse_args(&args[1..])? {
+            Command::Parse(options) if !options.inputs.is_empty() => {
+                Err("repl does not take input files".to_string())
+            }
+            Command::Parse(options) => Ok(Command::Repl(options)),
+            command => Ok(command),
+        };
+    }
+    let mut grammar = None;
+    let mut inputs = Vec::new();
+    let mut start = None;
+    let mut format = Format::Ascii;
+    let mut named = false;
+    let mut keep = Vec::new();
+    let mut hide = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("{} needs a value", arg))
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-s" | "--start" => start = Some(value()?),
+            "-f" | "--format" => {
+                format = match value()?.as_str() {
+                    "ascii" => Format::Ascii,
+                    "json" => Format::Json,
+                    "sexpr" => Format::Sexpr,
+                    "svg" => Format::Svg,
+                    other => return Err(format!("unknown format {}", other)),
+                }
+            }
+            "--named" => named = true,
+            "--keep" => keep.extend(value()?.split(',').map(str::to_string)),
+            "--hide" => hide.extend(value()?.split(',').map(str::to_string)),
+            option if option.starts_with('-') && option != "-" => {
+                return Err(format!("unknown option {}", option))
+            }
+            path if grammar.is_none() => grammar = Some(path.to_string()),
+            path => inputs.push(path.to_string()),
+        }
+    }
+    if !keep.is_empty() && (!hide.is_empty() || named) {
+        return Err("--keep cannot be combined with --hide or --named".to_string());
+    }
+    match grammar {
+        Some(grammar) => Ok(Command::Parse(Options {
+            grammar,
+            inputs,
+            start,
+            format,
+            named,
+            keep,
+            hide,
+        })),
+        None => Err("no grammar given".to_string()),
+    }
+}
+
+/// The id of a rule of the grammar or of an unnamed rule like `Seq`.
+fn rule_id(grammar: &Grammar, name: &str) -> Result<usize, String> {
+    if let Some(rule) = grammar.get(name) {
+        return Ok(rule.id);
+    }
+    unnamed_ids()
+        .iter()
+        .find(|(_, unnamed, _)| *unnamed == name)
+        .map(|(id, _, _)| *id)
+        .ok_or_else(|| format!("unknown rule {}", name))
+}
+
+/// The filter selected by `--named`, `--keep` and `--hide`.
+fn make_filter(
+    grammar: &Grammar,
+    named: bool,
+    keep: &[String],
+    hide: &[String],
+) -> Result<Option<Filter>, String> {
+    let ids = |names: &[String]| {
+        names
+            .iter()
+            .map(|name| rule_id(grammar, name))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    if !keep.is_empty() {
+        return Ok(Some(Filter::new_with_list(false, ids(keep)?)));
+    }
+    if named {
+        let mut allowed: Vec<_> = grammar.rules.iter().map(|rule| rule.id).collect();
+        allowed.push(*ERROR_ID);
+        let hidden = ids(hide)?;
+        allowed.retain(|id| !hidden.contains(id));
+        return Ok(Some(Filter::new_with_list(false, allowed)));
+    }
+    if !hide.is_empty() {
+        return Ok(Some(Filter::new_with_list(true, ids(hide)?)));
+    }
+    Ok(None)
+}
+
+/// Applies the filter if there is one, see `Filter::filter_tree`.
+fn apply_filter<'a>(tree: Node<'a>, filter: Option<&Filter>) -> Node<'a> {
+    match filter {
+        Some(filter) => filter.filter_tree(tree),
+        None => tree,
+    }
+}
+
+fn render(tree: &Node, format: Format) -> Result<String, String> {
+    match format {
+        Format::Ascii => Ok(tree.tree(TreeOptions::default()).to_string()),
+        Format::Json => Ok(tree.to_json() + "\n"),
+        Format::Sexpr => Ok(tree.to_sexpr() + "\n"),
+        Format::Svg => tree
+            .to_svg(&RenderOptions::default())
+            .map_err(|error| error.to_string()),
+    }
+}
+
+fn start_rule<'a>(grammar: &Grammar<'a>, start: Option<&str>) -> Result<Rule<'a>, String> {
+    match start {
+        Some(name) => grammar
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("the grammar has no rule {}", name)),
+        None => Ok(grammar.start.clone()),
+    }
+}
+
+/// Parses all inputs and prints their trees. An input only parses if the rule matches all
+/// of it. Returns whether all of them parsed, errors that stop everything are returned as
+/// `Err`.
+fn run(options: &Options) -> Result<bool, String> {
+    let mut inputs = Vec::new();
+    if options.inputs.is_empty() {
+        let mut input = String::new();
+        std::io::stdin()
+            .read_to_string(&mut input)
+            .map_err(|error| format!("cannot read standard input: {}", error))?;
+        inputs.push(("<stdin>".to_string(), input));
+    }
+    for path in &options.inputs {
+        let input = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path, error))?;
+        inputs.push((path.clone(), input));
+    }
+
+    let text = std::fs::read_to_string(&options.grammar)
+        .map_err(|error| format!("cannot read {}: {}", options.grammar, error))?;
+    let grammar =
+        Grammar::from_peg(&text).map_err(|error| format!("{}:{}", options.grammar, error))?;
+    let rule = start_rule(&grammar, options.start.as_deref())?;
+    let filter = make_filter(&grammar, options.named, &options.keep, &options.hide)?;
+
+    let mut success = true;
+    for (name, input) in &inputs {
+        match rule.parse_all(input) {
+            Ok(tree) => {
+                let tree = apply_filter(tree, filter.as_ref());
+                if inputs.len() > 1 && options.format == Format::Ascii {
+                    println!("{}:", name);
+                }
+                print!("{}", render(&tree, options.format)?);
+            }
+            Err(error) => {
+                eprint!("{}", error.render(input, name));
+                success = false;
+            }
+        }
+    }
+    Ok(success)
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match parse_args(&args) {
+        Ok(Command::Help) => {
+            print!("{}", USAGE);
+            ExitCode::SUCCESS
+        }
+        Ok(Command::Repl(options)) => match repl::run(options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => {
+                eprintln!("error: {}", error);
+                ExitCode::from(2)
+            }
+        },
+        Ok(Command::Parse(options)) => match run(&options) {
+            Ok(true) => ExitCode::SUCCESS,
+            Ok(false) => ExitCode::from(1),
+            Err(error) => {
+                eprintln!("error: {}", error);
+                ExitCode::from(2)
+            }
+        },
+        Err(error) => {
+            eprint!("error: {}\n\n{}", error, USAGE);
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn arguments() {
+        assert_eq!(
+            parse_args(&args(
+                "g.peg a.txt -f json --start Value b.txt --hide Seq,Char"
+            )),
+            Ok(Command::Parse(Options {
+                grammar: "g.peg".to_string(),
+                inputs: vec!["a.txt".to_string(), "b.txt".to_string()],
+                start: Some("Value".to_string()),
+                format: Format::Json,
+                named: false,
+                keep: Vec::new(),
+                hide: vec!["Seq".to_string(), "Char".to_string()],
+            }))
+        );
+        assert_eq!(parse_args(&args("g.peg --help")), Ok(Command::Help));
+        assert!(parse_args(&args("")).is_err());
+        assert!(parse_args(&args("g.peg -f xml")).is_err());
+        assert!(parse_args(&args("g.peg --start")).is_err());
+        assert!(parse_args(&args("g.peg --keep A --named")).is_err());
+        assert!(matches!(
+            parse_args(&args("repl g.peg -s Value")),
+            Ok(Command::Repl(Options { start: Some(_), .. }))
+        ));
+        assert!(parse_args(&args("repl g.peg a.txt")).is_err());
+    }
+
+    #[test]
+    fn filters_and_formats() {
+        let input = "ab";
+        let grammar = Grammar::from_peg("Pair <- Letter Letter ;\nLetter <- 'a' / 'b' ;").unwrap();
+        let tree = grammar.start.parse(input).unwrap();
+        let filter = make_filter(&grammar, true, &[], &[]).unwrap();
+        let tree = apply_filter(tree, filter.as_ref());
+        assert_eq!(
+            render(&tree, Format::Sexpr).unwrap(),
+            "(Pair (Letter \"a\") (Letter \"b\"))\n"
+        );
+        assert_eq!(
+            render(&tree, Format::Ascii).unwrap(),
+            "Pair \"ab\"\n├─ Letter \"a\"\n└─ Letter \"b\"\n"
+        );
+        assert!(render(&tree, Format::Svg).unwrap().contains("<svg"));
+
+        let tree = grammar.start.parse(input).unwrap();
+        let filter = make_filter(&grammar, false, &["Char".to_string()], &[]).unwrap();
+        let tree = apply_filter(tree, filter.as_ref());
+        assert_eq!(
+            render(&tree, Format::Sexpr).unwrap(),
+            "(Root (Char \"a\") (Char \"b\"))\n"
+        );
+        assert_eq!(
+            make_filter(&grammar, false, &[], &["Nothing".to_string()]).err(),
+            Some("unknown rule Nothing".to_string())
+        );
+        assert!(start_rule(&grammar, Some("Letter")).is_ok());
+        assert!(start_rule(&grammar, Some("Nothing")).is_err());
+        for name in ["Literals", "Must", "Recover", "Error"] {
+            assert!(rule_id(&grammar, name).is_ok());
+        }
+    }
+
+    #[test]
+    fn inputs_must_be_parsed_whole() {
+        let dir = std::env::temp_dir().join(format!("yaast-cli-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        std::fs::write(
+            path("g.peg"),
+            "Pair <- Letter Letter ;\nLetter <- 'a' / 'b' ;",
+        )
+        .unwrap();
+        std::fs::write(path("whole.txt"), "ab").unwrap();
+        std::fs::write(path("prefix.txt"), "abZZZ garbage").unwrap();
+        let options = |input: &str| match parse_args(&[path("g.peg"), path(input)]) {
+            Ok(Command::Parse(options)) => options,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(run(&options("whole.txt")), Ok(true));
+        assert_eq!(run(&options("prefix.txt")), Ok(false));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let input = "abZZZ garbage";
+        let grammar = Grammar::from_peg("Pair <- Letter Letter ;\nLetter <- 'a' / 'b' ;").unwrap();
+        let error = grammar.start.parse_all(input).unwrap_err();
+        assert_eq!(error.to_string(), "expected end of input at offset 2");
+    }
+}
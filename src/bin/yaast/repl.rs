@@ -0,0 +1,271 @@
+use std::io::{BufRead, Write};
+use std::time::SystemTime;
+
+use yaast::trace::Trace;
+use yaast::Grammar;
+
+use crate::{apply_filter, make_filter, render, start_rule, Format, Options};
+
+const HELP: &str = "\
+Any line that does not start with ':' is parsed with the start rule.
+
+commands:
+  :start [rule]      show or change the start rule
+  :rules             list the rules of the grammar
+  :format <format>   ascii, json, sexpr or svg
+  :trace             toggle printing every rule attempt
+  :reload            read the grammar file again
+  :help              show this message
+  :quit              leave
+";
+
+/// The state of an interactive session.
+///
+/// Only the text of the grammar is kept, the rules are built again for every
+/// input, as they borrow the input they parse.
+pub struct Session {
+    options: Options,
+    text: String,
+    modified: Option<SystemTime>,
+    trace: bool,
+}
+
+/// What to do after handling a line.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    Print(String),
+    Quit,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+impl Session {
+    /// Loads the grammar file, failing if it cannot be read or is not a valid grammar.
+    pub fn new(options: Options) -> Result<Session, String> {
+        let mut session = Session {
+            options,
+            text: String::new(),
+            modified: None,
+            trace: false,
+        };
+        session.load()?;
+        if let Some(start) = &session.options.start {
+            let grammar = session.grammar()?;
+            start_rule(&grammar, Some(start))?;
+        }
+        Ok(session)
+    }
+
+    fn grammar<'a>(&self) -> Result<Grammar<'a>, String> {
+        Grammar::from_peg(&self.text).map_err(|error| format!("{}:{}", self.options.grammar, error))
+    }
+
+    /// Reads the grammar file, keeping the current grammar if the new one is invalid.
+    fn load(&mut self) -> Result<(), String> {
+        let path = &self.options.grammar;
+        let modified = modified(path);
+        let text = std::fs::read_to_string(path)
+            .map_err(|error| format!("cannot read {}: {}", path, error))?;
+        Grammar::from_peg(&text).map_err(|error| format!("{}:{}", path, error))?;
+        self.text = text;
+        self.modified = modified;
+        Ok(())
+    }
+
+    /// Reloads the grammar if the file changed since it was last read.
+    /// Returns a message to show if it did, or tried to.
+    fn refresh(&mut self) -> Option<String> {
+        let modified = modified(&self.options.grammar);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        match self.load() {
+            Ok(()) => Some(format!("reloaded {}\n", self.options.grammar)),
+            Err(error) => {
+                // Do not report the same broken file again for every input.
+                self.modified = modified;
+                Some(format!("error: {}\n", error))
+            }
+        }
+    }
+
+    /// Handles a line typed by the user, without its line break.
+    pub fn handle(&mut self, line: &str) -> Reply {
+        let mut output = self.refresh().unwrap_or_default();
+        let result = match line.strip_prefix(':') {
+            Some(command) => {
+                let mut words = command.split_whitespace();
+                match (words.next(), words.next()) {
+                    (Some("quit" | "q"), _) => return Reply::Quit,
+                    (Some("help" | "h"), _) => Ok(HELP.to_string()),
+                    (Some("start"), name) => self.start(name),
+                    (Some("rules"), _) => self.rules(),
+                    (Some("format"), Some(format)) => self.format(format),
+                    (Some("trace"), _) => {
+                        self.trace = !self.trace;
+                        Ok(format!("trace {}\n", if self.trace { "on" } else { "off" }))
+                    }
+                    (Some("reload"), _) => self
+                        .load()
+                        .map(|()| format!("reloaded {}\n", self.options.grammar)),
+                    _ => Err(format!("unknown command :{}, try :help", command.trim())),
+                }
+            }
+            None => self.parse(line),
+        };
+        match result {
+            Ok(text) => output += &text,
+            Err(error) => output += &format!("error: {}\n", error),
+        }
+        Reply::Print(output)
+    }
+
+    fn start(&mut self, name: Option<&str>) -> Result<String, String> {
+        let grammar = self.grammar()?;
+        match name {
+            Some(name) => {
+                start_rule(&grammar, Some(name))?;
+                self.options.start = Some(name.to_string());
+                Ok(String::new())
+            }
+            None => Ok(format!(
+                "{}\n",
+                start_rule(&grammar, self.options.start.as_deref())?.name
+            )),
+        }
+    }
+
+    fn rules(&self) -> Result<String, String> {
+        Ok(self.grammar()?.to_peg())
+    }
+
+    fn format(&mut self, format: &str) -> Result<String, String> {
+        self.options.format = match format {
+            "ascii" => Format::Ascii,
+            "json" => Format::Json,
+            "sexpr" => Format::Sexpr,
+            "svg" => Format::Svg,
+            other => return Err(format!("unknown format {}", other)),
+        };
+        Ok(String::new())
+    }
+
+    fn parse(&self, input: &str) -> Result<String, String> {
+        let grammar = self.grammar()?;
+        // The start rule may have been removed from the file since it was chosen.
+        let rule = start_rule(&grammar, self.options.start.as_deref())?;
+        let options = &self.options;
+        let filter = make_filter(&grammar, options.named, &options.keep, &options.hide)?;
+
+        let mut output = String::new();
+        if self.trace {
+            output += &Trace::record(&rule, input).1.to_text();
+        }
+        match rule.parse_all(input) {
+            Ok(tree) => output += &render(&apply_filter(tree, filter.as_ref()), options.format)?,
+            Err(error) => output += &error.render(input, "<input>"),
+        }
+        Ok(output)
+    }
+}
+
+/// Reads lines from the standard input until it ends or `:quit` is typed.
+pub fn run(options: Options) -> Result<(), String> {
+    let mut session = Session::new(options)?;
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("> ");
+        let _ = std::io::stdout().flush();
+        let line = match lines.next() {
+            Some(line) => line.map_err(|error| format!("cannot read standard input: {}", error))?,
+            None => {
+                println!();
+                return Ok(());
+            }
+        };
+        match session.handle(&line) {
+            Reply::Print(text) => print!("{}", text),
+            Reply::Quit => return Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn session(name: &str, text: &str) -> (Session, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("yaast-repl-{}.peg", name));
+        std::fs::write(&path, text).unwrap();
+        let options = Options {
+            grammar: path.to_str().unwrap().to_string(),
+            inputs: Vec::new(),
+            start: None,
+            format: Format::Sexpr,
+            named: true,
+            keep: Vec::new(),
+            hide: Vec::new(),
+        };
+        (Session::new(options).unwrap(), path)
+    }
+
+    fn print(text: &str) -> Reply {
+        Reply::Print(text.to_string())
+    }
+
+    #[test]
+    fn commands() {
+        let (mut session, path) =
+            session("commands", "Pair <- Letter Letter ;\nLetter <- 'a' / 'b' ;");
+        assert_eq!(
+            session.handle("ab"),
+            print("(Pair (Letter \"a\") (Letter \"b\"))\n")
+        );
+        assert_eq!(session.handle(":start"), print("Pair\n"));
+        assert_eq!(session.handle(":start Letter"), print(""));
+        assert_eq!(session.handle("b"), print("(Letter \"b\")\n"));
+        assert_eq!(
+            session.handle("c"),
+            print("error: expected Letter at 1:1\n  --> <input>\n  |\n1 | c\n  | ^\n")
+        );
+        assert_eq!(
+            session.handle(":start Nothing"),
+            print("error: the grammar has no rule Nothing\n")
+        );
+        assert_eq!(
+            session.handle(":format xml"),
+            print("error: unknown format xml\n")
+        );
+        assert_eq!(session.handle(":trace"), print("trace on\n"));
+        assert!(
+            matches!(session.handle("a"), Reply::Print(text) if text.contains("Letter at 0: matched 1"))
+        );
+        assert_eq!(session.handle(":quit"), Reply::Quit);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn reloads_changed_grammar() {
+        let (mut session, path) = session("reload", "Letter <- 'a' ;");
+        assert_eq!(session.handle(":rules"), print("Letter <- 'a' ;\n"));
+
+        std::fs::write(&path, "Letter <- 'b' ;").unwrap();
+        // Pretend the file was read before it changed, the clock may be too coarse to tell.
+        session.modified = Some(SystemTime::UNIX_EPOCH);
+        let reloaded = format!("reloaded {}\n", path.display());
+        assert_eq!(session.handle("b"), print(&(reloaded + "(Letter \"b\")\n")));
+
+        std::fs::write(&path, "Letter <- 'c'").unwrap();
+        session.modified = Some(SystemTime::UNIX_EPOCH);
+        assert!(matches!(session.handle("b"), Reply::Print(text)
+            if text.starts_with("error: ") && text.ends_with("(Letter \"b\")\n")));
+        // The broken file is only reported once.
+        assert_eq!(session.handle("b"), print("(Letter \"b\")\n"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,451 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::rule::{Rule, RuleKind};
+use crate::Grammar;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// The grammar works, but probably not the way it was meant to.
+    Warning,
+    /// Parsing with the grammar panics or never ends.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LintKind {
+    /// Alternative `index` of a `Sor` never matches, because alternative `shadowed_by`
+    /// matches whenever it would.
+    UnreachableAlternative { index: usize, shadowed_by: usize },
+    /// The rule can apply itself again without consuming any input.
+    /// `cycle` holds the names of the rules involved, starting and ending with this one.
+    LeftRecursion { cycle: Vec<String> },
+    /// A `Star` or `Plus` whose rule can match without consuming input, so it repeats forever.
+    NullableRepetition,
+    /// An `UninitializedRule` that was never initialized.
+    Uninitialized,
+}
+
+/// A problem found by `Grammar::check`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lint {
+    pub severity: Severity,
+    pub kind: LintKind,
+    /// Name of the rule definition the problem is in.
+    pub rule: String,
+    /// The offending expression in PEG notation.
+    pub expression: String,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.severity {
+            Severity::Warning => write!(f, "warning")?,
+            Severity::Error => write!(f, "error")?,
+        }
+        write!(f, " in {}: ", self.rule)?;
+        match &self.kind {
+            LintKind::UnreachableAlternative { index, shadowed_by } => write!(
+                f,
+                "alternative {} of `{}` is unreachable, alternative {} matches first",
+                index + 1,
+                self.expression,
+                shadowed_by + 1
+            ),
+            LintKind::LeftRecursion { cycle } => {
+                write!(f, "left recursion {}", cycle.join(" -> "))
+            }
+            LintKind::NullableRepetition => write!(
+                f,
+                "`{}` repeats a rule that can match nothing",
+                self.expression
+            ),
+            LintKind::Uninitialized => write!(f, "rule was never initialized"),
+        }
+    }
+}
+
+impl<'a> Grammar<'a> {
+    /// Looks for unreachable `Sor` alternatives, left recursion, nullable repetitions
+    /// and uninitialized rules.
+    /// Rules that do not describe themselves are assumed to consume input.
+    pub fn check(&self) -> Vec<Lint> {
+        let nullable = self.nullable_rules();
+        let mut lints = Vec::new();
+        let mut recursive = HashSet::new();
+        for rule in &self.rules {
+            if rule.kind().is_none() {
+                lints.push(Lint {
+                    severity: Severity::Error,
+                    kind: LintKind::Uninitialized,
+                    rule: rule.name.to_string(),
+                    expression: rule.name.to_string(),
+                });
+                continue;
+            }
+            if !recursive.contains(&rule.instance_id()) {
+                if let Some(cycle) = self.left_recursion(rule, &nullable) {
+                    recursive.extend(cycle.iter().map(|rule| rule.instance_id()));
+                    lints.push(Lint {
+                        severity: Severity::Error,
+                        kind: LintKind::LeftRecursion {
+                            cycle: cycle.iter().map(|rule| rule.name.to_string()).collect(),
+                        },
+                        rule: rule.name.to_string(),
+                        expression: rule.name.to_string(),
+                    });
+                }
+            }
+            let body = match rule.kind() {
+                Some(RuleKind::Custom(inner)) => inner,
+                _ => rule,
+            };
+            check_body(&rule.name, body, &nullable, &mut lints);
+        }
+        lints
+    }
+
+    /// The instance ids of all rules that can match without consuming input.
+    pub fn nullable_rules(&self) -> HashSet<usize> {
+        let mut rules = Vec::new();
+        let mut seen = HashSet::new();
+        collect_all(&self.start, &mut seen, &mut rules);
+        let mut nullable = HashSet::new();
+        loop {
+            let mut changed = false;
+            for rule in &rules {
+                if !nullable.contains(&rule.instance_id()) && is_nullable(rule, &nullable) {
+                    nullable.insert(rule.instance_id());
+                    changed = true;
+                }
+            }
+            if !changed {
+                return nullable;
+            }
+        }
+    }
+
+    /// Finds a path of named rules from `rule` back to itself along which no input is consumed.
+    fn left_recursion(&self, rule: &Rule<'a>, nullable: &HashSet<usize>) -> Option<Vec<Rule<'a>>> {
+        let mut path = vec![rule.clone()];
+        let mut visited = HashSet::new();
+        if find_cycle(rule, rule.instance_id(), nullable, &mut path, &mut visited) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn collect_all<'a>(
+    rule: &Rule<'a>,
+    seen: &mut HashSet<usize>,
+    rules: &mut Vec<Rule<'a>>,
+) {
+    if !seen.insert(rule.instance_id()) {
+        return;
+    }
+    rules.push(rule.clone());
+    if let Some(kind) = rule.kind() {
+        for child in kind.children() {
+            collect_all(child, seen, rules);
+        }
+    }
+}
+
+fn is_nullable(rule: &Rule, nullable: &HashSet<usize>) -> bool {
+    let known = |rule: &Rule| nullable.contains(&rule.instance_id());
+    match rule.kind() {
+        Some(RuleKind::Str(s)) => s.is_empty(),
+        Some(RuleKind::Literals(literals)) => literals.literals.iter().any(String::is_empty),
+        Some(RuleKind::Eof) | Some(RuleKind::Cut) => true,
+        Some(RuleKind::Star(_)) | Some(RuleKind::Opt(_)) => true,
+        Some(RuleKind::Seq(rules)) => rules.iter().all(known),
+        Some(RuleKind::Sor(rules)) => rules.iter().any(known),
+        Some(RuleKind::Plus(inner))
+        | Some(RuleKind::Must(inner))
+        | Some(RuleKind::Recover(inner, _))
+        | Some(RuleKind::Custom(inner)) => known(inner),
+        Some(RuleKind::Char(_)) | Some(RuleKind::Opaque) | None => false,
+    }
+}
+
+/// The rules that can be applied at the same position as `rule` itself.
+fn leading<'r, 'a>(kind: RuleKind<'r, 'a>, nullable: &HashSet<usize>) -> Vec<&'r Rule<'a>> {
+    match kind {
+        RuleKind::Seq(rules) => {
+            let mut leading = Vec::new();
+            for rule in rules {
+                leading.push(rule);
+                if !nullable.contains(&rule.instance_id()) {
+                    break;
+                }
+            }
+            leading
+        }
+        RuleKind::Recover(inner, _) => vec![inner],
+        kind => kind.children(),
+    }
+}
+
+fn find_cycle<'a>(
+    rule: &Rule<'a>,
+    target: usize,
+    nullable: &HashSet<usize>,
+    path: &mut Vec<Rule<'a>>,
+    visited: &mut HashSet<usize>,
+) -> bool {
+    let kind = match rule.kind() {
+        Some(kind) => kind,
+        None => return false,
+    };
+    for child in leading(kind, nullable) {
+        if child.instance_id() == target {
+            path.push(child.clone());
+            return true;
+        }
+        if !visited.insert(child.instance_id()) {
+            continue;
+        }
+        let named = Grammar::is_named(child);
+        if named {
+            path.push(child.clone());
+        }
+        if find_cycle(child, target, nullable, path, visited) {
+            return true;
+        }
+        if named {
+            path.pop();
+        }
+    }
+    false
+}
+
+/// The literal every match of the rule starts with, as far as it is known.
+fn literal_prefix(rule: &Rule) -> String {
+    match rule.kind() {
+        Some(RuleKind::Char(c)) => c.to_string(),
+        Some(RuleKind::Str(s)) => s.to_string(),
+        Some(RuleKind::Custom(inner)) | Some(RuleKind::Must(inner)) => literal_prefix(inner),
+        Some(RuleKind::Seq(rules)) => {
+            let mut prefix = String::new();
+            for rule in rules {
+                match literal(rule) {
+                    Some(literal) => prefix += &literal,
+                    None => {
+                        prefix += &literal_prefix(rule);
+                        break;
+                    }
+                }
+            }
+            prefix
+        }
+        _ => String::new(),
+    }
+}
+
+/// The text the rule matches if it only ever matches that text.
+fn literal(rule: &Rule) -> Option<String> {
+    match rule.kind() {
+        Some(RuleKind::Char(c)) => Some(c.to_string()),
+        Some(RuleKind::Str(s)) => Some(s.to_string()),
+        Some(RuleKind::Custom(inner)) | Some(RuleKind::Must(inner)) => literal(inner),
+        Some(RuleKind::Seq(rules)) => rules.iter().map(literal).collect(),
+        _ => None,
+    }
+}
+
+fn check_body(definition: &str, rule: &Rule, nullable: &HashSet<usize>, lints: &mut Vec<Lint>) {
+    let kind = match rule.kind() {
+        Some(kind) => kind,
+        None => return,
+    };
+    match kind {
+        RuleKind::Sor(options) => {
+            let mut shadowed = HashMap::new();
+            for (i, option) in options.iter().enumerate() {
+                if nullable.contains(&option.instance_id()) {
+                    for j in i + 1..options.len() {
+                        shadowed.entry(j).or_insert(i);
+                    }
+                } else if let Some(literal) = literal(option) {
+                    for (j, later) in options.iter().enumerate().skip(i + 1) {
+                        if literal_prefix(later).starts_with(&literal) {
+                            shadowed.entry(j).or_insert(i);
+                        }
+                    }
+                }
+            }
+            let mut shadowed: Vec<_> = shadowed.into_iter().collect();
+            shadowed.sort();
+            for (index, shadowed_by) in shadowed {
+                lints.push(Lint {
+                    severity: Severity::Warning,
+                    kind: LintKind::UnreachableAlternative { index, shadowed_by },
+                    rule: definition.to_string(),
+                    expression: Grammar::expression(rule),
+                });
+            }
+        }
+        // Without taking the longest, a literal starting with an earlier one never matches.
+        RuleKind::Literals(literals) if !literals.longest => {
+            let fold = |literal: &String| {
+                if literals.ignore_case {
+                    literal.to_lowercase()
+                } else {
+                    literal.clone()
+                }
+            };
+            let folded: Vec<String> = literals.literals.iter().map(fold).collect();
+            for (index, literal) in folded.iter().enumerate() {
+                if let Some(shadowed_by) = folded[..index]
+                    .iter()
+                    .position(|earlier| literal.starts_with(earlier.as_str()))
+                {
+                    lints.push(Lint {
+                        severity: Severity::Warning,
+                        kind: LintKind::UnreachableAlternative { index, shadowed_by },
+                        rule: definition.to_string(),
+                        expression: Grammar::expression(rule),
+                    });
+                }
+            }
+        }
+        RuleKind::Star(inner) | RuleKind::Plus(inner)
+            if nullable.contains(&inner.instance_id()) =>
+        {
+            lints.push(Lint {
+                severity: Severity::Error,
+                kind: LintKind::NullableRepetition,
+                rule: definition.to_string(),
+                expression: Grammar::expression(rule),
+            });
+        }
+        _ => {}
+    }
+    for child in kind.children() {
+        if !Grammar::is_named(child) {
+            check_body(definition, child, nullable, lints);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use check::{LintKind, Severity};
+
+    #[test]
+    fn check_accepts_valid_grammar() {
+        let value = custom!("Value");
+        let list = seq!("List" => char!('['), opt!(seq!(value.get(), star!(seq!(char!(','), value.get())))), char!(']'));
+        let value = value.init(sor!(str!("null"), str!("nul"), plus!(char!('1')), list));
+        let grammar = Grammar::new(value);
+        assert_eq!(grammar.check(), vec![]);
+    }
+
+    #[test]
+    fn check_finds_unreachable_alternatives() {
+        let rule = sor!(
+            "Keyword" => str!("a"),
+            str!("ab"),
+            seq!(char!('a'), char!('c')),
+            opt!(char!('x')),
+            char!('y'),
+            str!("b")
+        );
+        let grammar = Grammar::new(rule);
+        let lints = grammar.check();
+        let kinds: Vec<_> = lints.iter().map(|lint| lint.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                LintKind::UnreachableAlternative {
+                    index: 1,
+                    shadowed_by: 0
+                },
+                LintKind::UnreachableAlternative {
+                    index: 2,
+                    shadowed_by: 0
+                },
+                LintKind::UnreachableAlternative {
+                    index: 4,
+                    shadowed_by: 3
+                },
+                LintKind::UnreachableAlternative {
+                    index: 5,
+                    shadowed_by: 3
+                },
+            ]
+        );
+        assert_eq!(lints[0].severity, Severity::Warning);
+        assert_eq!(lints[0].rule, "Keyword");
+        assert_eq!(
+            lints[0].to_string(),
+            "warning in Keyword: alternative 2 of `\"a\" / \"ab\" / 'a' 'c' / 'x'? / 'y' / \"b\"` \
+            is unreachable, alternative 1 matches first"
+        );
+    }
+
+    #[test]
+    fn check_finds_unreachable_literals() {
+        let grammar = Grammar::new(literals!("Keyword" => ignore_case; "in", "if", "INT"));
+        let lints = grammar.check();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(
+            lints[0].to_string(),
+            "warning in Keyword: alternative 3 of `literals(ignore_case, \"in\", \"if\", \"INT\")` \
+            is unreachable, alternative 1 matches first"
+        );
+        let grammar = Grammar::new(keywords!("Keyword" => "in", "if", "int"));
+        assert_eq!(grammar.check(), vec![]);
+    }
+
+    #[test]
+    fn check_finds_left_recursion() {
+        let expr = custom!("Expr");
+        let term = custom!("Term");
+        let term = term.init(sor!(seq!(opt!(char!('-')), expr.get()), char!('1')));
+        let expr = expr.init(seq!(term, star!(seq!(char!('+'), char!('1')))));
+        let grammar = Grammar::new(seq!("Start" => expr, eof!()));
+        let lints = grammar.check();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].severity, Severity::Error);
+        assert_eq!(
+            lints[0].kind,
+            LintKind::LeftRecursion {
+                cycle: vec!["Expr".to_string(), "Term".to_string(), "Expr".to_string()]
+            }
+        );
+        assert_eq!(
+            lints[0].to_string(),
+            "error in Expr: left recursion Expr -> Term -> Expr"
+        );
+    }
+
+    #[test]
+    fn check_finds_nullable_repetition() {
+        let rule = seq!("List" => star!(opt!(char!('a'))), plus!(seq!(star!(char!('b')), eof!())));
+        let grammar = Grammar::new(rule);
+        let lints = grammar.check();
+        let expressions: Vec<_> = lints.iter().map(|lint| lint.expression.as_str()).collect();
+        assert_eq!(expressions, vec!["'a'?*", "('b'* EOF)+"]);
+        assert!(lints
+            .iter()
+            .all(|lint| lint.kind == LintKind::NullableRepetition));
+    }
+
+    #[test]
+    fn check_finds_uninitialized_rules() {
+        let missing = custom!("Missing");
+        let grammar = Grammar::new(seq!("Start" => char!('a'), missing.get()));
+        let lints = grammar.check();
+        assert_eq!(lints.len(), 1);
+        assert_eq!(lints[0].kind, LintKind::Uninitialized);
+        assert_eq!(lints[0].rule, "Missing");
+        assert_eq!(
+            lints[0].to_string(),
+            "error in Missing: rule was never initialized"
+        );
+    }
+}
@@ -0,0 +1,503 @@
+//! Generates the Rust source of a parser for a grammar, with a function per rule
+//! calling the functions of its sub-rules directly.
+//!
+//! The generated parser builds the same trees and errors as the rules themselves.
+//! Unnamed rules like `Seq` get the same ids, named rules get ids of their own, as every
+//! instance of a grammar does. For a build script:
+//!
+//! ```text
+//! fn main() {
+//!     let out = std::env::var("OUT_DIR").unwrap();
+//!     yaast::codegen::compile_peg("src/list.peg", &format!("{}/list.rs", out)).unwrap();
+//! }
+//! ```
+//!
+//! and in the crate, `mod list { include!(concat!(env!("OUT_DIR"), "/list.rs")); }`.
+//! The module then has a `parse` function for the start rule, a `parse_` function for
+//! every named rule, like `parse_list_item` for `ListItem`, and `id` to look up the ids.
+
+use std::collections::HashMap;
+use std::fmt::{self, Write};
+use std::io;
+
+use crate::peg::PegError;
+use crate::rule::{unnamed_ids, Rule, RuleKind, CUT_ID};
+use crate::Grammar;
+
+#[derive(Debug)]
+pub enum CodegenError {
+    Peg(PegError),
+    /// A rule that does not describe itself, so there is nothing to generate it from.
+    Opaque(String),
+    Uninitialized(String),
+    /// Two rules whose names give the same function name.
+    Clash(String, String),
+    Io(io::Error),
+}
+
+impl fmt::Display for CodegenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodegenError::Peg(error) => write!(f, "invalid grammar: {}", error),
+            CodegenError::Opaque(name) => {
+                write!(
+                    f,
+                    "rule {} does not describe itself, cannot generate it",
+                    name
+                )
+            }
+            CodegenError::Uninitialized(name) => write!(f, "rule {} was never initialized", name),
+            CodegenError::Clash(first, second) => {
+                write!(
+                    f,
+                    "rules {} and {} would get the same function",
+                    first, second
+                )
+            }
+            CodegenError::Io(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for CodegenError {}
+
+impl From<io::Error> for CodegenError {
+    fn from(error: io::Error) -> Self {
+        CodegenError::Io(error)
+    }
+}
+
+impl From<PegError> for CodegenError {
+    fn from(error: PegError) -> Self {
+        CodegenError::Peg(error)
+    }
+}
+
+/// Reads a grammar in PEG notation and writes the parser for it, for build scripts.
+/// Tells cargo to run the build script again when the grammar changes.
+pub fn compile_peg(grammar: &str, output: &str) -> Result<(), CodegenError> {
+    println!("cargo:rerun-if-changed={}", grammar);
+    let text = std::fs::read_to_string(grammar)?;
+    let source = Grammar::from_peg(&text)?.to_rust()?;
+    std::fs::write(output, source)?;
+    Ok(())
+}
+
+/// The name of the static for the id of unnamed rules, `None` for named ones.
+fn static_id(id: usize) -> Option<&'static str> {
+    unnamed_ids()
+        .iter()
+        .find(|(static_id, _, _)| *static_id == id)
+        .map(|(_, _, name)| *name)
+}
+
+/// `ListItem` and `list_item` become `list_item`, the names generated functions and
+/// methods get for rules.
+pub fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 && chars[i - 1] != '_' {
+            let after_lower = !chars[i - 1].is_uppercase();
+            let before_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if after_lower || before_lower {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+/// Numbers the rules and the ids of named rules while writing the functions.
+struct Writer<'a> {
+    /// Function number of each rule instance.
+    functions: HashMap<usize, usize>,
+    /// Rules in the order of their function numbers.
+    queue: Vec<Rule<'a>>,
+    /// Ids of named rules, indices into `IDS` of the generated code.
+    named: Vec<usize>,
+    /// How many rules of the queue have their function written.
+    written: usize,
+}
+
+impl<'a> Writer<'a> {
+    /// The function for the rule, numbering it if it is new.
+    fn function(&mut self, rule: &Rule<'a>) -> String {
+        let count = self.functions.len();
+        let number = *self.functions.entry(rule.instance_id()).or_insert_with(|| {
+            self.queue.push(rule.clone());
+            count
+        });
+        format!("r{}", number)
+    }
+
+    /// The expression for the id of the rule.
+    fn id(&mut self, rule: &Rule<'a>) -> String {
+        if let Some(name) = static_id(rule.id) {
+            return format!("*::yaast::rule::{}", name);
+        }
+        let index = match self.named.iter().position(|id| *id == rule.id) {
+            Some(index) => index,
+            None => {
+                self.named.push(rule.id);
+                self.named.len() - 1
+            }
+        };
+        format!("ids()[{}]", index)
+    }
+
+    /// The body of the function parsing `input` with the rule.
+    fn body(&mut self, rule: &Rule<'a>) -> Result<String, CodegenError> {
+        let id = self.id(rule);
+        let name = format!("{:?}", rule.name);
+        let mut body = String::new();
+        let kind = match rule.kind() {
+            Some(kind) => kind,
+            None => return Err(CodegenError::Uninitialized(rule.name.to_string())),
+        };
+        let _ = match kind {
+            RuleKind::Char(c) => write!(
+                body,
+                "    if input.starts_with({c:?}) {{\n        Ok(::yaast::Node::new(&input[..{len}], {id}, {name}))\n    }} else {{\n        Err(backtrack(input, {name}, false))\n    }}\n",
+                len = c.len_utf8()
+            ),
+            RuleKind::Str(s) => write!(
+                body,
+                "    if input.starts_with({s:?}) {{\n        Ok(::yaast::Node::new(&input[..{len}], {id}, {name}))\n    }} else {{\n        Err(backtrack(input, {name}, false))\n    }}\n",
+                len = s.len()
+            ),
+            RuleKind::Eof => write!(
+                body,
+                "    if input.is_empty() {{\n        Ok(::yaast::Node::new_empty({id}, {name}))\n    }} else {{\n        Err(backtrack(input, {name}, false))\n    }}\n"
+            ),
+            RuleKind::Cut => writeln!(body, "    Ok(::yaast::Node::new(&input[..0], {id}, {name}))"),
+            RuleKind::Seq(rules) => {
+                let _ = writeln!(body, "    let mut node = ::yaast::Node::new_empty({id}, {name});");
+                let _ = writeln!(body, "    let mut size = 0;");
+                let mut committed = false;
+                for child in rules {
+                    let function = self.function(child);
+                    let _ = if committed {
+                        writeln!(
+                            body,
+                            "    let child = {function}(&input[size..])\n        .map_err(|failure| commit(failure, &input[size..], {}, {:?}))?;",
+                            self.id(child),
+                            child.name
+                        )
+                    } else {
+                        writeln!(body, "    let child = {function}(&input[size..])?;")
+                    };
+                    let _ = write!(
+                        body,
+                        "    size += child.content.len();\n    node.add_child(child);\n"
+                    );
+                    committed |= child.id == *CUT_ID;
+                }
+                write!(body, "    node.content = &input[..size];\n    Ok(node)\n")
+            }
+            RuleKind::Sor(rules) => {
+                for child in rules {
+                    let function = self.function(child);
+                    let _ = write!(
+                        body,
+                        "    match {function}(input) {{\n        Ok(child) => {{\n            let mut node = ::yaast::Node::new(child.content, {id}, {name});\n            node.add_child(child);\n            return Ok(node);\n        }}\n        Err(::yaast::Failure::Backtrack) => {{}}\n        Err(failure) => return Err(failure),\n    }}\n"
+                    );
+                }
+                writeln!(body, "    Err(::yaast::Failure::Backtrack)")
+            }
+            RuleKind::Star(child) | RuleKind::Plus(child) => {
+                let function = self.function(child);
+                let _ = writeln!(body, "    let mut node = ::yaast::Node::new_empty({id}, {name});");
+                if matches!(kind, RuleKind::Plus(_)) {
+                    let _ = write!(
+                        body,
+                        "    let child = {function}(input)?;\n    let mut size = child.content.len();\n    node.add_child(child);\n"
+                    );
+                } else {
+                    let _ = writeln!(body, "    let mut size = 0;");
+                }
+                write!(
+                    body,
+                    "    loop {{\n        match {function}(&input[size..]) {{\n            Ok(child) => {{\n                size += child.content.len();\n                node.add_child(child);\n            }}\n            Err(::yaast::Failure::Backtrack) => break,\n            Err(failure) => return Err(failure),\n        }}\n    }}\n    node.content = &input[..size];\n    Ok(node)\n"
+                )
+            }
+            RuleKind::Opt(child) => {
+                let function = self.function(child);
+                write!(
+                    body,
+                    "    match {function}(input) {{\n        Err(::yaast::Failure::Backtrack) => Ok(::yaast::Node::new_empty({id}, {name})),\n        result => result,\n    }}\n"
+                )
+            }
+            RuleKind::Must(child) => {
+                let function = self.function(child);
+                let child_id = self.id(child);
+                writeln!(
+                    body,
+                    "    {function}(input).map_err(|failure| commit(failure, input, {child_id}, {:?}))",
+                    child.name
+                )
+            }
+            RuleKind::Recover(child, sync) => {
+                let function = self.function(child);
+                let sync = self.function(sync);
+                let child_id = self.id(child);
+                write!(
+                    body,
+                    "    {function}(input).or_else(|failure| {{\n        ::yaast::rule::recover_from(input, failure, {child_id}, &::yaast::Name::Static({:?}), |rest| sync({sync}, rest))\n    }})\n",
+                    child.name
+                )
+            }
+            RuleKind::Custom(child) => {
+                let function = self.function(child);
+                write!(
+                    body,
+                    "    let child = match {function}(input) {{\n        Ok(child) => child,\n        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, {name}, true)),\n        Err(failure) => return Err(failure),\n    }};\n    let mut node = ::yaast::Node::new(child.content, {id}, {name});\n    node.add_child(child);\n    Ok(node)\n"
+                )
+            }
+            RuleKind::Literals(literals) => {
+                let list: Vec<String> = literals
+                    .literals
+                    .iter()
+                    .map(|literal| format!("{:?}.to_string()", literal))
+                    .collect();
+                write!(
+                    body,
+                    "    static LITERALS: ::std::sync::OnceLock<::yaast::rule::Literals> = ::std::sync::OnceLock::new();\n    let literals = LITERALS.get_or_init(|| {{\n        ::yaast::rule::Literals::new(vec![{}], {}, {})\n    }});\n    match literals.find(input) {{\n        Some((len, _)) => {{\n            let mut node = ::yaast::Node::new(&input[..len], {id}, {name});\n            node.add_child(::yaast::Node::new(&input[..len], *::yaast::rule::STR_ID, \"Str\"));\n            Ok(node)\n        }}\n        None => Err(backtrack(input, {name}, false)),\n    }}\n",
+                    list.join(", "),
+                    literals.longest,
+                    literals.ignore_case
+                )
+            }
+            RuleKind::Opaque => return Err(CodegenError::Opaque(rule.name.to_string())),
+        };
+        Ok(body)
+    }
+
+    /// Writes the functions of the rules numbered since the last call.
+    fn write_pending(&mut self, result: &mut String) -> Result<(), CodegenError> {
+        while self.written < self.queue.len() {
+            let rule = self.queue[self.written].clone();
+            let body = self.body(&rule)?;
+            let doc = match rule.kind() {
+                Some(RuleKind::Custom(child)) => {
+                    format!("{} <- {}", rule.name, Grammar::expression(child))
+                }
+                _ => Grammar::expression(&rule),
+            };
+            let _ = write!(
+                result,
+                "\n/// `{}`\nfn r{}<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {{\n{}}}\n",
+                doc.replace('`', "'"),
+                self.written,
+                body
+            );
+            self.written += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Grammar<'a> {
+    /// Generates the Rust source of a parser for the grammar, see the `codegen` module.
+    pub fn to_rust(&self) -> Result<String, CodegenError> {
+        let mut writer = Writer {
+            functions: HashMap::new(),
+            queue: Vec::new(),
+            named: Vec::new(),
+            written: 0,
+        };
+        let mut functions = String::new();
+        writer.function(&self.start);
+        writer.write_pending(&mut functions)?;
+
+        let mut parse_functions = String::new();
+        let mut taken: Vec<(String, &str)> = Vec::new();
+        for rule in &self.rules {
+            let function = format!("parse_{}", snake_case(&rule.name));
+            if let Some((_, other)) = taken.iter().find(|(taken, _)| *taken == function) {
+                return Err(CodegenError::Clash(
+                    other.to_string(),
+                    rule.name.to_string(),
+                ));
+            }
+            taken.push((function.clone(), &rule.name));
+            let _ = write!(
+                parse_functions,
+                "\n/// Parses with `{name}`, like `Rule::try_parse`.\npub fn {function}(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {{\n    run(input, {}, {name:?})\n}}\n",
+                writer.function(rule),
+                name = rule.name
+            );
+        }
+        // The functions of rules that are not reachable from the start rule.
+        writer.write_pending(&mut functions)?;
+
+        let names: Vec<String> = writer
+            .named
+            .iter()
+            .map(|id| {
+                let rule = self.rules.iter().find(|rule| rule.id == *id);
+                format!("{:?}", rule.map_or("", |rule| &rule.name))
+            })
+            .collect();
+        let count = names.len();
+        let mut result = format!(
+            "// Parser for the grammar starting with {start}, generated by yaast::codegen.\n\
+             // Do not edit, change the grammar instead.\n\
+             \n\
+             /// Names of the named rules, in the order of their ids in `ids`.\n\
+             pub const NAMES: [&str; {count}] = [{names}];\n\
+             \n\
+             /// Ids of the named rules, assigned on first use.\n\
+             pub fn ids() -> &'static [usize; {count}] {{\n    \
+                 static IDS: ::std::sync::OnceLock<[usize; {count}]> = ::std::sync::OnceLock::new();\n    \
+                 IDS.get_or_init(|| {{\n        \
+                     ::std::array::from_fn(|_| {{\n            \
+                         ::yaast::COUNTER.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst)\n        \
+                     }})\n    \
+                 }})\n\
+             }}\n\
+             \n\
+             /// The id of the named rule.\n\
+             pub fn id(name: &str) -> Option<usize> {{\n    \
+                 NAMES.iter().position(|other| *other == name).map(|index| ids()[index])\n\
+             }}\n\
+             \n\
+             /// Parses with `{start}`, the start rule.\n\
+             pub fn parse(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {{\n    \
+                 run(input, r0, {start:?})\n\
+             }}\n",
+            start = self.start.name,
+            names = names.join(", "),
+        );
+        result += &parse_functions;
+        result +=
+            "\n/// Turns backtracking into a failure that stops at the cut before the rule.\n\
+                   #[allow(dead_code)]\n\
+                   fn commit<'a>(\n    \
+                       failure: ::yaast::Failure<'a>,\n    \
+                       rest: &'a str,\n    \
+                       id: usize,\n    \
+                       name: &'static str,\n\
+                   ) -> ::yaast::Failure<'a> {\n    \
+                       match failure {\n        \
+                           ::yaast::Failure::Backtrack => ::yaast::Failure::Cut {\n            \
+                               rest,\n            \
+                               id,\n            \
+                               name: ::yaast::Name::Static(name),\n        \
+                           },\n        \
+                           failure => failure,\n    \
+                       }\n\
+                   }\n\
+                   \n\
+                   thread_local! {\n    \
+                       /// Length of the input being parsed and where the parse got furthest, see\n    \
+                       /// `::yaast::Furthest`.\n    \
+                       static FURTHEST: ::std::cell::RefCell<(usize, Option<::yaast::Furthest>)> =\n        \
+                           const { ::std::cell::RefCell::new((0, None)) };\n\
+                   }\n\
+                   \n\
+                   /// Parses the input with the rule, named `name`, for the public functions.\n\
+                   fn run<'a>(\n    \
+                       input: &'a str,\n    \
+                       rule: fn(&'a str) -> ::yaast::ParseResult<'a>,\n    \
+                       name: &str,\n\
+                   ) -> Result<::yaast::Node<'a>, ::yaast::ParseError> {\n    \
+                       FURTHEST.with(|furthest| *furthest.borrow_mut() = (input.len(), None));\n    \
+                       rule(input).map_err(|failure| {\n        \
+                           failure.into_error(input, name, FURTHEST.with(|furthest| furthest.take().1))\n    \
+                       })\n\
+                   }\n\
+                   \n\
+                   /// Records that the rule named `name` backtracked on `rest`.\n\
+                   #[allow(dead_code)]\n\
+                   fn backtrack(rest: &str, name: &'static str, named: bool) -> ::yaast::Failure<'static> {\n    \
+                       FURTHEST.with(|cell| {\n        \
+                           let (len, furthest) = &mut *cell.borrow_mut();\n        \
+                           let name = ::yaast::Name::Static(name);\n        \
+                           ::yaast::Furthest::record(furthest, *len - rest.len(), &name, named);\n    \
+                       });\n    \
+                       ::yaast::Failure::Backtrack\n\
+                   }\n\
+                   \n\
+                   /// Parses with the sync rule of a `recover!`, which does not count for errors.\n\
+                   #[allow(dead_code)]\n\
+                   fn sync<'a>(\n    \
+                       rule: fn(&'a str) -> ::yaast::ParseResult<'a>,\n    \
+                       rest: &'a str,\n\
+                   ) -> ::yaast::ParseResult<'a> {\n    \
+                       let furthest = FURTHEST.with(|furthest| furthest.borrow().clone());\n    \
+                       let result = rule(rest);\n    \
+                       FURTHEST.with(|cell| *cell.borrow_mut() = furthest);\n    \
+                       result\n\
+                   }\n";
+        result += &functions;
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Sentences;
+    use crate::test_support::{inputs, shape};
+
+    #[allow(dead_code)]
+    mod statements {
+        include!("../testdata/statements.rs");
+    }
+
+    const STATEMENTS: &str = include_str!("../testdata/statements.peg");
+
+    #[test]
+    fn generated_code_is_up_to_date() {
+        let generated = Grammar::from_peg(STATEMENTS).unwrap().to_rust().unwrap();
+        if std::env::var("YAAST_BLESS").is_ok() {
+            std::fs::write("testdata/statements.rs", &generated).unwrap();
+        }
+        assert!(
+            generated == include_str!("../testdata/statements.rs"),
+            "testdata/statements.rs is outdated, run the tests with YAAST_BLESS=1 to update it"
+        );
+    }
+
+    #[test]
+    fn same_trees_as_the_rules() {
+        let sentences = Sentences::new();
+        let grammar = Grammar::from_peg(STATEMENTS).unwrap();
+        let fixed = ["", "a = [1, 2];", "a = [1, ;  b = 2;", "a = b"];
+        for input in inputs(&grammar.start, &sentences, &fixed, 200) {
+            let interpreted = grammar.start.try_parse(input);
+            let generated = statements::parse(input);
+            match (interpreted, generated) {
+                (Ok(interpreted), Ok(generated)) => {
+                    assert_eq!(shape(&interpreted), shape(&generated), "{:?}", input)
+                }
+                (interpreted, generated) => {
+                    assert_eq!(interpreted.err(), generated.err(), "{:?}", input)
+                }
+            }
+        }
+
+        let tree = statements::parse_number("12").unwrap();
+        assert_eq!(tree.type_id, statements::id("Number").unwrap());
+        assert_eq!(statements::id("Nothing"), None);
+    }
+
+    #[test]
+    fn errors() {
+        let grammar = Grammar::from_peg("Word <- Letter+ ; Letter <- 'a' ;").unwrap();
+        let source = grammar.to_rust().unwrap();
+        assert!(source.contains("pub fn parse_word(input: &str)"));
+        assert!(source.contains("pub fn parse_letter(input: &str)"));
+
+        let grammar = Grammar::from_peg("Word <- word ; word <- 'a' ;").unwrap();
+        assert_eq!(
+            grammar.to_rust().err().unwrap().to_string(),
+            "rules Word and word would get the same function"
+        );
+        assert_eq!(snake_case("ListItem"), "list_item");
+        assert_eq!(snake_case("HTTPHeader"), "http_header");
+        assert_eq!(snake_case("key_value"), "key_value");
+    }
+}
@@ -0,0 +1,375 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::rule::{Rule, RuleKind};
+use crate::rule_handler::{Handler, Listener};
+use crate::{Annotation, Failure, Grammar, Node};
+
+/// How often a rule was applied and matched.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Counts {
+    pub attempts: usize,
+    pub matches: usize,
+    /// Matches of a `Star` that repeated zero times or of an `Opt` without its inner rule.
+    pub empty: usize,
+}
+
+/// Counts how often each rule, each `Sor` alternative, each `Opt` branch and
+/// each `Star` case matched, over any number of parses.
+/// Rules are told apart by `Rule::instance_id`, so the counts of a rule
+/// include every place it is used in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    pub inputs: usize,
+    rules: HashMap<usize, Counts>,
+    /// Matches of a rule directly applied by another one, by their instance ids.
+    children: HashMap<(usize, usize), usize>,
+}
+
+struct Frame {
+    instance: usize,
+    child_matched: bool,
+}
+
+struct Collector {
+    coverage: RefCell<Coverage>,
+    stack: RefCell<Vec<Frame>>,
+}
+
+impl<'a> Listener<'a> for Collector {
+    fn pre_parse(&self, rule: &Rule<'a>, _input: &'a str) {
+        let instance = rule.instance_id();
+        self.coverage
+            .borrow_mut()
+            .rules
+            .entry(instance)
+            .or_default()
+            .attempts += 1;
+        self.stack.borrow_mut().push(Frame {
+            instance,
+            child_matched: false,
+        });
+    }
+
+    fn success(&self, rule: &Rule<'a>, _input: &'a str, node: &Node<'a>) {
+        let mut stack = self.stack.borrow_mut();
+        let frame = match stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let mut coverage = self.coverage.borrow_mut();
+        let counts = coverage.rules.entry(frame.instance).or_default();
+        counts.matches += 1;
+        let empty = match rule.kind() {
+            Some(RuleKind::Star(_)) => node.children.is_empty(),
+            Some(RuleKind::Opt(_)) => !frame.child_matched,
+            _ => false,
+        };
+        if empty {
+            counts.empty += 1;
+        }
+        if let Some(parent) = stack.last_mut() {
+            parent.child_matched = true;
+            *coverage
+                .children
+                .entry((parent.instance, frame.instance))
+                .or_default() += 1;
+        }
+    }
+
+    fn failure(&self, _rule: &Rule<'a>, _input: &'a str, _failure: &Failure<'a>) {
+        self.stack.borrow_mut().pop();
+    }
+}
+
+impl Coverage {
+    pub fn new() -> Coverage {
+        Coverage::default()
+    }
+
+    /// Parses every input with the rule and returns the coverage of all of them.
+    pub fn run<'a>(rule: &Rule<'a>, inputs: &[&'a str]) -> Coverage {
+        let mut coverage = Coverage::new();
+        for input in inputs {
+            coverage.record(rule, input);
+        }
+        coverage
+    }
+
+    /// Parses the input with the rule, adding to the counts.
+    pub fn record<'a>(&mut self, rule: &Rule<'a>, input: &'a str) -> Option<Node<'a>> {
+        let collector = Rc::new(Collector {
+            coverage: RefCell::new(std::mem::take(self)),
+            stack: RefCell::new(Vec::new()),
+        });
+        let mut handler = Handler::new();
+        handler.add_listener(collector.clone());
+        let node = rule.parse_with_handler(input, &handler);
+        drop(handler);
+        *self = collector.coverage.take();
+        self.inputs += 1;
+        node
+    }
+
+    pub fn counts(&self, rule: &Rule) -> Counts {
+        self.rules
+            .get(&rule.instance_id())
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// How often alternative `index` of a `Sor` matched.
+    pub fn alternative(&self, sor: &Rule, index: usize) -> usize {
+        match sor.kind() {
+            Some(RuleKind::Sor(options)) => options
+                .get(index)
+                .map_or(0, |option| self.matches_within(sor, option)),
+            _ => 0,
+        }
+    }
+
+    fn matches_within(&self, parent: &Rule, child: &Rule) -> usize {
+        self.children
+            .get(&(parent.instance_id(), child.instance_id()))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Lists everything in the grammar that never matched.
+    pub fn missed(&self, grammar: &Grammar) -> Vec<String> {
+        let mut missed = Vec::new();
+        for rule in &grammar.rules {
+            if self.counts(rule).matches == 0 {
+                missed.push(format!("{}: never matched", rule.name));
+                continue;
+            }
+            let body = match rule.kind() {
+                Some(RuleKind::Custom(inner)) => inner,
+                _ => rule,
+            };
+            self.missed_in(&rule.name, body, true, &mut missed);
+        }
+        missed
+    }
+
+    fn missed_in(&self, definition: &str, rule: &Rule, top: bool, missed: &mut Vec<String>) {
+        if !top && Grammar::is_named(rule) {
+            return;
+        }
+        let kind = match rule.kind() {
+            Some(kind) => kind,
+            None => return,
+        };
+        let counts = self.counts(rule);
+        let expression = Grammar::expression(rule);
+        if counts.matches == 0 {
+            if !top {
+                missed.push(format!("{}: `{}` never matched", definition, expression));
+            }
+            return;
+        }
+        match kind {
+            RuleKind::Sor(options) => {
+                for (i, option) in options.iter().enumerate() {
+                    if self.matches_within(rule, option) == 0 {
+                        missed.push(format!(
+                            "{}: alternative {} `{}` of `{}` never matched",
+                            definition,
+                            i + 1,
+                            Grammar::expression(option),
+                            expression
+                        ));
+                    } else {
+                        self.missed_in(definition, option, false, missed);
+                    }
+                }
+                return;
+            }
+            RuleKind::Star(_) | RuleKind::Opt(_) => {
+                let (empty, full) = match kind {
+                    RuleKind::Star(_) => ("zero times", "more than zero times"),
+                    _ => ("without its content", "with its content"),
+                };
+                if counts.empty == 0 {
+                    missed.push(format!(
+                        "{}: `{}` never matched {}",
+                        definition, expression, empty
+                    ));
+                }
+                if counts.empty == counts.matches {
+                    missed.push(format!(
+                        "{}: `{}` never matched {}",
+                        definition, expression, full
+                    ));
+                    // Nothing inside was matched either
+                    return;
+                }
+            }
+            _ => {}
+        }
+        for child in kind.children() {
+            self.missed_in(definition, child, false, missed);
+        }
+    }
+
+    /// Renders the counts of all named rules followed by everything that never matched.
+    pub fn to_text(&self, grammar: &Grammar) -> String {
+        let width = grammar
+            .rules
+            .iter()
+            .map(|rule| rule.name.chars().count())
+            .chain(std::iter::once(4))
+            .max()
+            .unwrap_or(4);
+        let mut result = format!("coverage of {} inputs\n", self.inputs);
+        let _ = writeln!(
+            result,
+            "{:<width$}  {:>8}  {:>8}",
+            "rule", "attempts", "matches"
+        );
+        for rule in &grammar.rules {
+            let counts = self.counts(rule);
+            let _ = writeln!(
+                result,
+                "{:<width$}  {:>8}  {:>8}",
+                rule.name, counts.attempts, counts.matches
+            );
+        }
+        let missed = self.missed(grammar);
+        if missed.is_empty() {
+            result += "everything matched\n";
+        } else {
+            result += "never matched:\n";
+            for line in missed {
+                let _ = writeln!(result, "  {}", line);
+            }
+        }
+        result
+    }
+
+    /// Lists the grammar with the match counts of every rule, alternative,
+    /// optional and repetition.
+    pub fn annotated(&self, grammar: &Grammar) -> String {
+        grammar.annotated(&|annotation| match annotation {
+            Annotation::Definition(rule) => {
+                let counts = self.counts(rule);
+                format!("matched {} of {} attempts", counts.matches, counts.attempts)
+            }
+            Annotation::Alternative(sor, i) => format!(" [{}]", self.alternative(sor, i)),
+            Annotation::Repetition(rule) => {
+                let counts = self.counts(rule);
+                match rule.kind() {
+                    Some(RuleKind::Star(_)) => format!(
+                        " [zero {}, more {}]",
+                        counts.empty,
+                        counts.matches - counts.empty
+                    ),
+                    Some(RuleKind::Opt(_)) => format!(
+                        " [absent {}, present {}]",
+                        counts.empty,
+                        counts.matches - counts.empty
+                    ),
+                    _ => format!(" [{}]", counts.matches),
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use coverage::Coverage;
+    use rule::Rule;
+
+    fn values<'a>() -> Rule<'a> {
+        let value = custom!("Value");
+        let items = opt!(seq!(value.get(), star!(seq!(char!(','), value.get()))));
+        let list = seq!("List" => char!('['), items, char!(']'));
+        let number = plus!("Number" => sor!(char!('0'), char!('1')));
+        value.init(sor!(str!("true"), str!("false"), number, list))
+    }
+
+    #[test]
+    fn coverage_counts_rules_and_alternatives() {
+        let rule = values();
+        let coverage = Coverage::run(&rule, &["true", "[1,[]]", "x"]);
+        assert_eq!(coverage.inputs, 3);
+        let value = coverage.counts(&rule);
+        assert_eq!(value.attempts, 6);
+        assert_eq!(value.matches, 4);
+        let sor = match rule.kind() {
+            Some(rule::RuleKind::Custom(sor)) => sor.clone(),
+            _ => unreachable!(),
+        };
+        assert_eq!(coverage.alternative(&sor, 0), 1);
+        assert_eq!(coverage.alternative(&sor, 1), 0);
+        assert_eq!(coverage.alternative(&sor, 2), 1);
+        assert_eq!(coverage.alternative(&sor, 3), 2);
+        assert_eq!(coverage.alternative(&sor, 4), 0);
+    }
+
+    #[test]
+    fn coverage_reports_missed_cases() {
+        let rule = values();
+        let grammar = Grammar::new(rule.clone());
+        let coverage = Coverage::run(&rule, &["true", "[1]", "[]"]);
+        assert_eq!(
+            coverage.missed(&grammar),
+            vec![
+                "Value: alternative 2 `\"false\"` of `\"true\" / \"false\" / Number / List` never matched",
+                "Number: alternative 1 `'0'` of `'0' / '1'` never matched",
+                "List: `(',' Value)*` never matched more than zero times",
+            ]
+        );
+        let coverage = Coverage::run(&rule, &["[false,10]"]);
+        assert_eq!(
+            coverage.missed(&grammar),
+            vec![
+                "Value: alternative 1 `\"true\"` of `\"true\" / \"false\" / Number / List` never matched",
+                "List: `(Value (',' Value)*)?` never matched without its content",
+                "List: `(',' Value)*` never matched zero times",
+            ]
+        );
+    }
+
+    #[test]
+    fn coverage_reports_unused_rules() {
+        let rule = values();
+        let grammar = Grammar::new(rule.clone());
+        let coverage = Coverage::run(&rule, &["true"]);
+        let missed = coverage.missed(&grammar);
+        assert!(missed.contains(&"List: never matched".to_string()));
+        assert!(missed.contains(&"Number: never matched".to_string()));
+    }
+
+    #[test]
+    fn coverage_text_and_annotated_listing() {
+        let rule = values();
+        let grammar = Grammar::new(rule.clone());
+        let coverage = Coverage::run(&rule, &["true", "[1,0]", "[]"]);
+        // Alternatives that cannot start with the next character are not attempted.
+        assert_eq!(
+            coverage.to_text(&grammar),
+            "coverage of 3 inputs\n\
+            rule    attempts   matches\n\
+            Value          6         5\n\
+            Number         2         2\n\
+            List           2         2\n\
+            never matched:\n\
+            \x20 Value: alternative 2 `\"false\"` of `\"true\" / \"false\" / Number / List` never matched\n\
+            \x20 List: `(',' Value)*` never matched zero times\n"
+        );
+        assert_eq!(
+            coverage.annotated(&grammar),
+            "# matched 5 of 6 attempts\n\
+            Value <- \"true\" [1] / \"false\" [0] / Number [2] / List [2] ;\n\
+            # matched 2 of 2 attempts\n\
+            Number <- ('0' [1] / '1' [1])+ [2] ;\n\
+            # matched 2 of 2 attempts\n\
+            List <- '[' (Value (',' Value)* [zero 0, more 1])? [absent 1, present 1] ']' ;\n"
+        );
+    }
+}
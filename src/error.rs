@@ -0,0 +1,329 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use crate::rule::{Rule, RuleKind};
+use crate::rule_handler::{Handler, Listener};
+use crate::{InputBounds, Name, Node, Slice};
+
+/// Why a rule did not match.
+#[derive(Debug)]
+pub enum Failure<'a, I: ?Sized + Slice = str> {
+    /// The rule did not match, the caller is free to try something else.
+    Backtrack,
+    /// A rule following a cut did not match.
+    /// Callers must not try any alternatives and should pass it on unchanged.
+    Cut { rest: &'a I, id: usize, name: Name },
+}
+
+impl<I: ?Sized + Slice> Clone for Failure<'_, I> {
+    fn clone(&self) -> Self {
+        match self {
+            Failure::Backtrack => Failure::Backtrack,
+            Failure::Cut { rest, id, name } => Failure::Cut {
+                rest: *rest,
+                id: *id,
+                name: name.clone(),
+            },
+        }
+    }
+}
+
+impl<I: ?Sized + Slice> PartialEq for Failure<'_, I> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Failure::Backtrack, Failure::Backtrack) => true,
+            (
+                Failure::Cut { rest, id, name },
+                Failure::Cut {
+                    rest: other_rest,
+                    id: other_id,
+                    name: other_name,
+                },
+            ) => rest == other_rest && id == other_id && name == other_name,
+            _ => false,
+        }
+    }
+}
+
+pub type ParseResult<'a, I = str> = Result<Node<'a, I>, Failure<'a, I>>;
+
+impl<'a, I: ?Sized + Slice> Failure<'a, I> {
+    /// Turns the failure of the rule named `name` that was applied to `input` into a
+    /// `ParseError`. Backtracking is reported where the parse got `furthest`.
+    pub fn into_error(self, input: &'a I, name: &str, furthest: Option<Furthest>) -> ParseError {
+        match self {
+            Failure::Backtrack => match furthest {
+                Some(furthest) => ParseError {
+                    offset: furthest.offset,
+                    expected: furthest.name.to_string(),
+                    committed: false,
+                },
+                // No rule that is reported failed, as in an empty choice.
+                None => ParseError {
+                    offset: 0,
+                    expected: name.to_string(),
+                    committed: false,
+                },
+            },
+            Failure::Cut { rest, name, .. } => ParseError {
+                offset: InputBounds::of(input).offset(rest),
+                expected: name.to_string(),
+                committed: true,
+            },
+        }
+    }
+}
+
+/// Where a parse that backtracked got furthest: the offset at which a rule failed and the
+/// name of that rule. This is what errors without a cut report.
+///
+/// Only named rules and rules that are not made of other rules count. Of those that failed
+/// at the furthest offset, the first one is reported, unless a named rule starting there
+/// failed after it, as named rules say more than the rules they are made of.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Furthest {
+    pub offset: usize,
+    pub name: Name,
+}
+
+impl Furthest {
+    /// Records that a rule backtracked at `offset`, `named` if it is a named rule.
+    pub fn record(furthest: &mut Option<Furthest>, offset: usize, name: &Name, named: bool) {
+        let replace = match furthest {
+            Some(furthest) => offset > furthest.offset || (offset == furthest.offset && named),
+            None => true,
+        };
+        if replace {
+            *furthest = Some(Furthest {
+                offset,
+                name: name.clone(),
+            });
+        }
+    }
+
+    /// Parses `input` with the rule again, this time with a handler, to find where it got
+    /// furthest before it backtracked. The options a `Sor` skips count as failing where it
+    /// starts, so this finds the same place as the compiled and generated parsers, which
+    /// try them all.
+    pub fn find<'a, I: ?Sized + Slice>(rule: &Rule<'a, I>, input: &'a I) -> Option<Furthest> {
+        let listener = Rc::new(FurthestListener {
+            input: InputBounds::of(input),
+            furthest: Cell::new(None),
+        });
+        let mut handler = Handler::new();
+        // Only the failures are needed, so the tree is not built a second time.
+        handler.set_build_tree(false);
+        handler.add_listener(listener.clone());
+        let _ = rule.apply_with_handler(input, &handler);
+        listener.furthest.take()
+    }
+}
+
+/// Records the rules that backtrack, see `Furthest::find`.
+struct FurthestListener {
+    input: InputBounds,
+    furthest: Cell<Option<Furthest>>,
+}
+
+impl FurthestListener {
+    fn record<I: ?Sized + Slice>(&self, rule: &Rule<I>, input: &I) {
+        let named = match rule.kind() {
+            Some(RuleKind::Custom(_)) => true,
+            Some(
+                RuleKind::Char(_)
+                | RuleKind::Str(_)
+                | RuleKind::Eof
+                | RuleKind::Literals(_)
+                | RuleKind::Opaque,
+            ) => false,
+            _ => return,
+        };
+        let mut furthest = self.furthest.take();
+        Furthest::record(&mut furthest, self.input.offset(input), &rule.name, named);
+        self.furthest.set(furthest);
+    }
+}
+
+impl<'a, I: ?Sized + Slice> Listener<'a, I> for FurthestListener {
+    fn failure(&self, rule: &Rule<'a, I>, input: &'a I, failure: &Failure<'a, I>) {
+        if *failure == Failure::Backtrack {
+            self.record(rule, input);
+        }
+    }
+
+    fn skipped(&self, options: &[Rule<'a, I>], input: &'a I) {
+        for option in options {
+            self.record(option, input);
+        }
+    }
+}
+
+/// Error returned by `Rule::try_parse`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset into the input at which the expected rule did not match.
+    pub offset: usize,
+    /// Name of the rule that was expected at `offset`.
+    pub expected: String,
+    /// True if the error was raised after passing a cut.
+    pub committed: bool,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "expected {} at offset {}", self.expected, self.offset)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParseError {
+    /// Renders the error with the line of `input` it occurred in, like a compiler would:
+    ///
+    /// ```text
+    /// error: expected ';' at 2:7
+    ///   --> input.txt
+    ///   |
+    /// 2 | a <- b
+    ///   |       ^
+    /// ```
+    pub fn render(&self, input: &str, source: &str) -> String {
+        let (line, column) = line_column(input, self.offset);
+        let text = input.lines().nth(line - 1).unwrap_or("");
+        let number = line.to_string();
+        let margin = " ".repeat(number.len());
+        format!(
+            "error: expected {} at {}:{}\n{} --> {}\n{} |\n{} | {}\n{} | {}^\n",
+            self.expected,
+            line,
+            column,
+            margin,
+            source,
+            margin,
+            number,
+            text,
+            margin,
+            " ".repeat(column - 1)
+        )
+    }
+}
+
+/// The line and column of a byte offset, both starting at 1.
+/// Columns count characters, not bytes.
+pub fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(input.len());
+    let before = &input[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// A syntax error that was recovered from by a `recover!` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    /// Byte offset at which the expected rule did not match.
+    pub offset: usize,
+    /// Name of the rule that was expected at `offset`.
+    pub expected: String,
+    /// Byte range of the input covered by the `Error` node.
+    pub skipped: std::ops::Range<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "expected {} at offset {}, skipped {}..{}",
+            self.expected, self.offset, self.skipped.start, self.skipped.end
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn line_column_counts_chars() {
+        let input = "ab\nçd\n";
+        assert_eq!(line_column(input, 0), (1, 1));
+        assert_eq!(line_column(input, 2), (1, 3));
+        assert_eq!(line_column(input, 3), (2, 1));
+        assert_eq!(line_column(input, 5), (2, 2));
+        assert_eq!(line_column(input, 7), (3, 1));
+    }
+
+    #[test]
+    fn backtracking_reports_the_furthest_failure() {
+        let grammar = Grammar::from_peg(
+            "Statements <- Statement* EOF ; Statement <- Name '=' Name ';' ; Name <- ('a' / 'b' / 'c' / 'd')+ ;",
+        )
+        .unwrap();
+        let error = grammar.start.try_parse("a=b;c=d e").unwrap_err();
+        assert_eq!(error.to_string(), "expected Char at offset 7");
+        // A named rule that fails where it starts is reported instead of its parts.
+        let error = grammar.start.try_parse("a=b;c=;").unwrap_err();
+        assert_eq!(error.to_string(), "expected Name at offset 6");
+        assert!(!error.committed);
+        // Options a `Sor` skips are expected too, like when they are tried.
+        let rule = sor!(
+            custom!("Digit" => char!('1')),
+            custom!("Letter" => char!('a'))
+        );
+        let error = rule.try_parse("x").unwrap_err();
+        assert_eq!(error.to_string(), "expected Letter at offset 0");
+    }
+
+    #[test]
+    fn finding_the_furthest_failure_keeps_no_tree() {
+        use crate::rule::Rule;
+        use crate::rule_handler::Handler;
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        /// Matches a character, counting the children it got to keep when parsed with a
+        /// handler.
+        struct Probe(Rc<Cell<usize>>);
+        impl<'a> Parsable<'a> for Probe {
+            fn parse(&self, input: &'a str, id: usize, name: &Name) -> ParseResult<'a> {
+                match input.chars().next() {
+                    Some(c) => Ok(Node::new(&input[..c.len_utf8()], id, name)),
+                    None => Err(Failure::Backtrack),
+                }
+            }
+            fn parse_with_handler(
+                &self,
+                input: &'a str,
+                id: usize,
+                name: &Name,
+                handler: &Handler<'a>,
+            ) -> ParseResult<'a> {
+                let mut node = self.parse(input, id, name)?;
+                let child = Node::new(node.content, id, name);
+                handler.add_child(&mut node, child);
+                self.0.set(self.0.get() + node.children.len());
+                Ok(node)
+            }
+        }
+
+        let kept = Rc::new(Cell::new(0));
+        let probe = Rule::new(Box::new(Probe(kept.clone())), 0, "Probe");
+        let error = seq!(star!(probe), char!('x')).try_parse("abc").unwrap_err();
+        assert_eq!(error.offset, 3);
+        assert_eq!(kept.get(), 0);
+    }
+
+    #[test]
+    fn parse_error_render() {
+        let error = ParseError {
+            offset: 7,
+            expected: "';'".to_string(),
+            committed: true,
+        };
+        assert_eq!(
+            error.render("a <- b\nc <- d e\n", "test.peg"),
+            "error: expected ';' at 2:1\n  --> test.peg\n  |\n2 | c <- d e\n  | ^\n"
+        );
+    }
+}
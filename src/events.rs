@@ -0,0 +1,419 @@
+//! Parsing that passes the tree on as a stream of events instead of building it, for
+//! inputs whose tree does not fit in memory.
+//!
+//! The rules are listened to like when tracing, and drop the nodes of their sub-rules as
+//! they go (see `Handler::set_build_tree`). Events of a rule that an enclosing alternative
+//! or repetition may still undo by backtracking are held back until that is decided, so
+//! the consumer only ever sees the nodes of the final tree, in order. With a repetition at
+//! the top of the grammar, that is one repetition at a time.
+//!
+//! ```
+//! use std::cell::RefCell;
+//! use std::rc::Rc;
+//! use yaast::events::Event;
+//! use yaast::Grammar;
+//!
+//! let input = "a=1\nb=2\n";
+//! let grammar = Grammar::from_peg("Lines <- Line* EOF ; Line <- Letter '=' Digit '\n' ; Letter <- 'a' / 'b' ; Digit <- '1' / '2' ;").unwrap();
+//! let lines = Rc::new(RefCell::new(Vec::new()));
+//! let sink = lines.clone();
+//! let length = grammar.start.parse_events(input, move |event| match event {
+//!     Event::Exit { name, span, .. } if name == "Line" => sink.borrow_mut().push(span),
+//!     _ => {}
+//! });
+//! assert_eq!(length, Ok(input.len()));
+//! assert_eq!(*lines.borrow(), [0..4, 4..8]);
+//! ```
+
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::rule::{Rule, RuleKind, CUT_ID};
+use crate::rule_handler::{Handler, Listener};
+use crate::{Failure, Name, Node, ParseError};
+
+/// A step of a walk over the tree, in the order of the input.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// A node with children starts at the offset.
+    Enter {
+        id: usize,
+        name: Name,
+        offset: usize,
+    },
+    /// The node entered last ends, having matched the span.
+    Exit {
+        id: usize,
+        name: Name,
+        span: Range<usize>,
+    },
+    /// A node without children.
+    Token {
+        id: usize,
+        name: Name,
+        span: Range<usize>,
+    },
+}
+
+/// What kind of rule a frame is for, as far as backtracking is concerned.
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Seq,
+    Sor,
+    Star,
+    Plus,
+    Opt,
+    Must,
+    Recover,
+    Custom,
+    Leaf,
+    Opaque,
+}
+
+impl Kind {
+    fn of(rule: &Rule) -> Kind {
+        match rule.kind() {
+            Some(RuleKind::Seq(_)) => Kind::Seq,
+            Some(RuleKind::Sor(_)) => Kind::Sor,
+            Some(RuleKind::Star(_)) => Kind::Star,
+            Some(RuleKind::Plus(_)) => Kind::Plus,
+            Some(RuleKind::Opt(_)) => Kind::Opt,
+            Some(RuleKind::Must(_)) => Kind::Must,
+            Some(RuleKind::Recover(..)) => Kind::Recover,
+            Some(RuleKind::Custom(_)) => Kind::Custom,
+            Some(
+                RuleKind::Char(_)
+                | RuleKind::Str(_)
+                | RuleKind::Eof
+                | RuleKind::Cut
+                | RuleKind::Literals(_),
+            ) => Kind::Leaf,
+            Some(RuleKind::Opaque) | None => Kind::Opaque,
+        }
+    }
+
+    /// Whether the rule returns the node of its sub-rule as its own when that matches.
+    fn transparent(self) -> bool {
+        matches!(self, Kind::Opt | Kind::Must | Kind::Recover)
+    }
+}
+
+/// A rule being parsed.
+struct Frame {
+    kind: Kind,
+    offset: usize,
+    /// Number of events made before the rule started, including those passed on.
+    mark: usize,
+    /// Whether the rule is a `Seq` that passed a cut, after which it cannot backtrack.
+    committed: bool,
+}
+
+struct State {
+    frames: Vec<Frame>,
+    /// Events that backtracking may still undo.
+    pending: Vec<Event>,
+    /// Number of events passed on to the consumer.
+    passed: usize,
+}
+
+/// A `Listener` passing the tree of the parse on to a consumer, see the module
+/// documentation. Parse with a `Handler` that does not build the tree.
+pub struct EventStream<'a> {
+    input_len: usize,
+    state: RefCell<State>,
+    consumer: RefCell<Box<dyn FnMut(Event) + 'a>>,
+}
+
+impl<'a> EventStream<'a> {
+    /// Creates a stream for parsing `input`, which has to be the whole input passed to the
+    /// rule, as offsets are computed relative to it.
+    pub fn new(input: &str, consumer: impl FnMut(Event) + 'a) -> EventStream<'a> {
+        EventStream {
+            input_len: input.len(),
+            state: RefCell::new(State {
+                frames: Vec::new(),
+                pending: Vec::new(),
+                passed: 0,
+            }),
+            consumer: RefCell::new(Box::new(consumer)),
+        }
+    }
+
+    /// Passes on the pending events that no rule being parsed can undo anymore.
+    fn pass(&self, state: &mut State) {
+        // Whether each rule, from the innermost out, can still fail by backtracking.
+        let mut can_backtrack = false;
+        let mut end = state.passed + state.pending.len();
+        for i in (1..state.frames.len()).rev() {
+            let frame = &state.frames[i];
+            can_backtrack = match frame.kind {
+                Kind::Star | Kind::Opt | Kind::Must => false,
+                Kind::Seq => !frame.committed,
+                // Only if the sub-rule being parsed does, there is none right after one
+                // matched.
+                Kind::Custom | Kind::Sor | Kind::Plus => can_backtrack,
+                Kind::Recover | Kind::Leaf | Kind::Opaque => true,
+            };
+            let undone = match state.frames[i - 1].kind {
+                Kind::Sor | Kind::Star | Kind::Plus | Kind::Opt => can_backtrack,
+                Kind::Recover | Kind::Opaque => true,
+                _ => false,
+            };
+            if undone {
+                end = frame.mark;
+            }
+        }
+        // Whether a node has children, making it a `Token` or not, is only known once one
+        // of them is.
+        while end > state.passed
+            && matches!(state.pending[end - state.passed - 1], Event::Enter { .. })
+        {
+            end -= 1;
+        }
+        if end > state.passed {
+            let mut consumer = self.consumer.borrow_mut();
+            for event in state.pending.drain(..end - state.passed) {
+                consumer(event);
+            }
+            state.passed = end;
+        }
+    }
+}
+
+/// Makes the events of a node that was not made by rules being listened to, like the
+/// empty node of an `Opt` or the `Error` node of a `Recover`.
+fn node_events(node: &Node, offset: usize, events: &mut Vec<Event>) {
+    let span = offset..offset + node.content.len();
+    if node.children.is_empty() {
+        events.push(Event::Token {
+            id: node.type_id,
+            name: node.type_name.clone(),
+            span,
+        });
+        return;
+    }
+    events.push(Event::Enter {
+        id: node.type_id,
+        name: node.type_name.clone(),
+        offset,
+    });
+    for child in &node.children {
+        let start = child.content.as_ptr() as usize - node.content.as_ptr() as usize;
+        node_events(child, offset + start, events);
+    }
+    events.push(Event::Exit {
+        id: node.type_id,
+        name: node.type_name.clone(),
+        span,
+    });
+}
+
+impl<'a> Listener<'a> for EventStream<'a> {
+    fn pre_parse(&self, rule: &Rule<'a>, input: &'a str) {
+        let state = &mut *self.state.borrow_mut();
+        let kind = Kind::of(rule);
+        let offset = self.input_len - input.len();
+        state.frames.push(Frame {
+            kind,
+            offset,
+            mark: state.passed + state.pending.len(),
+            committed: false,
+        });
+        if !kind.transparent() {
+            state.pending.push(Event::Enter {
+                id: rule.id,
+                name: rule.name.clone(),
+                offset,
+            });
+        }
+    }
+
+    fn success(&self, rule: &Rule<'a>, _input: &'a str, node: &Node<'a>) {
+        let state = &mut *self.state.borrow_mut();
+        let Some(frame) = state.frames.pop() else {
+            return;
+        };
+        let made = state.passed + state.pending.len() - frame.mark;
+        if frame.kind.transparent() {
+            // Its sub-rule made the events of the node, unless it did not match.
+            if made == 0 {
+                node_events(node, frame.offset, &mut state.pending);
+            }
+        } else if made == 1 {
+            // Only its own `Enter`, so the rule made the node without sub-rules.
+            state.pending.pop();
+            node_events(node, frame.offset, &mut state.pending);
+        } else {
+            state.pending.push(Event::Exit {
+                id: rule.id,
+                name: rule.name.clone(),
+                span: frame.offset..frame.offset + node.content.len(),
+            });
+        }
+        if rule.id == *CUT_ID {
+            if let Some(parent) = state.frames.last_mut() {
+                parent.committed |= parent.kind == Kind::Seq;
+            }
+        }
+        self.pass(state);
+    }
+
+    fn failure(&self, _rule: &Rule<'a>, _input: &'a str, _failure: &Failure<'a>) {
+        let state = &mut *self.state.borrow_mut();
+        if let Some(frame) = state.frames.pop() {
+            let kept = frame.mark.saturating_sub(state.passed);
+            state.pending.truncate(kept);
+        }
+    }
+}
+
+impl<'a> Rule<'a> {
+    /// Parses the input, passing its tree to `consumer` as events instead of building it,
+    /// see `events`. Returns the length of the match.
+    /// When the parse fails, the consumer may already have received events for the start
+    /// of the input.
+    pub fn parse_events(
+        &self,
+        input: &'a str,
+        consumer: impl FnMut(Event) + 'a,
+    ) -> Result<usize, ParseError> {
+        let mut handler = Handler::new();
+        handler.set_build_tree(false);
+        handler.add_listener(Rc::new(EventStream::new(input, consumer)));
+        self.apply_with_handler(input, &handler)
+            .map(|node| node.content.len())
+            .map_err(|failure| self.error(input, failure))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Sentences;
+    use crate::rule::SEQ_ID;
+    use crate::test_support::inputs;
+    use crate::Grammar;
+
+    /// The events as `Node::to_sexpr` renders the tree.
+    fn sexpr(events: &[Event], input: &str) -> String {
+        let mut result = String::new();
+        for event in events {
+            if result.ends_with(')') && !matches!(event, Event::Exit { .. }) {
+                result += " ";
+            }
+            match event {
+                Event::Enter { name, .. } => result += &format!("({} ", name),
+                Event::Exit { .. } => result += ")",
+                Event::Token { name, span, .. } => {
+                    result += &format!("({} {:?})", name, &input[span.clone()])
+                }
+            }
+        }
+        result
+    }
+
+    fn events<'a>(rule: &Rule<'a>, input: &'a str) -> (Result<usize, ParseError>, Vec<Event>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let result = rule.parse_events(input, move |event| sink.borrow_mut().push(event));
+        let events = events.borrow().clone();
+        (result, events)
+    }
+
+    #[test]
+    fn same_as_the_tree() {
+        let sentences = Sentences::new();
+        let grammar = Grammar::from_peg(
+            r#"
+            Values <- Value (',' Space Value)* EOF ;
+            Value <- literals("null", "nil") / List / Number / Word ;
+            List <- '[' ^ Space (Value Space)* must(']') ;
+            Number <- '-'? Digit+ ;
+            Digit <- '0' / '1' / '2' ;
+            Word <- ('a' / 'b' / "cd")+ ;
+            Space <- ' '* ;
+            "#,
+        )
+        .unwrap();
+        let fixed = ["[1 [null] -2], ab", "[1 2"];
+        for input in inputs(&grammar.start, &sentences, &fixed, 100) {
+            let (result, events) = events(&grammar.start, input);
+            match grammar.start.try_parse(input) {
+                Ok(node) => {
+                    assert_eq!(result, Ok(node.content.len()));
+                    assert_eq!(sexpr(&events, input), node.to_sexpr(), "{:?}", input);
+                }
+                Err(error) => assert_eq!(result, Err(error)),
+            }
+        }
+    }
+
+    #[test]
+    fn recovered_errors_and_spans() {
+        let input = "a;x;b;";
+        let grammar = Grammar::from_peg(
+            "Statements <- Statement* EOF ; Statement <- recover(Letter ';', ';') ; Letter <- 'a' / 'b' ;",
+        )
+        .unwrap();
+        let (result, events) = events(&grammar.start, input);
+        assert_eq!(result, Ok(input.len()));
+        let node = grammar.start.parse(input).unwrap();
+        assert_eq!(sexpr(&events, input), node.to_sexpr());
+        let error = events
+            .iter()
+            .position(|event| matches!(event, Event::Enter { name, .. } if name == "Error"))
+            .unwrap();
+        assert_eq!(
+            events[error + 1],
+            Event::Token {
+                id: *SEQ_ID,
+                name: "Seq".into(),
+                span: 2..2,
+            }
+        );
+        assert!(matches!(events[error + 2], Event::Exit { ref span, .. } if *span == (2..4)));
+    }
+
+    /// Logs where `Item`s start, next to the events.
+    struct Starts(Rc<RefCell<Vec<String>>>, usize);
+
+    impl<'a> Listener<'a> for Starts {
+        fn pre_parse(&self, rule: &Rule<'a>, input: &'a str) {
+            if rule.name == "Item" {
+                let offset = self.1 - input.len();
+                self.0.borrow_mut().push(format!("Item at {}", offset));
+            }
+        }
+    }
+
+    #[test]
+    fn events_are_passed_on_as_repetitions_end() {
+        let input = "ab;ab;a";
+        let grammar = Grammar::from_peg("Items <- Item* ; Item <- 'a' 'b' ';' ;").unwrap();
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let sink = log.clone();
+        let stream = EventStream::new(input, move |event| {
+            sink.borrow_mut().push(match event {
+                Event::Enter { name, .. } => format!("enter {}", name),
+                Event::Exit { name, .. } => format!("exit {}", name),
+                Event::Token { name, .. } => name.to_string(),
+            })
+        });
+        let mut handler = Handler::new();
+        handler.set_build_tree(false);
+        handler.add_listener(Rc::new(stream));
+        handler.add_listener(Rc::new(Starts(log.clone(), input.len())));
+        let node = grammar.start.parse_with_handler(input, &handler).unwrap();
+        assert_eq!(node.content, "ab;ab;");
+        assert!(node.children.is_empty());
+
+        let item = "enter Item, enter Seq, Char, Char, Char, exit Seq, exit Item";
+        assert_eq!(
+            log.borrow().join(", "),
+            format!(
+                "Item at 0, enter Items, enter Star, {item}, Item at 3, {item}, Item at 6, exit Star, exit Items"
+            )
+        );
+    }
+}
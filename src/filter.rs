@@ -47,10 +47,20 @@ impl<'a> Filter {
         let is_root = ast.type_id == *crate::ROOT_ID;
         let mut root = if is_root { ast } else { Node::new_as_root(ast) };
         self.filter_node(&mut root);
-        return root;
+        root
     }
 
-    fn filter_node(&self, node: &mut Node<'a>) -> () {
+    /// Like `filter_ast`, but leaves out the root node it adds if a single tree is left.
+    pub fn filter_tree(&self, tree: Node<'a>) -> Node<'a> {
+        let mut root = self.filter_ast(tree);
+        if root.children.len() == 1 {
+            root.children.remove(0)
+        } else {
+            root
+        }
+    }
+
+    fn filter_node(&self, node: &mut Node<'a>) {
         while self.filter_node_children(node) {}
         for child in &mut node.children {
             self.filter_node(child);
@@ -73,7 +83,7 @@ impl<'a> Filter {
     }
 
     fn extract_children(&self, node: &mut Node<'a>, mut vec: Vec<Node<'a>>) -> Vec<Node<'a>> {
-        let mut children = std::mem::replace(&mut node.children, Vec::new());
+        let mut children = std::mem::take(&mut node.children);
         vec.append(&mut children);
         vec
     }
@@ -0,0 +1,230 @@
+use std::fmt;
+
+use crate::rule::{unnamed_ids, ERROR_ID};
+use crate::{Node, ROOT_ID, UNREACHABLE_ID};
+
+/// Builds a value from a node of the tree.
+///
+/// Usually derived with `yaast_derive::FromNode`: a struct is built from a node of the rule
+/// with the struct's name, taking each field from the named rules below it, and an enum
+/// from a node of one of the rules its variants are named after.
+pub trait FromNode<'a>: Sized {
+    fn from_node(node: &Node<'a>) -> Result<Self, FromNodeError>;
+}
+
+/// Why a tree could not be turned into a value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FromNodeError {
+    /// Names of the nodes from the one being converted down to the one that did not fit.
+    pub path: Vec<String>,
+    pub message: String,
+}
+
+impl FromNodeError {
+    pub fn new(message: String) -> FromNodeError {
+        FromNodeError {
+            path: Vec::new(),
+            message,
+        }
+    }
+
+    /// Adds the node the error happened in to the front of the path.
+    pub fn within(mut self, node: &Node) -> FromNodeError {
+        self.path.insert(0, node.type_name.to_string());
+        self
+    }
+}
+
+impl fmt::Display for FromNodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "in {}: {}", self.path.join(" > "), self.message)
+        }
+    }
+}
+
+impl std::error::Error for FromNodeError {}
+
+/// The text the node matched.
+impl<'a> FromNode<'a> for &'a str {
+    fn from_node(node: &Node<'a>) -> Result<Self, FromNodeError> {
+        Ok(node.content)
+    }
+}
+
+impl<'a> FromNode<'a> for String {
+    fn from_node(node: &Node<'a>) -> Result<Self, FromNodeError> {
+        Ok(node.content.to_string())
+    }
+}
+
+/// Whether the node comes from an unnamed rule like `Seq` or `Char`. `Error` nodes stand
+/// for what a rule failed to match, so they are kept.
+fn is_anonymous(node: &Node) -> bool {
+    node.type_id != *ERROR_ID
+        && (unnamed_ids().iter().any(|(id, _, _)| *id == node.type_id)
+            || [*ROOT_ID, *UNREACHABLE_ID].contains(&node.type_id))
+}
+
+/// The nodes of named rules below this one, looking through unnamed ones but not into
+/// named ones, in order.
+pub fn named_children<'n, 'a>(node: &'n Node<'a>) -> Vec<&'n Node<'a>> {
+    fn collect<'n, 'a>(node: &'n Node<'a>, result: &mut Vec<&'n Node<'a>>) {
+        for child in &node.children {
+            if is_anonymous(child) {
+                collect(child, result);
+            } else {
+                result.push(child);
+            }
+        }
+    }
+    let mut result = Vec::new();
+    collect(node, &mut result);
+    result
+}
+
+/// Fails unless the node comes from the rule.
+pub fn expect_rule(node: &Node, rule: &str) -> Result<(), FromNodeError> {
+    if node.type_name == rule {
+        Ok(())
+    } else {
+        Err(FromNodeError::new(format!(
+            "expected {}, found {} {:?}",
+            rule, node.type_name, node.content
+        )))
+    }
+}
+
+fn of_rule<'n, 'a>(children: &[&'n Node<'a>], rule: &str) -> Vec<&'n Node<'a>> {
+    children
+        .iter()
+        .filter(|child| child.type_name == rule)
+        .copied()
+        .collect()
+}
+
+/// Converts the only child of the rule, failing if there is none or several.
+pub fn one<'a, T: FromNode<'a>>(
+    node: &Node<'a>,
+    children: &[&Node<'a>],
+    rule: &str,
+) -> Result<T, FromNodeError> {
+    match of_rule(children, rule)[..] {
+        [child] => T::from_node(child).map_err(|error| error.within(node)),
+        [] => Err(FromNodeError::new(format!("expected {}, found none", rule)).within(node)),
+        ref found => {
+            Err(
+                FromNodeError::new(format!("expected one {}, found {}", rule, found.len()))
+                    .within(node),
+            )
+        }
+    }
+}
+
+/// Converts the child of the rule if there is one, failing if there are several.
+pub fn optional<'a, T: FromNode<'a>>(
+    node: &Node<'a>,
+    children: &[&Node<'a>],
+    rule: &str,
+) -> Result<Option<T>, FromNodeError> {
+    if of_rule(children, rule).is_empty() {
+        Ok(None)
+    } else {
+        one(node, children, rule).map(Some)
+    }
+}
+
+/// Converts every child of the rule.
+pub fn many<'a, T: FromNode<'a>>(
+    node: &Node<'a>,
+    children: &[&Node<'a>],
+    rule: &str,
+) -> Result<Vec<T>, FromNodeError> {
+    of_rule(children, rule)
+        .into_iter()
+        .map(|child| T::from_node(child).map_err(|error| error.within(node)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grammar;
+
+    /// What `#[derive(FromNode)]` generates for this struct.
+    struct Pair<'a> {
+        key: &'a str,
+        values: Vec<String>,
+        comment: Option<String>,
+    }
+
+    impl<'a> FromNode<'a> for Pair<'a> {
+        fn from_node(node: &Node<'a>) -> Result<Self, FromNodeError> {
+            expect_rule(node, "Pair")?;
+            let children = named_children(node);
+            Ok(Pair {
+                key: one(node, &children, "Key")?,
+                values: many(node, &children, "Value")?,
+                comment: optional(node, &children, "Comment")?,
+            })
+        }
+    }
+
+    const PAIR: &str = "
+        Pair <- Key '=' Value (',' Value)* Comment? ;
+        Key <- ('k' / Value)+ ;
+        Value <- 'v' / 'w' ;
+        Comment <- '#' 'c'* ;
+    ";
+
+    #[test]
+    fn fields() {
+        let input = "k=v,w#cc";
+        let grammar = Grammar::from_peg(PAIR).unwrap();
+        let tree = grammar.start.parse(input).unwrap();
+        let pair = Pair::from_node(&tree).unwrap();
+        assert_eq!(pair.key, "k");
+        assert_eq!(pair.values, ["v", "w"]);
+        assert_eq!(pair.comment.as_deref(), Some("#cc"));
+    }
+
+    #[test]
+    fn errors() {
+        let (value, twice, none, list) = ("v", "k=k", "v", "k==");
+        let grammar = Grammar::from_peg(PAIR).unwrap();
+        let tree = grammar.get("Value").unwrap().parse(value).unwrap();
+        assert_eq!(
+            Pair::from_node(&tree).err().unwrap().to_string(),
+            "expected Pair, found Value \"v\""
+        );
+
+        let grammar = Grammar::from_peg("Pair <- Key '=' Key ; Key <- 'k' ;").unwrap();
+        let tree = grammar.start.parse(twice).unwrap();
+        assert_eq!(
+            Pair::from_node(&tree).err().unwrap().to_string(),
+            "in Pair: expected one Key, found 2"
+        );
+
+        let grammar = Grammar::from_peg("Pair <- Value ; Value <- 'v' ;").unwrap();
+        let tree = grammar.start.parse(none).unwrap();
+        assert_eq!(
+            Pair::from_node(&tree).err().unwrap(),
+            FromNodeError {
+                path: vec!["Pair".to_string()],
+                message: "expected Key, found none".to_string(),
+            }
+        );
+
+        let grammar = Grammar::from_peg("List <- Pair+ ; Pair <- Key? '=' ; Key <- 'k' ;").unwrap();
+        let tree = grammar.start.parse(list).unwrap();
+        let error = many::<Pair>(&tree, &named_children(&tree), "Pair")
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "in List > Pair: expected Key, found none"
+        );
+    }
+}
@@ -0,0 +1,518 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+
+use crate::check::collect_all;
+use crate::rule::{Rule, RuleKind};
+
+/// Height of rules that cannot produce a sentence, like opaque ones.
+const UNREACHABLE: usize = usize::MAX;
+
+/// How many sentences `Generator::sentence` and `Generator::near_miss` try before giving up.
+const TRIES: usize = 20;
+
+/// How `Generator` chooses between alternatives and repetition counts.
+pub struct GenerateOptions {
+    /// Seed of the random choices, the same seed gives the same sentences.
+    pub seed: u64,
+    /// Named rules nested deeper than this are avoided, by taking the alternatives
+    /// and repetition counts that end the sentence soonest.
+    pub max_depth: usize,
+    /// Largest number of extra repetitions of a `Star` or `Plus`.
+    pub max_repeat: usize,
+    /// Once a sentence is this many bytes long, the shortest choices are taken.
+    pub max_len: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        GenerateOptions {
+            seed: 0,
+            max_depth: 10,
+            max_repeat: 3,
+            max_len: 256,
+        }
+    }
+}
+
+impl GenerateOptions {
+    pub fn new() -> GenerateOptions {
+        GenerateOptions::default()
+    }
+}
+
+/// SplitMix64, good enough for picking alternatives and reproducible across platforms.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`, `n` has to be positive.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Keeps the sentences a `Generator` tries, for as long as it lives. Rules can only parse
+/// text that outlives them, so create it before the grammar. Walks the rule rejects are
+/// kept as well, since a custom rule may hold on to the text it was given, but every text
+/// is stored once, however often it is tried.
+#[derive(Default)]
+pub struct Sentences {
+    // Boxed, so the text stays in place when the set grows.
+    sentences: RefCell<HashSet<Box<str>>>,
+}
+
+impl Sentences {
+    pub fn new() -> Sentences {
+        Sentences::default()
+    }
+
+    fn add(&self, sentence: String) -> &str {
+        let mut sentences = self.sentences.borrow_mut();
+        let stored: *const str = match sentences.get(sentence.as_str()) {
+            Some(stored) => &**stored,
+            None => {
+                let boxed = sentence.into_boxed_str();
+                let text: *const str = &*boxed;
+                sentences.insert(boxed);
+                text
+            }
+        };
+        // SAFETY: the text is on the heap and never moved, changed or dropped before
+        // `self` is, as nothing is ever removed from the set.
+        unsafe { &*stored }
+    }
+}
+
+/// Builds random sentences by walking the rules, for fuzzing whatever consumes the trees.
+///
+/// A walk follows the structure of the rules, but PEG parsers take the first alternative
+/// that matches and repeat as often as they can, so the grammar may still read it
+/// differently and fail. `sentence` only returns walks the rule parses, and `near_miss`
+/// only edited walks it rejects, trying again a few times before giving up:
+///
+/// ```
+/// use yaast::generate::{GenerateOptions, Generator, Sentences};
+/// use yaast::Grammar;
+///
+/// let sentences = Sentences::new();
+/// let grammar = Grammar::from_peg("Word <- 'a'+ 'b'? ;").unwrap();
+/// let mut generator = Generator::new(GenerateOptions::new());
+/// let sentence = generator.sentence(&grammar.start, &sentences).unwrap();
+/// assert!(grammar.start.parse_all(sentence).is_ok());
+/// let near_miss = generator.near_miss(&grammar.start, &sentences).unwrap();
+/// assert!(grammar.start.parse_all(near_miss).is_err());
+/// ```
+pub struct Generator {
+    options: GenerateOptions,
+    rng: Rng,
+    /// The alternatives the current walk took, by the instance id of their `Sor`.
+    chosen: Vec<(usize, usize)>,
+    walks: HashMap<(usize, usize), Walks>,
+}
+
+/// How many walks through an alternative of a `Sor` `Generator::sentence` tried, and how
+/// many of them the rule parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Walks {
+    pub tried: usize,
+    pub parsed: usize,
+}
+
+impl Generator {
+    pub fn new(options: GenerateOptions) -> Generator {
+        let rng = Rng(options.seed);
+        Generator {
+            options,
+            rng,
+            chosen: Vec::new(),
+            walks: HashMap::new(),
+        }
+    }
+
+    /// The walks `sentence` tried through alternative `index` of a `Sor`. An alternative
+    /// whose walks are all rejected is usually shadowed by an earlier one.
+    pub fn walks(&self, sor: &Rule, index: usize) -> Walks {
+        self.walks
+            .get(&(sor.instance_id(), index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// A random sentence the rule parses completely without recovering from errors, kept
+    /// in `sentences`. `None` if the rule has no sentence within `max_depth`, contains
+    /// opaque or uninitialized rules that cannot be walked, or parsed none of the walks
+    /// tried.
+    pub fn sentence<'a>(&mut self, rule: &Rule<'a>, sentences: &'a Sentences) -> Option<&'a str> {
+        let heights = heights(rule);
+        for _ in 0..TRIES {
+            let sentence = sentences.add(self.walk(rule, &heights)?);
+            let parsed = parses(rule, sentence);
+            let mut chosen = std::mem::take(&mut self.chosen);
+            chosen.sort_unstable();
+            chosen.dedup();
+            for alternative in chosen {
+                let walks = self.walks.entry(alternative).or_default();
+                walks.tried += 1;
+                walks.parsed += parsed as usize;
+            }
+            if parsed {
+                return Some(sentence);
+            }
+        }
+        None
+    }
+
+    /// A walk with a single random edit that the rule rejects, kept in `sentences`: a
+    /// character deleted, inserted, replaced, duplicated or swapped with the next one, or
+    /// the end cut off. Inserted characters come from the literals of the grammar, so the
+    /// result stays close to valid input. `None` like `sentence`, or if the rule accepted
+    /// every edit tried.
+    pub fn near_miss<'a>(&mut self, rule: &Rule<'a>, sentences: &'a Sentences) -> Option<&'a str> {
+        let heights = heights(rule);
+        let alphabet = alphabet(rule);
+        for _ in 0..TRIES {
+            let walk = self.walk(rule, &heights)?;
+            let near_miss = sentences.add(self.mutate(&walk, &alphabet));
+            if !parses(rule, near_miss) {
+                return Some(near_miss);
+            }
+        }
+        None
+    }
+
+    /// A random walk of the rule, `None` if there is none within `max_depth`.
+    fn walk(&mut self, rule: &Rule, heights: &HashMap<usize, usize>) -> Option<String> {
+        let mut walk = String::new();
+        self.chosen.clear();
+        if self.generate(rule, heights, self.options.max_depth, &mut walk) {
+            Some(walk)
+        } else {
+            None
+        }
+    }
+
+    /// Appends a sentence of `rule` to `output`, nesting at most `depth` named rules.
+    fn generate(
+        &mut self,
+        rule: &Rule,
+        heights: &HashMap<usize, usize>,
+        depth: usize,
+        output: &mut String,
+    ) -> bool {
+        let height = |rule: &Rule| *heights.get(&rule.instance_id()).unwrap_or(&UNREACHABLE);
+        if height(rule) > depth {
+            return false;
+        }
+        let short = output.len() >= self.options.max_len;
+        match rule.kind() {
+            Some(RuleKind::Char(c)) => output.push(c),
+            Some(RuleKind::Str(s)) => output.push_str(s),
+            Some(RuleKind::Literals(literals)) => {
+                let literals = &literals.literals;
+                let chosen = if short {
+                    literals.iter().min_by_key(|literal| literal.len())
+                } else if literals.is_empty() {
+                    None
+                } else {
+                    Some(&literals[self.rng.below(literals.len())])
+                };
+                match chosen {
+                    Some(literal) => output.push_str(literal),
+                    None => return false,
+                }
+            }
+            Some(RuleKind::Eof) | Some(RuleKind::Cut) => {}
+            Some(RuleKind::Seq(rules)) => {
+                for rule in rules {
+                    if !self.generate(rule, heights, depth, output) {
+                        return false;
+                    }
+                }
+            }
+            Some(RuleKind::Sor(rules)) => {
+                let fitting: Vec<(usize, &Rule)> = rules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, rule)| height(rule) <= depth)
+                    .collect();
+                let chosen = if short {
+                    fitting.iter().min_by_key(|(_, rule)| height(rule)).copied()
+                } else if fitting.is_empty() {
+                    None
+                } else {
+                    Some(fitting[self.rng.below(fitting.len())])
+                };
+                match chosen {
+                    Some((index, option)) => {
+                        self.chosen.push((rule.instance_id(), index));
+                        return self.generate(option, heights, depth, output);
+                    }
+                    None => return false,
+                }
+            }
+            Some(RuleKind::Star(inner))
+            | Some(RuleKind::Opt(inner))
+            | Some(RuleKind::Plus(inner)) => {
+                let (least, most) = match rule.kind() {
+                    Some(RuleKind::Plus(_)) => (1, 1 + self.options.max_repeat),
+                    Some(RuleKind::Opt(_)) => (0, 1),
+                    _ => (0, self.options.max_repeat),
+                };
+                let count = if short || height(inner) > depth {
+                    least
+                } else {
+                    least + self.rng.below(most - least + 1)
+                };
+                for _ in 0..count {
+                    if !self.generate(inner, heights, depth, output) {
+                        return false;
+                    }
+                }
+            }
+            Some(RuleKind::Must(inner)) | Some(RuleKind::Recover(inner, _)) => {
+                return self.generate(inner, heights, depth, output)
+            }
+            Some(RuleKind::Custom(inner)) => {
+                return self.generate(inner, heights, depth - 1, output)
+            }
+            Some(RuleKind::Opaque) | None => return false,
+        }
+        true
+    }
+
+    fn mutate(&mut self, sentence: &str, alphabet: &[char]) -> String {
+        let mut chars: Vec<char> = sentence.chars().collect();
+        let letter = alphabet[self.rng.below(alphabet.len())];
+        if chars.is_empty() {
+            return letter.to_string();
+        }
+        let at = self.rng.below(chars.len());
+        match self.rng.below(6) {
+            0 => {
+                chars.remove(at);
+            }
+            1 => chars.insert(at, letter),
+            2 => chars[at] = letter,
+            3 => chars.insert(at, chars[at]),
+            4 if at + 1 < chars.len() => chars.swap(at, at + 1),
+            _ => chars.truncate(at),
+        }
+        chars.into_iter().collect()
+    }
+}
+
+/// The fewest named rules that have to be nested to finish a sentence of each rule,
+/// `UNREACHABLE` for rules that cannot finish one.
+fn heights(rule: &Rule) -> HashMap<usize, usize> {
+    let mut rules = Vec::new();
+    collect_all(rule, &mut HashSet::new(), &mut rules);
+    let mut heights: HashMap<usize, usize> = HashMap::new();
+    loop {
+        let mut changed = false;
+        for rule in &rules {
+            let height = |rule: &Rule| *heights.get(&rule.instance_id()).unwrap_or(&UNREACHABLE);
+            let new = match rule.kind() {
+                Some(RuleKind::Char(_))
+                | Some(RuleKind::Str(_))
+                | Some(RuleKind::Eof)
+                | Some(RuleKind::Cut)
+                | Some(RuleKind::Star(_))
+                | Some(RuleKind::Opt(_)) => 0,
+                Some(RuleKind::Seq(rules)) => rules.iter().map(height).max().unwrap_or(0),
+                Some(RuleKind::Sor(rules)) => rules.iter().map(height).min().unwrap_or(UNREACHABLE),
+                Some(RuleKind::Literals(literals)) if literals.literals.is_empty() => UNREACHABLE,
+                Some(RuleKind::Literals(_)) => 0,
+                Some(RuleKind::Plus(inner))
+                | Some(RuleKind::Must(inner))
+                | Some(RuleKind::Recover(inner, _)) => height(inner),
+                Some(RuleKind::Custom(inner)) => height(inner).saturating_add(1),
+                Some(RuleKind::Opaque) | None => UNREACHABLE,
+            };
+            if new < height(rule) {
+                heights.insert(rule.instance_id(), new);
+                changed = true;
+            }
+        }
+        if !changed {
+            return heights;
+        }
+    }
+}
+
+/// The characters of all literals in the rule, in order of first appearance.
+fn alphabet(rule: &Rule) -> Vec<char> {
+    let mut rules = Vec::new();
+    collect_all(rule, &mut HashSet::new(), &mut rules);
+    let mut alphabet = Vec::new();
+    for rule in &rules {
+        let chars: Vec<char> = match rule.kind() {
+            Some(RuleKind::Char(c)) => vec![c],
+            Some(RuleKind::Str(s)) => s.chars().collect(),
+            Some(RuleKind::Literals(literals)) => literals.literals.concat().chars().collect(),
+            _ => continue,
+        };
+        for c in chars {
+            if !alphabet.contains(&c) {
+                alphabet.push(c);
+            }
+        }
+    }
+    if alphabet.is_empty() {
+        alphabet.push('x');
+    }
+    alphabet
+}
+
+/// Whether the rule parses the whole input without recovering from errors.
+fn parses<'a>(rule: &Rule<'a>, input: &'a str) -> bool {
+    match rule.parse_with_recovery(input) {
+        Ok((node, diagnostics)) => node.content.len() == input.len() && diagnostics.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// The inputs the rule parses completely, without recovering from errors.
+pub fn accepted<'a>(rule: &Rule<'a>, inputs: &'a [String]) -> Vec<&'a str> {
+    inputs
+        .iter()
+        .map(String::as_str)
+        .filter(|input| parses(rule, input))
+        .collect()
+}
+
+/// The inputs the rule does not parse completely, or only by recovering from errors.
+pub fn rejected<'a>(rule: &Rule<'a>, inputs: &'a [String]) -> Vec<&'a str> {
+    inputs
+        .iter()
+        .map(String::as_str)
+        .filter(|input| !parses(rule, input))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grammar;
+
+    const LIST: &str = "
+        List <- '[' (Item (',' Item)*)? ']' ;
+        Item <- Digit / List ;
+        Digit <- '0' / '1' / '2' ;
+    ";
+
+    fn sentences<'a>(
+        generator: &mut Generator,
+        rule: &Rule<'a>,
+        store: &'a Sentences,
+        count: usize,
+    ) -> Vec<&'a str> {
+        (0..count)
+            .filter_map(|_| generator.sentence(rule, store))
+            .collect()
+    }
+
+    #[test]
+    fn sentences_parse() {
+        let (store, again) = (Sentences::new(), Sentences::new());
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        let mut generator = Generator::new(GenerateOptions::new());
+        let generated = sentences(&mut generator, &grammar.start, &store, 200);
+        assert_eq!(generated.len(), 200);
+        assert!(generated
+            .iter()
+            .all(|sentence| parses(&grammar.start, sentence)));
+        assert!(generated.iter().any(|sentence| sentence.contains("[[")));
+
+        let mut generator = Generator::new(GenerateOptions::new());
+        assert_eq!(
+            sentences(&mut generator, &grammar.start, &again, 200),
+            generated
+        );
+    }
+
+    #[test]
+    fn sentences_are_stored_once() {
+        let store = Sentences::new();
+        let first = store.add("[0]".to_string());
+        for i in 0..10_000 {
+            store.add(i.to_string());
+        }
+        assert!(std::ptr::eq(store.add("[0]".to_string()), first));
+        assert_eq!(store.add("42".to_string()), "42");
+        assert_eq!(store.sentences.borrow().len(), 10_001);
+    }
+
+    #[test]
+    fn limits() {
+        let store = Sentences::new();
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        let mut options = GenerateOptions::new();
+        options.seed = 7;
+        options.max_depth = 1;
+        let mut generator = Generator::new(options);
+        for sentence in sentences(&mut generator, &grammar.start, &store, 50) {
+            assert!(!sentence[1..].contains('['), "{}", sentence);
+        }
+
+        let mut options = GenerateOptions::new();
+        options.max_len = 0;
+        let mut generator = Generator::new(options);
+        assert_eq!(generator.sentence(&grammar.start, &store), Some("[]"));
+
+        let mut options = GenerateOptions::new();
+        options.max_depth = 0;
+        let mut generator = Generator::new(options);
+        assert_eq!(generator.sentence(&grammar.start, &store), None);
+    }
+
+    #[test]
+    fn walks_the_grammar_rejects_are_retried() {
+        let store = Sentences::new();
+        // The repetition takes every 'a', so only the second alternative ever parses.
+        let grammar = Grammar::from_peg("Word <- 'a'* 'a' / 'b' ;").unwrap();
+        let mut generator = Generator::new(GenerateOptions::new());
+        let generated = sentences(&mut generator, &grammar.start, &store, 20);
+        assert_eq!(generated, vec!["b"; 20]);
+
+        let sor = grammar.start.kind().unwrap().children()[0].clone();
+        let (first, second) = (generator.walks(&sor, 0), generator.walks(&sor, 1));
+        assert!(first.tried > 0 && first.parsed == 0, "{:?}", first);
+        assert_eq!(
+            second,
+            Walks {
+                tried: 20,
+                parsed: 20
+            }
+        );
+
+        let grammar = Grammar::from_peg("Word <- 'a'* 'a' ;").unwrap();
+        assert_eq!(generator.sentence(&grammar.start, &store), None);
+    }
+
+    #[test]
+    fn near_misses() {
+        let store = Sentences::new();
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        let mut generator = Generator::new(GenerateOptions::new());
+        for _ in 0..200 {
+            let near_miss = generator.near_miss(&grammar.start, &store).unwrap();
+            assert!(!parses(&grammar.start, near_miss), "{}", near_miss);
+            assert!(
+                near_miss.chars().all(|c| "[],012".contains(c)),
+                "{}",
+                near_miss
+            );
+        }
+
+        // Every edit of a sentence of `'a'*` is either accepted or cut short.
+        let grammar = Grammar::from_peg("Word <- 'a'* ;").unwrap();
+        assert_eq!(generator.near_miss(&grammar.start, &store), None);
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashSet;
+
+use crate::render::{dot_to_svg, escape_dot, RenderError};
+use crate::rule::{Rule, RuleKind};
+
+/// The rules reachable from a start rule.
+pub struct Grammar<'a> {
+    pub start: Rule<'a>,
+    /// The start rule followed by all named rules it uses, in the order they were found.
+    /// Named rules are the ones created with `custom!` or a named macro like `seq!("Name" => ...)`.
+    pub rules: Vec<Rule<'a>>,
+}
+
+/// A place in a grammar listing that can be annotated, see `Grammar::annotated`.
+#[derive(Clone, Copy)]
+pub enum Annotation<'r, 'a> {
+    /// Before the definition of a rule, rendered as a comment line.
+    Definition(&'r Rule<'a>),
+    /// After an alternative of a `Sor`, given by its index.
+    Alternative(&'r Rule<'a>, usize),
+    /// After a `Star`, `Plus` or `Opt`.
+    Repetition(&'r Rule<'a>),
+}
+
+impl<'a> Grammar<'a> {
+    pub fn new(start: Rule<'a>) -> Grammar<'a> {
+        let mut grammar = Grammar {
+            start: start.clone(),
+            rules: Vec::new(),
+        };
+        let mut seen = HashSet::new();
+        grammar.collect(&start, &mut seen);
+        grammar
+    }
+
+    fn collect(&mut self, rule: &Rule<'a>, seen: &mut HashSet<usize>) {
+        if !seen.insert(rule.instance_id()) {
+            return;
+        }
+        let kind = rule.kind();
+        if self.rules.is_empty() || Self::is_named(rule) {
+            self.rules.push(rule.clone());
+        }
+        if let Some(kind) = kind {
+            for child in kind.children() {
+                self.collect(child, seen);
+            }
+        }
+    }
+
+    /// Named rules are listed as definitions of their own and referenced by name.
+    /// Rules that were never initialized can only be named ones.
+    pub fn is_named(rule: &Rule<'a>) -> bool {
+        matches!(rule.kind(), Some(RuleKind::Custom(_)) | None)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Rule<'a>> {
+        self.rules.iter().find(|rule| rule.name == name)
+    }
+
+    /// Lists the grammar in PEG notation, one definition per line:
+    /// `'c'` and `"str"` literals, `EOF`, `^` for a cut, `FAIL` for an empty choice,
+    /// `a b` sequences, `a / b` choices, `a*`, `a+`, `a?`, `must(a)`, `recover(a, b)`,
+    /// and `<Name>` for rules that do not describe themselves.
+    pub fn to_peg(&self) -> String {
+        self.annotated(&|_| String::new())
+    }
+
+    /// Like `to_peg`, with the text returned by `annotate` added at every place
+    /// described by `Annotation`.
+    pub fn annotated(&self, annotate: &dyn Fn(Annotation<'_, 'a>) -> String) -> String {
+        let mut result = String::new();
+        for rule in &self.rules {
+            let comment = annotate(Annotation::Definition(rule));
+            if !comment.is_empty() {
+                result += &format!("# {}\n", comment);
+            }
+            result += &rule.name;
+            result += " <- ";
+            match rule.kind() {
+                Some(RuleKind::Custom(inner)) => write_expression(inner, &mut result, 0, annotate),
+                Some(_) => write_body(rule, &mut result, 0, annotate),
+                None => result += "<uninitialized>",
+            }
+            result += " ;\n";
+        }
+        result
+    }
+
+    /// The dependency graph between the named rules in DOT notation.
+    /// Edges are labelled by the combinator containing the reference, references that
+    /// may be skipped are dashed and rules and references that are part of a recursion
+    /// cycle are red. The start rule is filled.
+    pub fn to_dot(&self) -> String {
+        let references: Vec<_> = self
+            .rules
+            .iter()
+            .map(|rule| self.references(rule))
+            .collect();
+        let reaches = |from: usize, to: usize| {
+            let mut seen = vec![false; self.rules.len()];
+            let mut stack = vec![from];
+            while let Some(rule) = stack.pop() {
+                for &(target, _) in &references[rule] {
+                    if target == to {
+                        return true;
+                    }
+                    if !seen[target] {
+                        seen[target] = true;
+                        stack.push(target);
+                    }
+                }
+            }
+            false
+        };
+
+        let mut result = "digraph G {\n".to_string();
+        for (i, rule) in self.rules.iter().enumerate() {
+            result += &format!("{} [label=\"{}\" shape=box", i, escape_dot(&rule.name));
+            if i == 0 {
+                result += " style=filled fillcolor=lightblue";
+            }
+            if rule.kind().is_none() {
+                result += " color=gray";
+            } else if reaches(i, i) {
+                result += " color=red";
+            }
+            result += "];\n";
+        }
+        for (i, targets) in references.iter().enumerate() {
+            for &(target, via) in targets {
+                let mut attributes = Vec::new();
+                let (label, dashed) = match via {
+                    "sor" => ("/", false),
+                    "star" => ("*", true),
+                    "opt" => ("?", true),
+                    "plus" => ("+", false),
+                    "sync" => ("sync", true),
+                    "seq" => ("", false),
+                    via => (via, false),
+                };
+                if !label.is_empty() {
+                    attributes.push(format!("label=\"{}\"", label));
+                }
+                if dashed {
+                    attributes.push("style=dashed".to_string());
+                }
+                if reaches(target, i) {
+                    attributes.push("color=red".to_string());
+                }
+                if attributes.is_empty() {
+                    result += &format!("{} -> {};\n", i, target);
+                } else {
+                    result += &format!("{} -> {} [{}];\n", i, target, attributes.join(" "));
+                }
+            }
+        }
+        result += "}\n";
+        result
+    }
+
+    /// Renders the dependency graph of `to_dot` as SVG and writes it to a file.
+    pub fn make_svg(&self, filename: &str) -> Result<(), RenderError> {
+        std::fs::write(filename, dot_to_svg(&self.to_dot())?)?;
+        Ok(())
+    }
+
+    /// The indices in `rules` of the named rules used by the definition of a rule,
+    /// each with the innermost combinator other than a sequence containing it,
+    /// without duplicates.
+    fn references(&self, rule: &Rule<'a>) -> Vec<(usize, &'static str)> {
+        let mut references = Vec::new();
+        match rule.kind() {
+            Some(RuleKind::Custom(inner)) => self.collect_references(inner, "seq", &mut references),
+            Some(_) => self.collect_references(rule, "seq", &mut references),
+            None => {}
+        }
+        references
+    }
+
+    fn collect_references(
+        &self,
+        rule: &Rule<'a>,
+        via: &'static str,
+        references: &mut Vec<(usize, &'static str)>,
+    ) {
+        if Self::is_named(rule) {
+            let instance = rule.instance_id();
+            if let Some(index) = self.rules.iter().position(|r| r.instance_id() == instance) {
+                if !references.contains(&(index, via)) {
+                    references.push((index, via));
+                }
+            }
+            return;
+        }
+        if let Some(kind) = rule.kind() {
+            if let RuleKind::Recover(inner, sync) = kind {
+                self.collect_references(inner, "recover", references);
+                self.collect_references(sync, "sync", references);
+                return;
+            }
+            let via = combinator(kind).unwrap_or(via);
+            for child in kind.children() {
+                self.collect_references(child, via, references);
+            }
+        }
+    }
+
+    /// Renders a single rule in PEG notation, referring to named rules by name.
+    pub fn expression(rule: &Rule<'a>) -> String {
+        let mut result = String::new();
+        write_expression(rule, &mut result, 0, &|_| String::new());
+        result
+    }
+}
+
+/// Precedence levels: 0 for choices, 1 for sequences and 2 for everything else.
+fn write_expression<'a>(
+    rule: &Rule<'a>,
+    out: &mut String,
+    precedence: u8,
+    annotate: &dyn Fn(Annotation<'_, 'a>) -> String,
+) {
+    if Grammar::is_named(rule) {
+        *out += &rule.name;
+    } else {
+        write_body(rule, out, precedence, annotate);
+    }
+}
+
+fn write_body<'a>(
+    rule: &Rule<'a>,
+    out: &mut String,
+    precedence: u8,
+    annotate: &dyn Fn(Annotation<'_, 'a>) -> String,
+) {
+    let kind = match rule.kind() {
+        Some(kind) => kind,
+        None => {
+            *out += &rule.name;
+            return;
+        }
+    };
+    let parenthesize = match kind {
+        RuleKind::Seq(rules) => rules.len() > 1 && precedence > 1,
+        RuleKind::Sor(rules) => !rules.is_empty() && precedence > 0,
+        _ => false,
+    };
+    if parenthesize {
+        *out += "(";
+    }
+    match kind {
+        RuleKind::Char(c) => *out += &char_literal(c),
+        RuleKind::Str(s) => *out += &str_literal(s),
+        RuleKind::Eof => *out += "EOF",
+        RuleKind::Cut => *out += "^",
+        RuleKind::Seq([]) => *out += "()",
+        RuleKind::Seq(rules) => {
+            for (i, rule) in rules.iter().enumerate() {
+                if i > 0 {
+                    *out += " ";
+                }
+                write_expression(
+                    rule,
+                    out,
+                    if rules.len() > 1 { 2 } else { precedence },
+                    annotate,
+                );
+            }
+        }
+        RuleKind::Sor([]) => *out += "FAIL",
+        RuleKind::Sor(rules) => {
+            for (i, option) in rules.iter().enumerate() {
+                if i > 0 {
+                    *out += " / ";
+                }
+                write_expression(option, out, 1, annotate);
+                *out += &annotate(Annotation::Alternative(rule, i));
+            }
+        }
+        RuleKind::Star(inner) | RuleKind::Plus(inner) | RuleKind::Opt(inner) => {
+            write_expression(inner, out, 2, annotate);
+            *out += match kind {
+                RuleKind::Star(_) => "*",
+                RuleKind::Plus(_) => "+",
+                _ => "?",
+            };
+            *out += &annotate(Annotation::Repetition(rule));
+        }
+        RuleKind::Must(inner) => {
+            *out += "must(";
+            write_expression(inner, out, 0, annotate);
+            *out += ")";
+        }
+        RuleKind::Recover(inner, sync) => {
+            *out += "recover(";
+            write_expression(inner, out, 0, annotate);
+            *out += ", ";
+            write_expression(sync, out, 0, annotate);
+            *out += ")";
+        }
+        RuleKind::Custom(_) => *out += &rule.name,
+        RuleKind::Literals(literals) => {
+            *out += if literals.longest {
+                "keywords("
+            } else {
+                "literals("
+            };
+            let mut arguments: Vec<String> =
+                literals.literals.iter().map(|s| str_literal(s)).collect();
+            if literals.ignore_case {
+                arguments.insert(0, "ignore_case".to_string());
+            }
+            *out += &arguments.join(", ");
+            *out += ")";
+        }
+        RuleKind::Opaque => *out += &format!("<{}>", rule.name),
+    }
+    if parenthesize {
+        *out += ")";
+    }
+}
+
+/// The name of the combinator of a rule, used to label references.
+/// Sequences keep the label of the combinator containing them.
+fn combinator(kind: RuleKind) -> Option<&'static str> {
+    match kind {
+        RuleKind::Sor(_) => Some("sor"),
+        RuleKind::Star(_) => Some("star"),
+        RuleKind::Plus(_) => Some("plus"),
+        RuleKind::Opt(_) => Some("opt"),
+        RuleKind::Must(_) => Some("must"),
+        _ => None,
+    }
+}
+
+fn escape(c: char, quote: char) -> String {
+    match c {
+        '\\' => "\\\\".to_string(),
+        '\n' => "\\n".to_string(),
+        '\r' => "\\r".to_string(),
+        '\t' => "\\t".to_string(),
+        c if c == quote => format!("\\{}", c),
+        c if c.is_control() => format!("\\u{{{:x}}}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+pub(crate) fn char_literal(c: char) -> String {
+    format!("'{}'", escape(c, '\''))
+}
+
+pub(crate) fn str_literal(s: &str) -> String {
+    let escaped: String = s.chars().map(|c| escape(c, '"')).collect();
+    format!("\"{}\"", escaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use grammar::{Annotation, Grammar};
+
+    #[test]
+    fn grammar_collects_named_rules() {
+        let value = custom!("Value");
+        let list = seq!("List" => char!('['), opt!(seq!(value.get(), star!(seq!(char!(','), value.get())))), char!(']'));
+        let value = value.init(sor!(
+            str!("null"),
+            plus!("Digits" => sor!(char!('0'), char!('1'))),
+            list
+        ));
+        let document = seq!(value.clone(), eof!());
+        let grammar = Grammar::new(document);
+        let names: Vec<_> = grammar
+            .rules
+            .iter()
+            .map(|rule| rule.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["Seq", "Value", "Digits", "List"]);
+        assert_eq!(
+            grammar.get("List").unwrap().instance_id(),
+            list.instance_id()
+        );
+        assert!(grammar.get("Nothing").is_none());
+        assert_eq!(
+            grammar.to_peg(),
+            "Seq <- Value EOF ;\n\
+            Value <- \"null\" / Digits / List ;\n\
+            Digits <- ('0' / '1')+ ;\n\
+            List <- '[' (Value (',' Value)*)? ']' ;\n"
+        );
+    }
+
+    #[test]
+    fn grammar_listing_escapes_and_nesting() {
+        let rule = seq!(
+            "Rule" => sor!(seq!(char!('\''), cut!()), str!("a\"\n")),
+            must!(sor!()),
+            recover!(seq!(), eof!()),
+            seq!(char!('\\'))
+        );
+        let grammar = Grammar::new(rule);
+        assert_eq!(
+            grammar.to_peg(),
+            "Rule <- ('\\'' ^ / \"a\\\"\\n\") must(FAIL) recover((), EOF) '\\\\' ;\n"
+        );
+        assert_eq!(Grammar::expression(&grammar.start), "Rule");
+    }
+
+    #[test]
+    fn grammar_lists_uninitialized_rules() {
+        let missing = custom!("Missing");
+        let grammar = Grammar::new(seq!("Start" => char!('a'), missing.get()));
+        assert_eq!(
+            grammar.to_peg(),
+            "Start <- 'a' Missing ;\nMissing <- <uninitialized> ;\n"
+        );
+    }
+
+    #[test]
+    fn grammar_annotated_listing() {
+        let rule = seq!("Start" => sor!(char!('a'), char!('b')), star!(char!('c')));
+        let grammar = Grammar::new(rule);
+        let listing = grammar.annotated(&|annotation| match annotation {
+            Annotation::Definition(rule) => format!("definition of {}", rule.name),
+            Annotation::Alternative(_, i) => format!(" [{}]", i),
+            Annotation::Repetition(rule) => format!(" [{}]", rule.name),
+        });
+        assert_eq!(
+            listing,
+            "# definition of Start\nStart <- ('a' [0] / 'b' [1]) 'c'* [Star] ;\n"
+        );
+    }
+
+    #[test]
+    fn grammar_dependency_graph() {
+        let value = custom!("Value");
+        let list = seq!("List" => char!('['), opt!(seq!(value.get(), star!(seq!(char!(','), value.get())))), char!(']'));
+        let number = plus!("Number" => char!('1'));
+        let value = value.init(sor!(
+            number,
+            list,
+            recover!(custom!("Missing").get(), eof!())
+        ));
+        let grammar = Grammar::new(seq!(value, eof!()));
+        assert_eq!(
+            grammar.to_dot(),
+            "digraph G {\n\
+            0 [label=\"Seq\" shape=box style=filled fillcolor=lightblue];\n\
+            1 [label=\"Value\" shape=box color=red];\n\
+            2 [label=\"Number\" shape=box];\n\
+            3 [label=\"List\" shape=box color=red];\n\
+            4 [label=\"Missing\" shape=box color=gray];\n\
+            0 -> 1;\n\
+            1 -> 2 [label=\"/\"];\n\
+            1 -> 3 [label=\"/\" color=red];\n\
+            1 -> 4 [label=\"recover\"];\n\
+            3 -> 1 [label=\"?\" style=dashed color=red];\n\
+            3 -> 1 [label=\"*\" style=dashed color=red];\n\
+            }\n"
+        );
+        let svg = render::dot_to_svg(&grammar.to_dot()).unwrap();
+        assert!(svg.contains("Value"));
+    }
+}
@@ -0,0 +1,334 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::rc::Rc;
+
+use crate::rule::Rule;
+use crate::rule_handler::Handler;
+use crate::{Node, ParseError};
+
+/// Replaces `deleted` bytes at `offset` with `inserted`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edit {
+    pub offset: usize,
+    pub deleted: usize,
+    pub inserted: String,
+}
+
+impl Edit {
+    pub fn new(offset: usize, deleted: usize, inserted: &str) -> Edit {
+        Edit {
+            offset,
+            deleted,
+            inserted: inserted.to_string(),
+        }
+    }
+
+    /// Applies the edits in order.
+    /// The offset of each edit refers to the text produced by the previous ones.
+    pub fn apply(input: &str, edits: &[Edit]) -> String {
+        let mut output = input.to_string();
+        for edit in edits {
+            output.replace_range(edit.offset..edit.offset + edit.deleted, &edit.inserted);
+        }
+        output
+    }
+
+    /// Maps the start of the range `start..end` of the text before this edit to the text after it.
+    /// Returns `None` if the edit changes the range or the character right after it,
+    /// since the rule that matched the range may have looked at that character.
+    fn map(&self, start: usize, end: usize) -> Option<usize> {
+        if end < self.offset {
+            Some(start)
+        } else if self.offset + self.deleted <= start {
+            Some(start + self.inserted.len() - self.deleted)
+        } else {
+            None
+        }
+    }
+}
+
+/// Result of `Incremental::reparse`.
+#[derive(Debug)]
+pub struct Reparse<'a> {
+    pub tree: Node<'a>,
+    /// Byte ranges of the new input that were parsed again instead of being reused.
+    pub changed: Vec<Range<usize>>,
+}
+
+/// Reparses edited input, reusing the nodes of memoizable `Custom` rules
+/// that were not touched by the edits.
+///
+/// A memoizable rule has to match the same way wherever it is used and
+/// must not look further than one character past the end of its match.
+pub struct Incremental {
+    pub memoizable: Vec<usize>,
+}
+
+/// The nodes of the old tree that survived the edits, by rule id and new offset.
+/// Nodes are stored as paths of child indices into the old tree.
+struct ReuseTable<'a> {
+    old_input: &'a str,
+    new_input: &'a str,
+    old_tree: Node<'a>,
+    nodes: HashMap<(usize, usize), Vec<usize>>,
+    reused: RefCell<Vec<Range<usize>>>,
+}
+
+impl Incremental {
+    pub fn new() -> Incremental {
+        Incremental {
+            memoizable: Vec::new(),
+        }
+    }
+
+    pub fn new_with_list(memoizable: Vec<usize>) -> Incremental {
+        Incremental { memoizable }
+    }
+
+    pub fn add_rule(&mut self, id: usize) {
+        self.memoizable.push(id);
+    }
+
+    /// Parses `new_input`, which must be the result of applying `edits` to `old_input`,
+    /// with `rule`, reusing parts of `old_tree`, the tree `rule` produced for `old_input`.
+    pub fn reparse<'a>(
+        &self,
+        rule: &Rule<'a>,
+        old_input: &'a str,
+        old_tree: Node<'a>,
+        edits: &[Edit],
+        new_input: &'a str,
+    ) -> Result<Reparse<'a>, ParseError> {
+        let mut nodes = HashMap::new();
+        self.collect(&old_tree, old_input, edits, &mut Vec::new(), &mut nodes);
+        let table = Rc::new(ReuseTable {
+            old_input,
+            new_input,
+            old_tree,
+            nodes,
+            reused: RefCell::new(Vec::new()),
+        });
+
+        let mut handler = Handler::new();
+        let reuse_table = table.clone();
+        handler.set_reuse_handler(move |id, input| reuse_table.reuse(id, input));
+        let tree = rule
+            .apply_with_handler(new_input, &handler)
+            .map_err(|failure| rule.error(new_input, failure))?;
+
+        let mut reused = table.reused.borrow().clone();
+        reused.sort_by_key(|range| range.start);
+        let mut changed = Vec::new();
+        let mut position = 0;
+        for range in reused {
+            if range.start > position {
+                changed.push(position..range.start);
+            }
+            position = position.max(range.end);
+        }
+        if position < new_input.len() {
+            changed.push(position..new_input.len());
+        }
+        Ok(Reparse { tree, changed })
+    }
+
+    fn collect(
+        &self,
+        node: &Node,
+        old_input: &str,
+        edits: &[Edit],
+        path: &mut Vec<usize>,
+        nodes: &mut HashMap<(usize, usize), Vec<usize>>,
+    ) {
+        if self.memoizable.contains(&node.type_id) && !node.content.is_empty() {
+            if let Some(offset) = node.offset_in(old_input) {
+                let mut start = Some(offset);
+                for edit in edits {
+                    start = start.and_then(|start| edit.map(start, start + node.content.len()));
+                }
+                if let Some(start) = start {
+                    nodes.insert((node.type_id, start), path.clone());
+                }
+            }
+        }
+        for (i, child) in node.children.iter().enumerate() {
+            path.push(i);
+            self.collect(child, old_input, edits, path, nodes);
+            path.pop();
+        }
+    }
+}
+
+impl Default for Incremental {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ReuseTable<'a> {
+    fn reuse(&self, id: usize, input: &'a str) -> Option<Node<'a>> {
+        let start = self.new_input.len() - input.len();
+        let path = self.nodes.get(&(id, start))?;
+        let node = path
+            .iter()
+            .fold(&self.old_tree, |node, &i| &node.children[i]);
+        let old_start = node.offset_in(self.old_input)?;
+        self.reused
+            .borrow_mut()
+            .push(start..start + node.content.len());
+        Some(self.rebase(node, start as isize - old_start as isize))
+    }
+
+    /// Copies an old node, pointing its content at the same text in the new input.
+    fn rebase(&self, node: &Node<'a>, shift: isize) -> Node<'a> {
+        let content = match node.offset_in(self.old_input) {
+            Some(offset) => {
+                let start = (offset as isize + shift) as usize;
+                &self.new_input[start..start + node.content.len()]
+            }
+            None => node.content,
+        };
+        Node {
+            type_id: node.type_id,
+            type_name: node.type_name.clone(),
+            content,
+            children: node
+                .children
+                .iter()
+                .map(|child| self.rebase(child, shift))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use incremental::{Edit, Incremental, Reparse};
+    use rule::Rule;
+
+    struct Grammar<'a> {
+        document: Rule<'a>,
+        entry: Rule<'a>,
+        block: Rule<'a>,
+    }
+
+    /// `key=value;` entries, optionally grouped in `{...}` blocks.
+    fn grammar<'a>() -> Grammar<'a> {
+        let letter = sor!(
+            char!('a'),
+            char!('b'),
+            char!('c'),
+            char!('x'),
+            char!('y'),
+            char!('z')
+        );
+        let word = plus!("Word" => letter);
+        let entry = seq!("Entry" => word.clone(), char!('='), word, char!(';'));
+        let block = custom!("Block");
+        let item = sor!(entry.clone(), block.get());
+        let block = block.init(seq!(char!('{'), star!(item.clone()), char!('}')));
+        let document = seq!("Document" => star!(item), eof!());
+        Grammar {
+            document,
+            entry,
+            block,
+        }
+    }
+
+    fn reparse<'a>(
+        grammar: &Grammar<'a>,
+        old_input: &'a str,
+        edits: &[Edit],
+        new_input: &'a str,
+    ) -> Reparse<'a> {
+        let old_tree = grammar.document.parse(old_input).unwrap();
+        let incremental = Incremental::new_with_list(vec![grammar.entry.id, grammar.block.id]);
+        let reparse = incremental
+            .reparse(&grammar.document, old_input, old_tree, edits, new_input)
+            .unwrap();
+        let full = grammar.document.parse(new_input).unwrap();
+        assert_eq!(format!("{:?}", reparse.tree), format!("{:?}", full));
+        reparse
+    }
+
+    #[test]
+    fn edit_apply() {
+        let edits = [
+            Edit::new(0, 1, "xy"),
+            Edit::new(3, 0, "z"),
+            Edit::new(5, 2, ""),
+        ];
+        assert_eq!(Edit::apply("abcdefg", &edits), "xybzcfg");
+    }
+
+    #[test]
+    fn reparse_without_edits_reuses_everything() {
+        let grammar = grammar();
+        let input = "a=b;{c=x;}";
+        let result = reparse(&grammar, input, &[], input);
+        assert!(result.changed.is_empty());
+    }
+
+    #[test]
+    fn reparse_replaced_value() {
+        let old_input = "a=b;c=x;y=z;";
+        let edits = [Edit::new(6, 1, "zz")];
+        let new_input = Edit::apply(old_input, &edits);
+        let grammar = grammar();
+        assert_eq!(new_input, "a=b;c=zz;y=z;");
+        let result = reparse(&grammar, old_input, &edits, &new_input);
+        assert_eq!(result.changed, vec![4..9]);
+    }
+
+    #[test]
+    fn reparse_shifts_following_entries() {
+        let old_input = "a=b;{c=x;y=z;}x=y;";
+        let edits = [Edit::new(0, 0, "zz=a;")];
+        let new_input = Edit::apply(old_input, &edits);
+        let grammar = grammar();
+        let result = reparse(&grammar, old_input, &edits, &new_input);
+        assert_eq!(result.changed, vec![0..5]);
+        let items = &result.tree.children[0].children[0].children;
+        assert_eq!(items[1].content, "a=b;");
+    }
+
+    #[test]
+    fn reparse_inside_block_reuses_siblings() {
+        let old_input = "a=b;{c=x;y=z;}x=y;";
+        let edits = [Edit::new(11, 1, "a")];
+        let new_input = Edit::apply(old_input, &edits);
+        let grammar = grammar();
+        let result = reparse(&grammar, old_input, &edits, &new_input);
+        assert_eq!(result.changed, vec![4..5, 9..14]);
+    }
+
+    #[test]
+    fn reparse_edit_that_breaks_structure() {
+        let old_input = "a=b;{c=x;}y=z;";
+        let edits = [Edit::new(9, 1, ""), Edit::new(13, 0, "}")];
+        let new_input = Edit::apply(old_input, &edits);
+        let grammar = grammar();
+        assert_eq!(new_input, "a=b;{c=x;y=z;}");
+        let result = reparse(&grammar, old_input, &edits, &new_input);
+        // The entries next to the removed and the inserted brace are parsed again
+        assert_eq!(result.changed, vec![4..14]);
+    }
+
+    #[test]
+    fn reparse_reports_errors() {
+        let old_input = "a=b;";
+        let edits = [Edit::new(3, 1, "")];
+        let new_input = Edit::apply(old_input, &edits);
+        let grammar = grammar();
+        let old_tree = grammar.document.parse(old_input).unwrap();
+        let incremental = Incremental::new_with_list(vec![grammar.entry.id]);
+        let error = incremental
+            .reparse(&grammar.document, old_input, old_tree, &edits, &new_input)
+            .unwrap_err();
+        // The missing ';' after the last letter
+        assert_eq!(error.offset, 3);
+        assert_eq!(error.expected, "Char");
+    }
+}
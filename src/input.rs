@@ -0,0 +1,283 @@
+//! Inputs that do not have to be in memory all at once, for compiled programs (see `vm`).
+//!
+//! Rules take their whole input as a `&str`, as the nodes they make point into it. A
+//! `Program` only needs the text at the position it is matching and at the positions it
+//! may still backtrack to, so it can also read from an `Input` that drops the text behind
+//! it, like a `ReaderInput` reading from a file as the program goes.
+//!
+//! Only programs parse an `Input`. Grammars that cannot be compiled (see
+//! `CompileError`), such as those using `recover!` or opaque rules, have to be parsed from
+//! a `&str` holding the whole input.
+//!
+//! How far back a `ReaderInput` can go is limited: it keeps the text from the oldest
+//! position the program may backtrack to, but never more than its limit before the
+//! furthest position the program got to. A program that needs to go back further than
+//! that fails with `InputError::Discarded`. Grammars made of many small items stay well
+//! within any limit, unless an alternative spans many items without a cut.
+//!
+//! ```
+//! use yaast::input::ReaderInput;
+//! use yaast::vm::Program;
+//! use yaast::Grammar;
+//!
+//! let grammar = Grammar::from_peg("Lines <- (Letter+ '\n')* EOF ; Letter <- 'a' / 'b' ;").unwrap();
+//! let program = Program::compile(&grammar.start).unwrap();
+//! let file = "ab\nba\n".repeat(1000);
+//! let mut input = ReaderInput::with_limit(file.as_bytes(), 64);
+//! assert_eq!(program.recognize_input(&mut input).unwrap(), file.len());
+//! ```
+
+use std::fmt;
+use std::io::{self, Read};
+
+use crate::ParseError;
+
+#[derive(Debug)]
+pub enum InputError {
+    Io(io::Error),
+    /// The input is not valid UTF-8 at the offset.
+    InvalidUtf8(usize),
+    /// The text at `offset` was asked for after it was dropped, the oldest text kept being
+    /// at `retained`.
+    Discarded {
+        offset: usize,
+        retained: usize,
+    },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InputError::Io(error) => write!(f, "{}", error),
+            InputError::InvalidUtf8(offset) => write!(f, "invalid UTF-8 at offset {}", offset),
+            InputError::Discarded { offset, retained } => write!(
+                f,
+                "cannot go back to offset {}, the input was only kept from offset {}",
+                offset, retained
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(error: io::Error) -> Self {
+        InputError::Io(error)
+    }
+}
+
+/// Why parsing an `Input` failed.
+#[derive(Debug)]
+pub enum ReadError {
+    Parse(ParseError),
+    Input(InputError),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::Parse(error) => write!(f, "{}", error),
+            ReadError::Input(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReadError {}
+
+impl From<ParseError> for ReadError {
+    fn from(error: ParseError) -> Self {
+        ReadError::Parse(error)
+    }
+}
+
+impl From<InputError> for ReadError {
+    fn from(error: InputError) -> Self {
+        ReadError::Input(error)
+    }
+}
+
+/// Text that is read as it is needed, see the module documentation. Offsets are in bytes
+/// from the start of the input.
+pub trait Input {
+    /// The text from `offset`, which is at a character boundary, on: at least `len` bytes
+    /// of it, or all the rest if there are fewer.
+    fn text(&mut self, offset: usize, len: usize) -> Result<&str, InputError>;
+
+    /// Tells the input that the text before `offset` will not be asked for again.
+    fn release(&mut self, _offset: usize) {}
+}
+
+impl Input for &str {
+    fn text(&mut self, offset: usize, _len: usize) -> Result<&str, InputError> {
+        Ok(&self[offset..])
+    }
+}
+
+/// How much a `ReaderInput` reads at once.
+const CHUNK: usize = 8192;
+
+/// Reads the input from a `Read` as it is needed, dropping the text that was released or
+/// that is more than the limit before the furthest offset asked for.
+pub struct ReaderInput<R> {
+    reader: R,
+    /// The text kept, starting at offset `start`.
+    buffer: String,
+    start: usize,
+    /// Bytes read after the last whole character.
+    partial: Vec<u8>,
+    released: usize,
+    furthest: usize,
+    limit: usize,
+    done: bool,
+}
+
+impl<R: Read> ReaderInput<R> {
+    /// The text kept before the furthest offset asked for, unless another limit is given.
+    pub const DEFAULT_LIMIT: usize = 1 << 20;
+
+    pub fn new(reader: R) -> ReaderInput<R> {
+        ReaderInput::with_limit(reader, Self::DEFAULT_LIMIT)
+    }
+
+    /// Keeps at least `limit` bytes before the furthest offset asked for, which is how far
+    /// back a program can always go.
+    pub fn with_limit(reader: R, limit: usize) -> ReaderInput<R> {
+        ReaderInput {
+            reader,
+            buffer: String::new(),
+            start: 0,
+            partial: Vec::new(),
+            released: 0,
+            furthest: 0,
+            limit,
+            done: false,
+        }
+    }
+
+    /// Offset of the oldest text kept.
+    pub fn retained(&self) -> usize {
+        self.start
+    }
+
+    fn end(&self) -> usize {
+        self.start + self.buffer.len()
+    }
+
+    fn read_chunk(&mut self) -> Result<(), InputError> {
+        let mut bytes = [0; CHUNK];
+        let read = loop {
+            match self.reader.read(&mut bytes) {
+                Ok(read) => break read,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error.into()),
+            }
+        };
+        if read == 0 {
+            self.done = true;
+            return match self.partial.is_empty() {
+                true => Ok(()),
+                false => Err(InputError::InvalidUtf8(self.end())),
+            };
+        }
+        self.partial.extend_from_slice(&bytes[..read]);
+        let valid = match std::str::from_utf8(&self.partial) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(InputError::InvalidUtf8(self.end() + error.valid_up_to())),
+        };
+        let text = std::str::from_utf8(&self.partial[..valid]).expect("checked above");
+        self.buffer.push_str(text);
+        self.partial.drain(..valid);
+        Ok(())
+    }
+
+    /// Drops the text before `offset` that is not needed anymore.
+    fn drop_before(&mut self, offset: usize) {
+        let keep = self
+            .released
+            .max(self.furthest.saturating_sub(self.limit))
+            .min(offset);
+        let mut dropped = keep.saturating_sub(self.start);
+        // Moving the rest is only worth it once it is no bigger than what is dropped.
+        if dropped == 0 || dropped < self.buffer.len() / 2 {
+            return;
+        }
+        while !self.buffer.is_char_boundary(dropped) {
+            dropped -= 1;
+        }
+        self.buffer.drain(..dropped);
+        self.start += dropped;
+    }
+}
+
+impl<R: Read> Input for ReaderInput<R> {
+    fn text(&mut self, offset: usize, len: usize) -> Result<&str, InputError> {
+        if offset < self.start {
+            return Err(InputError::Discarded {
+                offset,
+                retained: self.start,
+            });
+        }
+        while self.end() < offset + len && !self.done {
+            self.read_chunk()?;
+        }
+        self.furthest = self.furthest.max(offset);
+        self.drop_before(offset);
+        Ok(&self.buffer[offset - self.start..])
+    }
+
+    fn release(&mut self, offset: usize) {
+        self.released = self.released.max(offset);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::Trickle;
+
+    #[test]
+    fn reads_as_needed() {
+        let text = "aé€😀b".repeat(10);
+        let mut input = ReaderInput::new(Trickle::new(text.as_bytes()));
+        assert!(input.text(0, 4).unwrap().starts_with("aé€"));
+        assert!(input.text(1, 100).unwrap().len() >= 100);
+        assert_eq!(input.text(1, 1000).unwrap(), &text[1..]);
+        assert_eq!(input.text(text.len(), 1).unwrap(), "");
+    }
+
+    #[test]
+    fn drops_released_text_and_text_past_the_limit() {
+        let text = "0123456789".repeat(10_000);
+        let mut input = ReaderInput::with_limit(text.as_bytes(), 100);
+        input.text(0, 10).unwrap();
+        input.release(50_000);
+        assert_eq!(input.text(50_000, 10).unwrap().len(), 7344);
+        assert_eq!(input.retained(), 50_000);
+        assert!(matches!(
+            input.text(49_999, 1),
+            Err(InputError::Discarded {
+                offset: 49_999,
+                retained: 50_000
+            })
+        ));
+
+        // Without releasing, only the limit is kept.
+        input.text(90_000, 10).unwrap();
+        assert!(input.text(89_900, 1).is_ok());
+        let error = input.text(89_899, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot go back to offset 89899, the input was only kept from offset 89900"
+        );
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let mut input = ReaderInput::new(&b"ab\xffcd"[..]);
+        assert!(matches!(input.text(0, 1), Err(InputError::InvalidUtf8(2))));
+        let mut input = ReaderInput::new(&b"ab\xe2\x82"[..]);
+        assert!(matches!(input.text(0, 10), Err(InputError::InvalidUtf8(2))));
+    }
+}
@@ -1,13 +1,50 @@
+// Lets generated code, which names the crate `::yaast`, be used in the crate itself.
+extern crate self as yaast;
+
 pub mod node;
 pub use node::*;
+pub mod slice;
+pub use slice::Slice;
+pub mod error;
+pub use error::*;
 #[macro_use]
 pub mod rule;
 
 pub mod filter;
 pub use filter::*;
 
+pub mod render;
+
 pub mod rule_handler;
 
+pub mod grammar;
+pub mod peg;
+pub use grammar::*;
+
+pub mod check;
+
+pub mod railroad;
+
+pub mod incremental;
+
+pub mod trace;
+
+pub mod profile;
+
+pub mod coverage;
+pub mod events;
+pub mod from_node;
+pub mod input;
+pub use from_node::{FromNode, FromNodeError};
+pub mod codegen;
+pub mod generate;
+#[cfg(test)]
+mod test_support;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tree;
+pub mod vm;
+
 #[cfg(test)]
 mod tests {
 
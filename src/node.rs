@@ -1,19 +1,149 @@
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::rc::Rc;
 use std::{sync::atomic::AtomicUsize, vec};
 
 use once_cell::sync::Lazy;
 
-use layout::{self, backends::svg::SVGWriter};
-
+use crate::render::{RenderError, RenderOptions};
+use crate::rule::RuleKind;
 use crate::rule_handler::Handler;
+use crate::{ParseResult, Slice};
 
 pub static COUNTER: AtomicUsize = AtomicUsize::new(0);
 
+/// The name of a rule and of the nodes it makes. Cloning it does not copy the text, so
+/// making a node does not either: names known when the program is compiled are static, the
+/// others are shared by the rule and its nodes, and dropped with the last of them.
+#[derive(Clone)]
+pub enum Name {
+    Static(&'static str),
+    Shared(Rc<str>),
+}
+
+impl Name {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Name::Static(name) => name,
+            Name::Shared(name) => name,
+        }
+    }
+}
+
+impl Deref for Name {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&'static str> for Name {
+    fn from(name: &'static str) -> Name {
+        Name::Static(name)
+    }
+}
+
+impl From<String> for Name {
+    fn from(name: String) -> Name {
+        Name::Shared(name.into())
+    }
+}
+
+impl From<&Name> for Name {
+    fn from(name: &Name) -> Name {
+        name.clone()
+    }
+}
+
+impl PartialEq for Name {
+    fn eq(&self, other: &Name) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Name {}
+
+impl PartialEq<str> for Name {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Name {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Name {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl Hash for Name {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self)
+    }
+}
+
+/// Where a whole input is in memory, to find the offsets of parts of it without keeping it
+/// borrowed. The rest of the input a rule is applied to, or that a failure stopped at, is
+/// not always a suffix of it: in the field of a `Prefixed` rule it ends where the field
+/// does.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputBounds {
+    start: usize,
+    len: usize,
+}
+
+impl InputBounds {
+    pub fn of<I: ?Sized + Slice>(input: &I) -> InputBounds {
+        InputBounds {
+            start: input.as_ptr() as usize,
+            len: input.len(),
+        }
+    }
+
+    /// The byte offset of `part` in the input, `None` if it does not point into it.
+    pub fn find<I: ?Sized + Slice>(&self, part: &I) -> Option<usize> {
+        let position = part.as_ptr() as usize;
+        if position >= self.start && position + part.len() <= self.start + self.len {
+            Some(position - self.start)
+        } else {
+            None
+        }
+    }
+
+    /// The byte offset of the rest of the input. A rest that does not point into the input
+    /// is taken to end where it does.
+    pub fn offset<I: ?Sized + Slice>(&self, rest: &I) -> usize {
+        self.find(rest)
+            .unwrap_or_else(|| self.len.saturating_sub(rest.len()))
+    }
+}
+
+/// What a rule matched, in text unless the rule parses bytes.
 #[derive(Debug)] // Add the Debug trait
-pub struct Node<'a> {
+pub struct Node<'a, I: ?Sized + Slice = str> {
     pub type_id: usize,
-    pub type_name: String,
-    pub content: &'a str,
-    pub children: Vec<Node<'a>>,
+    pub type_name: Name,
+    pub content: &'a I,
+    pub children: Vec<Node<'a, I>>,
 }
 
 pub static ROOT_ID: Lazy<usize> =
@@ -22,106 +152,85 @@ pub static ROOT_ID: Lazy<usize> =
 pub static UNREACHABLE_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
-pub trait Parsable<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>>;
+pub trait Parsable<'a, I: ?Sized + Slice = str> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I>;
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &Handler<'a>,
-    ) -> Option<Node<'a>>;
+        name: &Name,
+        handler: &Handler<'a, I>,
+    ) -> ParseResult<'a, I>;
+
+    /// Describes what the rule matches, so the grammar can be walked.
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Opaque
+    }
 }
 
-impl<'a> Node<'a> {
-    pub fn new(content: &'a str, id: usize, name: &String) -> Node<'a> {
+impl<'a, I: ?Sized + Slice> Node<'a, I> {
+    pub fn new(content: &'a I, id: usize, name: impl Into<Name>) -> Node<'a, I> {
         Node {
             content,
             children: Vec::new(),
             type_id: id,
-            type_name: name.clone(),
+            type_name: name.into(),
         }
     }
 
-    pub fn new_empty(id: usize, name: &String) -> Node<'a> {
+    pub fn new_empty(id: usize, name: impl Into<Name>) -> Node<'a, I> {
         Node {
-            content: "",
+            content: I::empty(),
             children: Vec::new(),
             type_id: id,
-            type_name: name.clone(),
+            type_name: name.into(),
         }
     }
 
-    pub fn new_as_root(node: Node<'a>) -> Node<'a> {
+    pub fn new_as_root(node: Node<'a, I>) -> Node<'a, I> {
         Node {
             content: node.content,
             children: vec![node],
             type_id: *ROOT_ID,
-            type_name: "Root".to_string(),
+            type_name: Name::Static("Root"),
         }
     }
 
-    pub fn new_as_unreachable() -> Node<'a> {
+    pub fn new_as_unreachable() -> Node<'a, I> {
         Node {
-            content: "",
+            content: I::empty(),
             children: Vec::new(),
             type_id: *UNREACHABLE_ID,
-            type_name: "Unreachable".to_string(),
+            type_name: Name::Static("Unreachable"),
         }
     }
 
-    pub fn add_child(&mut self, child: Node<'a>) {
+    pub fn add_child(&mut self, child: Node<'a, I>) {
         self.children.push(child);
     }
 
-    pub fn children(&self) -> &Vec<Node<'a>> {
+    pub fn children(&self) -> &Vec<Node<'a, I>> {
         &self.children
     }
 
-    fn to_dot_private(&self, id: &mut usize) -> String {
-        let content_preview = if self.content.len() > 10 {
-            format!("{}...", &self.content[..10])
-        } else {
-            self.content.to_string()
-        };
-
-        let mut result = format!(
-            "{} [label=\"{}\\n\\\"{}\\\"\"];\n",
-            id, self.type_name, content_preview
-        );
-
-        let my_id = *id;
-        *id += 1;
-        for child in &self.children {
-            result += &format!("{} -> {};\n", my_id, *id);
-            result += &child.to_dot_private(id);
-        }
-        result
+    /// Returns the byte offset of this node in `input`,
+    /// or `None` if its content does not point into `input`.
+    pub fn offset_in(&self, input: &I) -> Option<usize> {
+        InputBounds::of(input).find(self.content)
     }
+}
 
+impl Node<'_> {
     pub fn to_dot(&self) -> String {
-        let mut id = 0;
-        let mut result = "digraph G {\n".to_string();
-        result += &self.to_dot_private(&mut id);
-        result += "}\n";
-        result
+        self.to_dot_with(&RenderOptions::default())
     }
 
-    pub fn make_svg(&self, filename: &str) {
-        let mut svg = SVGWriter::new();
-        let dot = self.to_dot();
-        let mut parser = layout::gv::DotParser::new(&dot);
-        let graph = parser.process().unwrap();
-        let mut builder = layout::gv::GraphBuilder::new();
-        builder.visit_graph(&graph);
-        let mut visual_graph = builder.get();
-        visual_graph.do_it(false, false, false, &mut svg);
-        let s = svg.finalize();
-        std::fs::write(filename, s).unwrap();
+    pub fn make_svg(&self, filename: &str) -> Result<(), RenderError> {
+        self.write_svg(filename, &RenderOptions::default())
     }
 }
 
-impl std::cmp::PartialEq for Node<'_> {
+impl<I: ?Sized + Slice> std::cmp::PartialEq for Node<'_, I> {
     fn eq(&self, other: &Self) -> bool {
         if self.children.len() != other.children.len() {
             return false;
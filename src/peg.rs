@@ -0,0 +1,535 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use once_cell::sync::Lazy;
+
+use crate::rule::{
+    Literals, Rule, RuleKind, Seq, Sor, UninitializedRule, LITERALS_ID, SEQ_ID, SOR_ID,
+};
+use crate::rule_handler::Handler;
+use crate::{line_column, Failure, Filter, Grammar, Name, Node, Parsable, ParseResult, COUNTER};
+
+/// Error returned by `Grammar::from_peg`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PegError {
+    /// Line of the grammar text the error is in, starting at 1.
+    pub line: usize,
+    /// Column in characters, starting at 1.
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for PegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for PegError {}
+
+/// Matches one character for which the predicate holds.
+struct CharWhere {
+    predicate: fn(char) -> bool,
+}
+
+impl<'a> Parsable<'a> for CharWhere {
+    fn parse(&self, input: &'a str, id: usize, name: &Name) -> ParseResult<'a> {
+        match input.chars().next() {
+            Some(c) if (self.predicate)(c) => Ok(Node::new(&input[..c.len_utf8()], id, name)),
+            _ => Err(Failure::Backtrack),
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a str,
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a>,
+    ) -> ParseResult<'a> {
+        handler.handle_pre_parse(id);
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+}
+
+static CHAR_WHERE_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+fn char_where<'a>(predicate: fn(char) -> bool) -> Rule<'a> {
+    Rule::new(
+        Box::new(CharWhere { predicate }),
+        *CHAR_WHERE_ID,
+        "CharWhere",
+    )
+}
+
+/// The grammar of the PEG notation produced by `Grammar::to_peg`, written with the
+/// crate's own rules. The ids of the rules that are kept in the tree are stored
+/// to find them again.
+struct Meta<'t> {
+    grammar: Rule<'t>,
+    definition: usize,
+    name: usize,
+    expression: usize,
+    sequence: usize,
+    suffixed: usize,
+    suffix: usize,
+    char_literal: usize,
+    str_literal: usize,
+    cut: usize,
+    reference: usize,
+    group: usize,
+    call: usize,
+    opaque: usize,
+}
+
+impl<'t> Meta<'t> {
+    fn new() -> Meta<'t> {
+        let comment = seq!(char!('#'), star!(char_where(|c| c != '\n')));
+        let spacing = star!(sor!(char_where(char::is_whitespace), comment));
+        let token = |name: &str, c: char| custom!(name => seq!(char!(c), spacing.clone()));
+
+        let name = custom!("Name" => plus!(char_where(|c| c.is_alphanumeric() || c == '_')));
+        let escape = sor!(
+            seq!(
+                str!("\\u{"),
+                plus!(char_where(|c| c.is_ascii_hexdigit())),
+                char!('}')
+            ),
+            seq!(char!('\\'), char_where(|_| true))
+        );
+        let char_literal = custom!("CharLit" => seq!(
+            char!('\''),
+            cut!(),
+            sor!("character" => escape.clone(), char_where(|c| c != '\'' && c != '\\')),
+            char!("closing '" => '\'')
+        ));
+        let str_literal = custom!("StrLit" => seq!(
+            char!('"'),
+            cut!(),
+            star!(sor!(escape, char_where(|c| c != '"' && c != '\\'))),
+            char!("closing \"" => '"')
+        ));
+        let cut = char!("Cut" => '^');
+        let opaque = custom!("Opaque" => seq!(
+            char!('<'),
+            plus!(char_where(|c| c != '>')),
+            char!('>')
+        ));
+
+        let expression = custom!("Expression");
+        let group = custom!("Group" => seq!(
+            char!('('),
+            cut!(),
+            spacing.clone(),
+            expression.get(),
+            char!("')'" => ')')
+        ));
+        let call = custom!("Call" => seq!(
+            name.clone(),
+            char!('('),
+            cut!(),
+            spacing.clone(),
+            expression.get(),
+            star!(seq!(token("','", ','), expression.get())),
+            char!("')'" => ')')
+        ));
+        let reference = custom!("Reference" => name.clone());
+        let primary = sor!(
+            call.clone(),
+            reference.clone(),
+            group.clone(),
+            char_literal.clone(),
+            str_literal.clone(),
+            cut.clone(),
+            opaque.clone()
+        );
+        let suffix = custom!("Suffix" => sor!(char!('*'), char!('+'), char!('?')));
+        let suffixed = custom!("Suffixed" => seq!(
+            primary,
+            spacing.clone(),
+            star!(seq!(suffix.clone(), spacing.clone()))
+        ));
+        let sequence = custom!("Sequence" => star!(suffixed.clone()));
+        let expression = expression.init(seq!(
+            sequence.clone(),
+            star!(seq!(token("'/'", '/'), sequence.clone()))
+        ));
+        let definition = custom!("Definition" => seq!(
+            name.clone(),
+            spacing.clone(),
+            str!("<-"),
+            cut!(),
+            spacing.clone(),
+            expression.clone(),
+            token("';'", ';')
+        ));
+        let grammar = seq!(
+            spacing,
+            cut!(),
+            star!(definition.clone()),
+            eof!("definition or end of input")
+        );
+        Meta {
+            grammar,
+            definition: definition.id,
+            name: name.id,
+            expression: expression.id,
+            sequence: sequence.id,
+            suffixed: suffixed.id,
+            suffix: suffix.id,
+            char_literal: char_literal.id,
+            str_literal: str_literal.id,
+            cut: cut.id,
+            reference: reference.id,
+            group: group.id,
+            call: call.id,
+            opaque: opaque.id,
+        }
+    }
+
+    fn filter(&self) -> Filter {
+        Filter::new_with_list(
+            false,
+            vec![
+                self.definition,
+                self.name,
+                self.expression,
+                self.sequence,
+                self.suffixed,
+                self.suffix,
+                self.char_literal,
+                self.str_literal,
+                self.cut,
+                self.reference,
+                self.group,
+                self.call,
+                self.opaque,
+            ],
+        )
+    }
+}
+
+/// Turns the filtered tree of a grammar text into rules.
+struct Loader<'t, 'a> {
+    meta: Meta<'t>,
+    text: &'t str,
+    rules: HashMap<String, UninitializedRule<'a>>,
+}
+
+impl<'t, 'a> Loader<'t, 'a> {
+    fn error(&self, node: &Node, message: String) -> PegError {
+        let (line, column) = line_column(self.text, node.offset_in(self.text).unwrap_or(0));
+        PegError {
+            line,
+            column,
+            message,
+        }
+    }
+
+    fn expression(&self, node: &Node) -> Result<Rule<'a>, PegError> {
+        let mut options = node
+            .children
+            .iter()
+            .map(|sequence| self.sequence(sequence))
+            .collect::<Result<Vec<_>, _>>()?;
+        if options.len() == 1 {
+            return Ok(options.remove(0));
+        }
+        Ok(Rule::new(Box::new(Sor::new(options)), *SOR_ID, "Sor"))
+    }
+
+    fn sequence(&self, node: &Node) -> Result<Rule<'a>, PegError> {
+        let mut rules = node
+            .children
+            .iter()
+            .map(|suffixed| self.suffixed(suffixed))
+            .collect::<Result<Vec<_>, _>>()?;
+        if rules.len() == 1 {
+            return Ok(rules.remove(0));
+        }
+        Ok(Rule::new(Box::new(Seq { rules }), *SEQ_ID, "Seq"))
+    }
+
+    fn suffixed(&self, node: &Node) -> Result<Rule<'a>, PegError> {
+        let mut rule = self.primary(&node.children[0])?;
+        for suffix in &node.children[1..] {
+            rule = match suffix.content {
+                "*" => star!(rule),
+                "+" => plus!(rule),
+                _ => opt!(rule),
+            };
+        }
+        Ok(rule)
+    }
+
+    fn primary(&self, node: &Node) -> Result<Rule<'a>, PegError> {
+        let meta = &self.meta;
+        let id = node.type_id;
+        if id == meta.char_literal {
+            let text = self.unescape(node, &node.content[1..node.content.len() - 1])?;
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(char!(c)),
+                _ => Err(self.error(node, "expected a single character".to_string())),
+            }
+        } else if id == meta.str_literal {
+            Ok(str!(self.unescape(
+                node,
+                &node.content[1..node.content.len() - 1]
+            )?))
+        } else if id == meta.cut {
+            Ok(cut!())
+        } else if id == meta.reference {
+            match node.content {
+                "EOF" => Ok(eof!()),
+                "FAIL" => Ok(Rule::new(Box::new(Sor::new(Vec::new())), *SOR_ID, "Sor")),
+                name => match self.rules.get(name) {
+                    Some(rule) => Ok(rule.get()),
+                    None => Err(self.error(node, format!("rule {} is not defined", name))),
+                },
+            }
+        } else if id == meta.group {
+            self.expression(&node.children[0])
+        } else if id == meta.call {
+            let name = node.children[0].content;
+            let arguments = &node.children[1..];
+            match (name, arguments.len()) {
+                ("must", 1) => Ok(must!(self.expression(&arguments[0])?)),
+                ("recover", 2) => Ok(recover!(
+                    self.expression(&arguments[0])?,
+                    self.expression(&arguments[1])?
+                )),
+                ("literals", _) | ("keywords", _) => self.literals(name == "keywords", arguments),
+                ("must", _) | ("recover", _) => {
+                    Err(self.error(node, format!("wrong number of arguments for {}", name)))
+                }
+                _ => Err(self.error(node, format!("unknown function {}", name))),
+            }
+        } else {
+            Err(self.error(
+                node,
+                format!("rule {} cannot be written in PEG notation", node.content),
+            ))
+        }
+    }
+
+    /// `literals(...)` or `keywords(...)`, of string literals optionally preceded by
+    /// `ignore_case`.
+    fn literals(&self, longest: bool, arguments: &[Node]) -> Result<Rule<'a>, PegError> {
+        let ignore_case = arguments
+            .first()
+            .is_some_and(|argument| argument.content.trim() == "ignore_case");
+        let mut literals = Vec::new();
+        for argument in &arguments[ignore_case as usize..] {
+            let literal = match self.expression(argument)?.kind() {
+                Some(RuleKind::Str(s)) => s.to_string(),
+                Some(RuleKind::Char(c)) => c.to_string(),
+                _ => return Err(self.error(argument, "expected a literal".to_string())),
+            };
+            literals.push(literal);
+        }
+        Ok(Rule::new(
+            Box::new(Literals::new(literals, longest, ignore_case)),
+            *LITERALS_ID,
+            "Literals",
+        ))
+    }
+
+    fn unescape(&self, node: &Node, text: &str) -> Result<String, PegError> {
+        let mut result = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                result.push(c);
+                continue;
+            }
+            let escaped = match chars.next() {
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('r') => '\r',
+                Some('t') => '\t',
+                Some('\'') => '\'',
+                Some('"') => '"',
+                Some('u') => {
+                    let rest = chars.as_str();
+                    let code = rest
+                        .strip_prefix('{')
+                        .and_then(|rest| rest.split_once('}'))
+                        .and_then(|(hex, _)| u32::from_str_radix(hex, 16).ok())
+                        .and_then(char::from_u32);
+                    match code {
+                        Some(code) => {
+                            let end = rest.find('}').unwrap_or(0);
+                            chars = rest[end + 1..].chars();
+                            code
+                        }
+                        None => return Err(self.error(node, "invalid unicode escape".to_string())),
+                    }
+                }
+                _ => return Err(self.error(node, "unknown escape sequence".to_string())),
+            };
+            result.push(escaped);
+        }
+        Ok(result)
+    }
+}
+
+impl<'a> Grammar<'a> {
+    /// Loads a grammar in the PEG notation written by `Grammar::to_peg`.
+    /// `# comments` are allowed wherever spaces are. The first definition is the start
+    /// rule and all definitions are in `rules`, in the order they are written.
+    /// Every definition is a named rule. `EOF` and `FAIL` cannot be used as rule names.
+    pub fn from_peg(text: &str) -> Result<Grammar<'a>, PegError> {
+        let meta = Meta::new();
+        let tree = match meta.grammar.try_parse(text) {
+            Ok(tree) => meta.filter().filter_ast(tree),
+            Err(error) => {
+                let (line, column) = line_column(text, error.offset);
+                return Err(PegError {
+                    line,
+                    column,
+                    message: format!("expected {}", error.expected),
+                });
+            }
+        };
+        let mut loader = Loader {
+            meta,
+            text,
+            rules: HashMap::new(),
+        };
+
+        let mut names = Vec::new();
+        for definition in &tree.children {
+            let name = &definition.children[0];
+            if loader.rules.contains_key(name.content) {
+                return Err(loader.error(name, format!("rule {} is defined twice", name.content)));
+            }
+            if name.content == "EOF" || name.content == "FAIL" {
+                return Err(loader.error(name, format!("{} is a reserved name", name.content)));
+            }
+            loader
+                .rules
+                .insert(name.content.to_string(), custom!(name.content));
+            names.push(name.content);
+        }
+        if names.is_empty() {
+            return Err(PegError {
+                line: 1,
+                column: 1,
+                message: "the grammar has no rules".to_string(),
+            });
+        }
+
+        let mut rules = Vec::new();
+        for (definition, name) in tree.children.iter().zip(names) {
+            let body = loader.expression(&definition.children[1])?;
+            rules.push(loader.rules[name].init(body));
+        }
+        Ok(Grammar {
+            start: rules[0].clone(),
+            rules,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+
+    #[test]
+    fn peg_round_trip() {
+        let listing = "Document <- Value EOF ;\n\
+            Value <- \"null\" / Digits / List ;\n\
+            Digits <- ('0' / '1')+ ;\n\
+            List <- '[' (Value (',' Value)*)? ']' ;\n\
+            Escapes <- must(recover('x', ';' ^)) '\\'' \"\\\\\\n\\t\\\"\" () FAIL '\\u{1}' ;\n";
+        let grammar = Grammar::from_peg(listing).unwrap();
+        assert_eq!(grammar.to_peg(), listing);
+        assert_eq!(grammar.start.name, "Document");
+        let names: Vec<_> = grammar.rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Document", "Value", "Digits", "List", "Escapes"]
+        );
+        let input = "[10,[],null]";
+        let tree = grammar.start.parse(input).unwrap();
+        assert_eq!(tree.content, input);
+        assert!(grammar.start.parse("[10,]").is_none());
+    }
+
+    #[test]
+    fn peg_literals() {
+        let listing = "Keyword <- keywords(ignore_case, \"select\", \"from\") ;\n\
+            Operator <- literals(\"<=\", \"<\", \"=\") ;\n";
+        let grammar = Grammar::from_peg(listing).unwrap();
+        assert_eq!(grammar.to_peg(), listing);
+        assert_eq!(grammar.start.parse("FROM x").unwrap().content, "FROM");
+        let operator = grammar.get("Operator").unwrap();
+        assert_eq!(operator.parse("<=").unwrap().content, "<=");
+
+        let error = Grammar::from_peg("A <- literals('a', 'b' 'c') ;")
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "1:20: expected a literal");
+    }
+
+    #[test]
+    fn peg_comments_and_spacing() {
+        let text = "# A list of a's\n\
+            List<-'a'* # any number\n\
+            ;\n\
+            \n   Other <- List'b'?;";
+        let grammar = Grammar::from_peg(text).unwrap();
+        assert_eq!(grammar.to_peg(), "List <- 'a'* ;\nOther <- List 'b'? ;\n");
+        let list = grammar.get("List").unwrap();
+        assert_eq!(list.parse("aab").unwrap().content, "aa");
+    }
+
+    #[test]
+    fn peg_syntax_errors() {
+        let error = Grammar::from_peg("A <- 'a'\nB <- 'b' ;").err().unwrap();
+        assert_eq!(error.to_string(), "2:3: expected ';'");
+        let error = Grammar::from_peg("A <- ('a' 'b' ;").err().unwrap();
+        assert_eq!(error.to_string(), "1:15: expected ')'");
+        let error = Grammar::from_peg("A <- 'ab' ;").err().unwrap();
+        assert_eq!(error.to_string(), "1:8: expected closing '");
+        let error = Grammar::from_peg("A <- 'a' ;\n!").err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "2:1: expected definition or end of input"
+        );
+    }
+
+    #[test]
+    fn peg_semantic_errors() {
+        let error = |text| Grammar::from_peg(text).err().unwrap().to_string();
+        assert_eq!(error("A <- B ;"), "1:6: rule B is not defined");
+        assert_eq!(
+            error("A <- 'a' ;\nA <- 'b' ;"),
+            "2:1: rule A is defined twice"
+        );
+        assert_eq!(error("EOF <- 'a' ;"), "1:1: EOF is a reserved name");
+        assert_eq!(
+            error("A <- <Native> ;"),
+            "1:6: rule <Native> cannot be written in PEG notation"
+        );
+        assert_eq!(
+            error("A <- must('a', 'b') ;"),
+            "1:6: wrong number of arguments for must"
+        );
+        assert_eq!(error("A <- not('a') ;"), "1:6: unknown function not");
+        assert_eq!(error("A <- \"\\q\" ;"), "1:6: unknown escape sequence");
+        assert_eq!(error("A <- '' ;"), "1:7: expected character");
+        assert_eq!(error("# nothing"), "1:1: the grammar has no rules");
+    }
+}
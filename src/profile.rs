@@ -0,0 +1,259 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::rule::Rule;
+use crate::rule_handler::{Handler, Listener};
+use crate::{Failure, Node};
+
+/// What a profile is sorted by, always in descending order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortBy {
+    SelfTime,
+    TotalTime,
+    Invocations,
+    Backtracked,
+}
+
+/// Statistics of all applications of one rule id.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RuleStats {
+    pub id: usize,
+    pub name: String,
+    pub invocations: usize,
+    pub successes: usize,
+    pub failures: usize,
+    /// Bytes matched by successful applications.
+    pub consumed: usize,
+    /// Bytes matched by sub-rules that were thrown away because this rule failed.
+    pub backtracked: usize,
+    /// Time spent in the rule including its sub-rules.
+    /// Counted once per application, so it adds up for recursive rules.
+    pub total_time: Duration,
+    /// Time spent in the rule excluding its sub-rules.
+    pub self_time: Duration,
+}
+
+/// Statistics of a parse, per rule id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub rules: Vec<RuleStats>,
+}
+
+struct Frame {
+    id: usize,
+    start: Instant,
+    children_time: Duration,
+    matched: usize,
+}
+
+/// A `Listener` collecting a `Profile`.
+pub struct Profiler {
+    stats: RefCell<HashMap<usize, RuleStats>>,
+    stack: RefCell<Vec<Frame>>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            stats: RefCell::new(HashMap::new()),
+            stack: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the statistics collected so far, sorted by self time.
+    pub fn profile(&self) -> Profile {
+        let mut profile = Profile {
+            rules: self.stats.borrow().values().cloned().collect(),
+        };
+        profile.sort_by(SortBy::SelfTime);
+        profile
+    }
+
+    fn finish(&self, consumed: Option<usize>) {
+        let mut stack = self.stack.borrow_mut();
+        let frame = match stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let elapsed = frame.start.elapsed();
+        let mut stats = self.stats.borrow_mut();
+        let rule = stats.get_mut(&frame.id).unwrap();
+        rule.total_time += elapsed;
+        rule.self_time += elapsed.saturating_sub(frame.children_time);
+        match consumed {
+            Some(consumed) => {
+                rule.successes += 1;
+                rule.consumed += consumed;
+            }
+            None => {
+                rule.failures += 1;
+                rule.backtracked += frame.matched;
+            }
+        }
+        if let Some(parent) = stack.last_mut() {
+            parent.children_time += elapsed;
+            parent.matched += consumed.unwrap_or(0);
+        }
+    }
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Listener<'a> for Profiler {
+    fn pre_parse(&self, rule: &Rule<'a>, _input: &'a str) {
+        let mut stats = self.stats.borrow_mut();
+        let stats = stats.entry(rule.id).or_insert_with(|| RuleStats {
+            id: rule.id,
+            name: rule.name.to_string(),
+            ..Default::default()
+        });
+        stats.invocations += 1;
+        self.stack.borrow_mut().push(Frame {
+            id: rule.id,
+            start: Instant::now(),
+            children_time: Duration::ZERO,
+            matched: 0,
+        });
+    }
+
+    fn success(&self, _rule: &Rule<'a>, _input: &'a str, node: &Node<'a>) {
+        self.finish(Some(node.content.len()));
+    }
+
+    fn failure(&self, _rule: &Rule<'a>, _input: &'a str, _failure: &Failure<'a>) {
+        self.finish(None);
+    }
+}
+
+impl Profile {
+    /// Parses the input with the rule, collecting statistics about every rule applied.
+    pub fn record<'a>(rule: &Rule<'a>, input: &'a str) -> (Option<Node<'a>>, Profile) {
+        let profiler = Rc::new(Profiler::new());
+        let mut handler = Handler::new();
+        handler.add_listener(profiler.clone());
+        let node = rule.parse_with_handler(input, &handler);
+        (node, profiler.profile())
+    }
+
+    pub fn sort_by(&mut self, key: SortBy) {
+        self.rules.sort_by(|a, b| match key {
+            SortBy::SelfTime => b.self_time.cmp(&a.self_time),
+            SortBy::TotalTime => b.total_time.cmp(&a.total_time),
+            SortBy::Invocations => b.invocations.cmp(&a.invocations),
+            SortBy::Backtracked => b.backtracked.cmp(&a.backtracked),
+        });
+    }
+
+    /// Total number of bytes that were matched and then thrown away by backtracking.
+    pub fn backtracked(&self) -> usize {
+        self.rules.iter().map(|rule| rule.backtracked).sum()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&RuleStats> {
+        self.rules.iter().find(|rule| rule.id == id)
+    }
+
+    /// Renders the statistics as a table, in their current order.
+    pub fn to_table(&self) -> String {
+        let width = self
+            .rules
+            .iter()
+            .map(|rule| rule.name.chars().count())
+            .chain(std::iter::once(4))
+            .max()
+            .unwrap_or(4);
+        let mut result = format!(
+            "{:<width$}  {:>8}  {:>8}  {:>8}  {:>10}  {:>11}  {:>10}  {:>10}\n",
+            "rule", "calls", "success", "failure", "consumed", "backtracked", "total ms", "self ms",
+        );
+        for rule in &self.rules {
+            let _ = writeln!(
+                result,
+                "{:<width$}  {:>8}  {:>8}  {:>8}  {:>10}  {:>11}  {:>10.3}  {:>10.3}",
+                rule.name,
+                rule.invocations,
+                rule.successes,
+                rule.failures,
+                rule.consumed,
+                rule.backtracked,
+                rule.total_time.as_secs_f64() * 1000.0,
+                rule.self_time.as_secs_f64() * 1000.0,
+            );
+        }
+        let _ = writeln!(result, "backtracked bytes: {}", self.backtracked());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use profile::{Profile, SortBy};
+    use rule::{CHAR_ID, SEQ_ID, SOR_ID, STR_ID};
+
+    #[test]
+    fn profile_counts_invocations() {
+        let rule = star!(sor!(
+            seq!(char!('a'), char!('c')),
+            seq!(char!('a'), char!('b'))
+        ));
+        let (node, profile) = Profile::record(&rule, "abab");
+        assert_eq!(node.unwrap().content, "abab");
+        let char = profile.get(*CHAR_ID).unwrap();
+        assert_eq!(char.name, "Char");
+        assert_eq!(char.invocations, 10);
+        assert_eq!(char.successes, 6);
+        assert_eq!(char.failures, 4);
+        assert_eq!(char.consumed, 6);
+        let seq = profile.get(*SEQ_ID).unwrap();
+        assert_eq!(seq.invocations, 6);
+        assert_eq!(seq.successes, 2);
+        assert_eq!(seq.failures, 4);
+        let sor = profile.get(*SOR_ID).unwrap();
+        assert_eq!(sor.invocations, 3);
+        assert_eq!(sor.consumed, 4);
+    }
+
+    #[test]
+    fn profile_counts_backtracked_bytes() {
+        let rule = sor!(
+            seq!("Long" => str!("abc"), str!("def"), char!('x')),
+            str!("abcdefy")
+        );
+        let (_, profile) = Profile::record(&rule, "abcdefy");
+        assert_eq!(profile.backtracked(), 6);
+        let long = profile.rules.iter().find(|r| r.name == "Long").unwrap();
+        // The wrapped Seq throws away the matched bytes, so Long has nothing left to throw away
+        assert_eq!(long.backtracked, 0);
+        assert_eq!(profile.get(*SEQ_ID).unwrap().backtracked, 6);
+        assert_eq!(profile.get(*STR_ID).unwrap().consumed, 13);
+    }
+
+    #[test]
+    fn profile_sorting_and_table() {
+        let rule = plus!(sor!(char!('b'), char!('a')));
+        let (_, mut profile) = Profile::record(&rule, "aa");
+        profile.sort_by(SortBy::Invocations);
+        let names: Vec<_> = profile.rules.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["Char", "Sor", "Plus"]);
+        let table = profile.to_table();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[0].starts_with("rule  "));
+        // 'b' is skipped before each 'a' and only tried at the end.
+        assert!(
+            lines[1].starts_with("Char         4         2         2           2            0  ")
+        );
+        assert_eq!(lines[4], "backtracked bytes: 0");
+        profile.sort_by(SortBy::Backtracked);
+        profile.sort_by(SortBy::TotalTime);
+        assert_eq!(profile.rules[0].name, "Plus");
+    }
+}
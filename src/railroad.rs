@@ -0,0 +1,504 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::grammar::{char_literal, str_literal};
+use crate::rule::{Rule, RuleKind};
+use crate::Grammar;
+
+/// Radius of the arcs joining the lines.
+const ARC: i32 = 10;
+/// Horizontal space between the items of a sequence.
+const GAP: i32 = 10;
+/// Vertical space between the branches of a choice.
+const SPACE: i32 = 8;
+/// Half the height of a box.
+const BOX: i32 = 11;
+const CHAR_WIDTH: i32 = 8;
+const PADDING: i32 = 10;
+const MARGIN: i32 = 20;
+
+const STYLE: &str = "<style>\
+path { stroke: #333; stroke-width: 2; fill: none; } \
+rect { stroke: #333; stroke-width: 2; fill: #dfe; } \
+rect.rule { fill: #def; } \
+rect.special { fill: #eee; stroke-dasharray: 4 2; } \
+text { font: 13px monospace; text-anchor: middle; } \
+a text { fill: #05a; text-decoration: underline; }\
+</style>";
+
+/// A railroad diagram, drawn left to right along its baseline.
+enum Diagram {
+    /// A literal.
+    Terminal(String),
+    /// A reference to a named rule.
+    NonTerminal(String),
+    /// Anything else that is drawn as a box, like `EOF`.
+    Special(String),
+    /// A line matching nothing.
+    Skip,
+    Sequence(Vec<Diagram>),
+    /// The first branch is on the baseline, the others below it.
+    Choice(Vec<Diagram>),
+    /// The item with a line looping back below it.
+    OneOrMore(Box<Diagram>),
+}
+
+/// Size of a diagram: its width and how far it reaches above and below its baseline.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Size {
+    width: i32,
+    up: i32,
+    down: i32,
+}
+
+impl Diagram {
+    fn new(rule: &Rule, top: bool) -> Diagram {
+        if !top && Grammar::is_named(rule) {
+            return Diagram::NonTerminal(rule.name.to_string());
+        }
+        let kind = match rule.kind() {
+            Some(kind) => kind,
+            None => return Diagram::Special("<uninitialized>".to_string()),
+        };
+        match kind {
+            RuleKind::Char(c) => Diagram::Terminal(char_literal(c)),
+            RuleKind::Str(s) => Diagram::Terminal(str_literal(s)),
+            RuleKind::Eof => Diagram::Special("EOF".to_string()),
+            RuleKind::Cut => Diagram::Special("^".to_string()),
+            RuleKind::Seq([]) => Diagram::Skip,
+            RuleKind::Seq([rule]) => Diagram::new(rule, false),
+            RuleKind::Seq(rules) => {
+                Diagram::Sequence(rules.iter().map(|rule| Diagram::new(rule, false)).collect())
+            }
+            RuleKind::Sor([]) => Diagram::Special("FAIL".to_string()),
+            RuleKind::Sor([rule]) => Diagram::new(rule, false),
+            RuleKind::Sor(rules) => {
+                Diagram::Choice(rules.iter().map(|rule| Diagram::new(rule, false)).collect())
+            }
+            RuleKind::Star(inner) => Diagram::Choice(vec![
+                Diagram::Skip,
+                Diagram::OneOrMore(Box::new(Diagram::new(inner, false))),
+            ]),
+            RuleKind::Plus(inner) => Diagram::OneOrMore(Box::new(Diagram::new(inner, false))),
+            RuleKind::Opt(inner) => {
+                Diagram::Choice(vec![Diagram::Skip, Diagram::new(inner, false)])
+            }
+            // Committing and recovering do not change what the rule matches
+            RuleKind::Must(inner) | RuleKind::Recover(inner, _) | RuleKind::Custom(inner) => {
+                Diagram::new(inner, false)
+            }
+            RuleKind::Literals(literals) => match &literals.literals[..] {
+                [] => Diagram::Special("FAIL".to_string()),
+                [literal] => Diagram::Terminal(str_literal(literal)),
+                literals => Diagram::Choice(
+                    literals
+                        .iter()
+                        .map(|literal| Diagram::Terminal(str_literal(literal)))
+                        .collect(),
+                ),
+            },
+            RuleKind::Opaque => Diagram::Special(format!("<{}>", rule.name)),
+        }
+    }
+
+    fn size(&self) -> Size {
+        match self {
+            Diagram::Terminal(text) | Diagram::NonTerminal(text) | Diagram::Special(text) => Size {
+                width: text.chars().count() as i32 * CHAR_WIDTH + 2 * PADDING,
+                up: BOX,
+                down: BOX,
+            },
+            Diagram::Skip => Size {
+                width: 0,
+                up: 0,
+                down: 0,
+            },
+            Diagram::Sequence(items) => {
+                let sizes: Vec<_> = items.iter().map(Diagram::size).collect();
+                Size {
+                    width: sizes.iter().map(|size| size.width).sum::<i32>()
+                        + GAP * (sizes.len() as i32 - 1),
+                    up: sizes.iter().map(|size| size.up).max().unwrap_or(0),
+                    down: sizes.iter().map(|size| size.down).max().unwrap_or(0),
+                }
+            }
+            Diagram::Choice(items) => {
+                let sizes: Vec<_> = items.iter().map(Diagram::size).collect();
+                let baselines = Diagram::baselines(&sizes);
+                Size {
+                    width: sizes.iter().map(|size| size.width).max().unwrap_or(0) + 4 * ARC,
+                    up: sizes[0].up,
+                    down: baselines[baselines.len() - 1] + sizes[sizes.len() - 1].down,
+                }
+            }
+            Diagram::OneOrMore(item) => {
+                let size = item.size();
+                Size {
+                    width: size.width + 2 * ARC,
+                    up: size.up,
+                    down: (size.down + SPACE).max(2 * ARC),
+                }
+            }
+        }
+    }
+
+    /// Offsets of the baselines of the branches of a choice from the first one.
+    fn baselines(sizes: &[Size]) -> Vec<i32> {
+        let mut baselines = vec![0];
+        for pair in sizes.windows(2) {
+            let last = baselines[baselines.len() - 1];
+            baselines.push(last + (pair[0].down + SPACE + pair[1].up).max(2 * ARC));
+        }
+        baselines
+    }
+
+    /// Draws the diagram with its baseline starting at `(x, y)`.
+    fn render(&self, x: i32, y: i32, link: &dyn Fn(&str) -> String, out: &mut String) {
+        match self {
+            Diagram::Terminal(text) => render_box(text, "", x, y, out),
+            Diagram::NonTerminal(name) => {
+                let _ = write!(out, "<a href=\"{}\">", escape(&link(name)));
+                render_box(name, "rule", x, y, out);
+                *out += "</a>";
+            }
+            Diagram::Special(text) => render_box(text, "special", x, y, out),
+            Diagram::Skip => {}
+            Diagram::Sequence(items) => {
+                let mut x = x;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        let _ = write!(out, "<path d=\"M{} {}h{}\"/>", x, y, GAP);
+                        x += GAP;
+                    }
+                    item.render(x, y, link, out);
+                    x += item.size().width;
+                }
+            }
+            Diagram::Choice(items) => {
+                let sizes: Vec<_> = items.iter().map(Diagram::size).collect();
+                let width = self.size().width;
+                for ((item, size), baseline) in
+                    items.iter().zip(&sizes).zip(Diagram::baselines(&sizes))
+                {
+                    let branch = y + baseline;
+                    if baseline == 0 {
+                        let _ = write!(out, "<path d=\"M{} {}h{}\"/>", x, y, 2 * ARC);
+                    } else {
+                        let _ = write!(
+                            out,
+                            "<path d=\"M{} {}a{r} {r} 0 0 1 {r} {r}V{}a{r} {r} 0 0 0 {r} {r}\"/>",
+                            x,
+                            y,
+                            branch - ARC,
+                            r = ARC
+                        );
+                    }
+                    item.render(x + 2 * ARC, branch, link, out);
+                    let end = x + 2 * ARC + size.width;
+                    if baseline == 0 {
+                        let _ = write!(out, "<path d=\"M{} {}H{}\"/>", end, y, x + width);
+                    } else {
+                        let _ = write!(
+                            out,
+                            "<path d=\"M{} {}H{}a{r} {r} 0 0 0 {r} -{r}V{}a{r} {r} 0 0 1 {r} -{r}\"/>",
+                            end,
+                            branch,
+                            x + width - 2 * ARC,
+                            y + ARC,
+                            r = ARC
+                        );
+                    }
+                }
+            }
+            Diagram::OneOrMore(item) => {
+                let size = item.size();
+                let bottom = y + self.size().down;
+                let _ = write!(out, "<path d=\"M{} {}h{}\"/>", x, y, ARC);
+                item.render(x + ARC, y, link, out);
+                let _ = write!(
+                    out,
+                    "<path d=\"M{} {}h{r}M{} {}a{r} {r} 0 0 1 {r} {r}V{}a{r} {r} 0 0 1 -{r} {r}H{}a{r} {r} 0 0 1 -{r} -{r}V{}a{r} {r} 0 0 1 {r} -{r}\"/>",
+                    x + ARC + size.width,
+                    y,
+                    x + ARC + size.width,
+                    y,
+                    bottom - ARC,
+                    x + ARC,
+                    y + ARC,
+                    r = ARC
+                );
+            }
+        }
+    }
+}
+
+fn render_box(text: &str, class: &str, x: i32, y: i32, out: &mut String) {
+    let width = text.chars().count() as i32 * CHAR_WIDTH + 2 * PADDING;
+    let _ = write!(
+        out,
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"",
+        x,
+        y - BOX,
+        width,
+        2 * BOX
+    );
+    if class.is_empty() {
+        let _ = write!(out, " rx=\"{}\"", BOX);
+    } else {
+        let _ = write!(out, " class=\"{}\"", class);
+    }
+    let _ = write!(
+        out,
+        "/><text x=\"{}\" y=\"{}\">{}</text>",
+        x + width / 2,
+        y + 4,
+        escape(text)
+    );
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Name of the SVG file of a rule, without characters that are unsafe in file names.
+/// `Grammar::write_railroad` adds a number to it when another rule gets the same one.
+pub fn file_name(name: &str) -> String {
+    format!("{}.svg", anchor(name))
+}
+
+fn anchor(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+impl<'a> Grammar<'a> {
+    /// The anchors, and names of the SVG files without `.svg`, of the named rules: `anchor`
+    /// of their names, followed by a number for names that only differ in characters it
+    /// replaces, or in case, which some file systems ignore.
+    fn anchors(&self) -> HashMap<&str, String> {
+        let mut anchors = HashMap::new();
+        let mut taken = HashSet::new();
+        for rule in &self.rules {
+            let base = anchor(&rule.name);
+            let mut unique = base.clone();
+            let mut number = 2;
+            while !taken.insert(unique.to_lowercase()) {
+                unique = format!("{}_{}", base, number);
+                number += 1;
+            }
+            anchors.insert(rule.name.as_str(), unique);
+        }
+        anchors
+    }
+
+    /// Draws the railroad diagram of the definition of a rule as an SVG document.
+    /// Named rules used by it are drawn as boxes linking to `link(name)`.
+    pub fn railroad(&self, rule: &Rule<'a>, link: &dyn Fn(&str) -> String) -> String {
+        let diagram = match rule.kind() {
+            Some(RuleKind::Custom(inner)) => Diagram::new(inner, true),
+            _ => Diagram::new(rule, true),
+        };
+        let size = diagram.size();
+        let width = size.width + 2 * MARGIN + 2 * GAP;
+        let height = size.up + size.down + 2 * MARGIN;
+        let y = MARGIN + size.up;
+        let mut result = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\">{}",
+            STYLE,
+            w = width,
+            h = height
+        );
+        let _ = write!(
+            result,
+            "<path d=\"M{x} {}v{}M{x} {y}h{gap}\"/>",
+            y - BOX / 2,
+            BOX,
+            x = MARGIN,
+            y = y,
+            gap = GAP
+        );
+        diagram.render(MARGIN + GAP, y, link, &mut result);
+        let end = MARGIN + GAP + size.width;
+        let _ = write!(
+            result,
+            "<path d=\"M{end} {y}h{gap}M{} {}v{}\"/></svg>",
+            end + GAP,
+            y - BOX / 2,
+            BOX,
+            end = end,
+            y = y,
+            gap = GAP
+        );
+        result
+    }
+
+    /// An HTML page showing the railroad diagrams of all named rules, with their
+    /// PEG notation, linked to each other.
+    pub fn railroad_index(&self) -> String {
+        let anchors = self.anchors();
+        let anchor = |name: &str| anchors.get(name).cloned().unwrap_or_else(|| anchor(name));
+        let link = |name: &str| format!("#{}", anchor(name));
+        let mut result = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Grammar</title>\n\
+            <style>body { font-family: sans-serif; } pre { background: #f4f4f4; padding: 4px; }</style>\n\
+            </head>\n<body>\n<h1>Grammar</h1>\n<ul>\n",
+        );
+        for rule in &self.rules {
+            let _ = writeln!(
+                result,
+                "<li><a href=\"{}\">{}</a></li>",
+                escape(&link(&rule.name)),
+                escape(&rule.name)
+            );
+        }
+        result += "</ul>\n";
+        for rule in &self.rules {
+            let definition = match rule.kind() {
+                Some(RuleKind::Custom(inner)) => Grammar::expression(inner),
+                Some(_) => Grammar::expression(rule),
+                None => "<uninitialized>".to_string(),
+            };
+            let _ = write!(
+                result,
+                "<h2 id=\"{}\">{}</h2>\n<pre>{}</pre>\n{}\n",
+                escape(&anchor(&rule.name)),
+                escape(&rule.name),
+                escape(&definition),
+                self.railroad(rule, &link)
+            );
+        }
+        result += "</body>\n</html>\n";
+        result
+    }
+
+    /// Writes one SVG file per named rule, as named by `file_name` with a number added
+    /// to tell apart rules that would get the same one, and an `index.html` showing all of
+    /// them into the directory.
+    pub fn write_railroad(&self, directory: &str) -> std::io::Result<()> {
+        let directory = std::path::Path::new(directory);
+        std::fs::create_dir_all(directory)?;
+        let anchors = self.anchors();
+        let file_name = |name: &str| match anchors.get(name) {
+            Some(anchor) => format!("{}.svg", anchor),
+            None => file_name(name),
+        };
+        for rule in &self.rules {
+            let svg = self.railroad(rule, &file_name);
+            std::fs::write(directory.join(file_name(&rule.name)), svg)?;
+        }
+        std::fs::write(directory.join("index.html"), self.railroad_index())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use railroad::{file_name, Diagram, Size};
+
+    #[test]
+    fn railroad_sizes() {
+        let word = Diagram::Terminal("'a'".to_string());
+        assert_eq!(
+            word.size(),
+            Size {
+                width: 44,
+                up: 11,
+                down: 11
+            }
+        );
+        let optional = Diagram::Choice(vec![Diagram::Skip, word]);
+        assert_eq!(
+            optional.size(),
+            Size {
+                width: 84,
+                up: 0,
+                down: 31
+            }
+        );
+        let repeated = Diagram::OneOrMore(Box::new(Diagram::NonTerminal("Rule".to_string())));
+        assert_eq!(
+            repeated.size(),
+            Size {
+                width: 72,
+                up: 11,
+                down: 20
+            }
+        );
+    }
+
+    #[test]
+    fn railroad_draws_rules() {
+        let value = custom!("Value");
+        let list = seq!("List" => char!('['), opt!(seq!(value.get(), star!(seq!(char!(','), value.get())))), char!(']'));
+        let value = value.init(sor!(str!("a<b"), list, eof!()));
+        let grammar = Grammar::new(value.clone());
+        let svg = grammar.railroad(&value, &file_name);
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(">&quot;a&lt;b&quot;</text>"));
+        assert!(svg.contains("<a href=\"List.svg\"><rect"));
+        assert!(svg.contains("class=\"special\"/><text x=\"72\" y=\"95\">EOF</text>"));
+        let svg = grammar.railroad(grammar.get("List").unwrap(), &|name| format!("#{}", name));
+        assert_eq!(svg.matches("<a href=\"#Value\">").count(), 2);
+        assert_eq!(svg.matches("<rect").count(), 5);
+    }
+
+    #[test]
+    fn railroad_index_and_files() {
+        let rule = seq!("Pair" => plus!("Key" => char!('k')), char!('='), custom!("Missing").get());
+        let grammar = Grammar::new(rule);
+        let index = grammar.railroad_index();
+        assert!(index.contains("<li><a href=\"#Key\">Key</a></li>"));
+        assert!(index.contains("<h2 id=\"Pair\">Pair</h2>\n<pre>Key '=' Missing</pre>\n<svg"));
+        assert!(index.contains("<h2 id=\"Key\">Key</h2>\n<pre>'k'+</pre>"));
+        assert!(index.contains("<pre>&lt;uninitialized&gt;</pre>"));
+        assert_eq!(file_name("a b/c"), "a_b_c.svg");
+
+        let directory = std::env::temp_dir().join(format!("yaast-railroad-{}", std::process::id()));
+        grammar.write_railroad(directory.to_str().unwrap()).unwrap();
+        for file in ["Pair.svg", "Key.svg", "Missing.svg", "index.html"] {
+            assert!(directory.join(file).exists(), "{} was not written", file);
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn railroad_files_of_similar_names() {
+        let rule =
+            seq!("a.b" => custom!("a b").get(), custom!("A_B").get(), custom!("a_b_2").get());
+        let grammar = Grammar::new(rule);
+        let index = grammar.railroad_index();
+        for (anchor, name) in [
+            ("a_b", "a.b"),
+            ("a_b_2", "a b"),
+            ("A_B_3", "A_B"),
+            ("a_b_2_2", "a_b_2"),
+        ] {
+            assert!(
+                index.contains(&format!("<h2 id=\"{}\">{}</h2>", anchor, name)),
+                "{}",
+                name
+            );
+        }
+
+        let directory = std::env::temp_dir().join(format!("yaast-similar-{}", std::process::id()));
+        grammar.write_railroad(directory.to_str().unwrap()).unwrap();
+        let svg = std::fs::read_to_string(directory.join("a_b.svg")).unwrap();
+        assert!(svg.contains("<a href=\"a_b_2.svg\">"));
+        assert!(svg.contains("<a href=\"A_B_3.svg\">"));
+        for file in ["a_b_2.svg", "A_B_3.svg", "a_b_2_2.svg"] {
+            assert!(directory.join(file).exists(), "{} was not written", file);
+        }
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,516 @@
+use std::fmt;
+use std::fmt::Write;
+
+use layout::backends::svg::SVGWriter;
+
+use crate::{Filter, Node};
+
+/// Fill colors used by `RenderOptions::color_by_rule`.
+const PALETTE: [&str; 8] = [
+    "#fde2e2", "#e2f0fd", "#e4fde2", "#fdf6e2", "#efe2fd", "#e2fdf8", "#fde2f5", "#eeeeee",
+];
+
+/// Error returned when a tree or grammar cannot be rendered.
+#[derive(Debug)]
+pub enum RenderError {
+    /// The generated DOT could not be laid out.
+    Layout(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Layout(message) => write!(f, "could not lay out graph: {}", message),
+            RenderError::Io(error) => write!(f, "could not write file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RenderError::Layout(_) => None,
+            RenderError::Io(error) => Some(error),
+        }
+    }
+}
+
+impl From<std::io::Error> for RenderError {
+    fn from(error: std::io::Error) -> Self {
+        RenderError::Io(error)
+    }
+}
+
+/// How `Node::to_dot_with` and `Node::to_svg` draw a tree.
+pub struct RenderOptions {
+    /// Nodes deeper than this are replaced by a single node counting them.
+    /// The root has depth 0.
+    pub max_depth: Option<usize>,
+    /// Number of characters of the content shown in each node.
+    pub preview: usize,
+    /// Fills the nodes of each rule id with its own color.
+    pub color_by_rule: bool,
+    /// Nodes not allowed by the filter are left out, their children take their place.
+    /// The root is always shown.
+    pub filter: Option<Filter>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            max_depth: None,
+            preview: 10,
+            color_by_rule: false,
+            filter: None,
+        }
+    }
+}
+
+impl RenderOptions {
+    pub fn new() -> RenderOptions {
+        RenderOptions::default()
+    }
+
+    /// The children of a node that are shown, with the ones of hidden nodes in their place.
+    pub(crate) fn visible_children<'n, 'a>(&self, node: &'n Node<'a>) -> Vec<&'n Node<'a>> {
+        let mut children = Vec::new();
+        for child in &node.children {
+            match &self.filter {
+                Some(filter) if !filter.is_allowed(child.type_id) => {
+                    children.extend(self.visible_children(child))
+                }
+                _ => children.push(child),
+            }
+        }
+        children
+    }
+
+    /// The number of shown nodes below a node.
+    pub(crate) fn count_descendants(&self, node: &Node) -> usize {
+        self.visible_children(node)
+            .iter()
+            .map(|child| 1 + self.count_descendants(child))
+            .sum()
+    }
+}
+
+/// The first `length` characters of the content, quoted and escaped.
+pub(crate) fn preview(content: &str, length: usize) -> String {
+    match content.char_indices().nth(length) {
+        Some((end, _)) => format!("{:?}...", &content[..end]),
+        None => format!("{:?}", content),
+    }
+}
+
+/// Escapes text for a quoted DOT string.
+pub(crate) fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A JSON string literal that can also be embedded in a `<script>` element.
+pub(crate) fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => result += "\\\"",
+            '\\' => result += "\\\\",
+            '\n' => result += "\\n",
+            '\r' => result += "\\r",
+            '\t' => result += "\\t",
+            '<' | '>' | '&' => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c if (c as u32) < 0x20 => {
+                let _ = write!(result, "\\u{:04x}", c as u32);
+            }
+            c => result.push(c),
+        }
+    }
+    result + "\""
+}
+
+/// Lays out a graph in DOT notation and renders it as SVG.
+pub(crate) fn dot_to_svg(dot: &str) -> Result<String, RenderError> {
+    let mut svg = SVGWriter::new();
+    let mut parser = layout::gv::DotParser::new(dot);
+    let graph = parser.process().map_err(RenderError::Layout)?;
+    let mut builder = layout::gv::GraphBuilder::new();
+    builder.visit_graph(&graph);
+    let mut visual_graph = builder.get();
+    visual_graph.do_it(false, false, false, &mut svg);
+    Ok(svg.finalize())
+}
+
+/// How `TreeView` prints a tree.
+#[derive(Debug, Clone)]
+pub struct TreeOptions {
+    /// Shows the byte range of each node, relative to the root.
+    pub spans: bool,
+    /// Shows the rule id of each node.
+    pub ids: bool,
+    /// Prints the content as an escaped string literal instead of as it is.
+    pub escape: bool,
+    /// Number of characters of the content shown, `None` for all of it.
+    pub width: Option<usize>,
+    /// Colors the rule names with ANSI escape codes, each rule id in its own color.
+    pub colors: bool,
+}
+
+impl Default for TreeOptions {
+    fn default() -> Self {
+        TreeOptions {
+            spans: false,
+            ids: false,
+            escape: true,
+            width: Some(40),
+            colors: false,
+        }
+    }
+}
+
+/// Prints a tree with box-drawing characters, one node per line:
+///
+/// ```text
+/// Seq "ab"
+/// ├─ Char "a"
+/// └─ Char "b"
+/// ```
+pub struct TreeView<'n, 'a> {
+    pub node: &'n Node<'a>,
+    pub options: TreeOptions,
+}
+
+impl TreeView<'_, '_> {
+    fn write_node(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        node: &Node,
+        start: usize,
+        prefix: &str,
+        last: Option<bool>,
+    ) -> fmt::Result {
+        let options = &self.options;
+        match last {
+            Some(true) => write!(f, "{}└─ ", prefix)?,
+            Some(false) => write!(f, "{}├─ ", prefix)?,
+            None => {}
+        }
+        if options.colors {
+            write!(
+                f,
+                "\x1b[{}m{}\x1b[0m",
+                31 + node.type_id % 6,
+                node.type_name
+            )?;
+        } else {
+            write!(f, "{}", node.type_name)?;
+        }
+        if options.ids {
+            write!(f, " #{}", node.type_id)?;
+        }
+        if options.spans {
+            write!(f, " {}..{}", start, start + node.content.len())?;
+        }
+        let content = match options.width {
+            Some(width) => match node.content.char_indices().nth(width) {
+                Some((end, _)) => &node.content[..end],
+                None => node.content,
+            },
+            None => node.content,
+        };
+        if options.escape {
+            write!(f, " {:?}", content)?;
+        } else {
+            write!(f, " \"{}\"", content)?;
+        }
+        if content.len() < node.content.len() {
+            write!(f, "...")?;
+        }
+        writeln!(f)?;
+
+        let prefix = match last {
+            Some(true) => format!("{}   ", prefix),
+            Some(false) => format!("{}│  ", prefix),
+            None => String::new(),
+        };
+        let mut position = start;
+        for (i, child) in node.children.iter().enumerate() {
+            // Zero-width nodes made by `Node::new_empty` do not point into the input
+            let start = child.offset_in(self.node.content).unwrap_or(position);
+            let last = i + 1 == node.children.len();
+            self.write_node(f, child, start, &prefix, Some(last))?;
+            position = start + child.content.len();
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for TreeView<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write_node(f, self.node, 0, "", None)
+    }
+}
+
+impl fmt::Display for Node<'_> {
+    /// Prints the tree with the default `TreeOptions`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tree(TreeOptions::default()).fmt(f)
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Renders the tree in DOT notation.
+    pub fn to_dot_with(&self, options: &RenderOptions) -> String {
+        let mut result = "digraph G {\n".to_string();
+        let mut id = 0;
+        self.write_dot(options, 0, &mut id, &mut result);
+        result += "}\n";
+        result
+    }
+
+    fn write_dot(&self, options: &RenderOptions, depth: usize, id: &mut usize, out: &mut String) {
+        let my_id = *id;
+        *id += 1;
+        *out += &format!(
+            "{} [label=\"{}\\n{}\"",
+            my_id,
+            escape_dot(&self.type_name),
+            escape_dot(&preview(self.content, options.preview))
+        );
+        if options.color_by_rule {
+            *out += &format!(
+                " style=filled fillcolor=\"{}\"",
+                PALETTE[self.type_id % PALETTE.len()]
+            );
+        }
+        *out += "];\n";
+
+        let children = options.visible_children(self);
+        if children.is_empty() {
+            return;
+        }
+        if options.max_depth.is_some_and(|max| depth >= max) {
+            let hidden = options.count_descendants(self);
+            *out += &format!("{} -> {};\n", my_id, *id);
+            *out += &format!("{} [label=\"{} more\" color=gray];\n", *id, hidden);
+            *id += 1;
+            return;
+        }
+        for child in children {
+            *out += &format!("{} -> {};\n", my_id, *id);
+            child.write_dot(options, depth + 1, id, out);
+        }
+    }
+
+    /// A `Display` implementation printing the tree as configured by `options`.
+    pub fn tree(&self, options: TreeOptions) -> TreeView<'_, 'a> {
+        TreeView {
+            node: self,
+            options,
+        }
+    }
+
+    /// Renders the tree as JSON. Every node is an object with the fields
+    /// `rule`, `id`, `start` and `end` (byte offsets relative to the root),
+    /// `content` and `children`.
+    pub fn to_json(&self) -> String {
+        let mut result = String::new();
+        self.write_json(self.content, 0, &mut result);
+        result
+    }
+
+    fn write_json(&self, root: &str, start: usize, out: &mut String) {
+        let _ = write!(
+            out,
+            "{{\"rule\":{},\"id\":{},\"start\":{},\"end\":{},\"content\":{},\"children\":[",
+            json_string(&self.type_name),
+            self.type_id,
+            start,
+            start + self.content.len(),
+            json_string(self.content)
+        );
+        let mut position = start;
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                *out += ",";
+            }
+            let start = child.offset_in(root).unwrap_or(position);
+            child.write_json(root, start, out);
+            position = start + child.content.len();
+        }
+        *out += "]}";
+    }
+
+    /// Renders the tree as an S-expression: `(Name "content")` for leaves
+    /// and `(Name child...)` for every other node.
+    pub fn to_sexpr(&self) -> String {
+        if self.children.is_empty() {
+            return format!("({} {:?})", self.type_name, self.content);
+        }
+        let children: Vec<_> = self.children.iter().map(Node::to_sexpr).collect();
+        format!("({} {})", self.type_name, children.join(" "))
+    }
+
+    /// Renders the tree as an SVG document.
+    pub fn to_svg(&self, options: &RenderOptions) -> Result<String, RenderError> {
+        dot_to_svg(&self.to_dot_with(options))
+    }
+
+    /// Renders the tree as SVG and writes it to a file.
+    pub fn write_svg(&self, filename: &str, options: &RenderOptions) -> Result<(), RenderError> {
+        std::fs::write(filename, self.to_svg(options)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use render::{RenderError, RenderOptions, TreeOptions};
+    use rule::{CHAR_ID, SEQ_ID};
+
+    #[test]
+    fn render_default_dot() {
+        let rule = seq!(char!('a'), str!("\"\\"));
+        let input = "a\"\\";
+        let node = rule.parse(input).unwrap();
+        assert_eq!(
+            node.to_dot(),
+            "digraph G {\n\
+            0 [label=\"Seq\\n\\\"a\\\\\\\"\\\\\\\\\\\"\"];\n\
+            0 -> 1;\n\
+            1 [label=\"Char\\n\\\"a\\\"\"];\n\
+            0 -> 2;\n\
+            2 [label=\"Str\\n\\\"\\\\\\\"\\\\\\\\\\\"\"];\n\
+            }\n"
+        );
+        assert!(node.to_svg(&RenderOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn render_preview_is_char_safe() {
+        let rule = star!(sor!(char!('é'), char!('ü')));
+        let input = "éüéüéüéüéüéüéü";
+        let node = rule.parse(input).unwrap();
+        let options = RenderOptions {
+            preview: 3,
+            ..Default::default()
+        };
+        let dot = node.to_dot_with(&options);
+        assert!(dot.starts_with("digraph G {\n0 [label=\"Star\\n\\\"éüé\\\"...\"];\n"));
+        assert!(node.to_svg(&RenderOptions::new()).is_ok());
+    }
+
+    #[test]
+    fn render_depth_filter_and_colors() {
+        let rule = seq!(seq!(char!('a'), seq!(char!('b'), char!('c'))), char!('d'));
+        let input = "abcd";
+        let node = rule.parse(input).unwrap();
+        let options = RenderOptions {
+            max_depth: Some(1),
+            ..Default::default()
+        };
+        let dot = node.to_dot_with(&options);
+        assert!(dot.contains("2 [label=\"4 more\" color=gray];\n"));
+        assert_eq!(dot.matches("label=").count(), 4);
+
+        let options = RenderOptions {
+            filter: Some(Filter::new_with_list(true, vec![*SEQ_ID])),
+            color_by_rule: true,
+            ..Default::default()
+        };
+        let dot = node.to_dot_with(&options);
+        assert_eq!(dot.matches("label=\"Char").count(), 4);
+        assert_eq!(dot.matches("label=\"Seq").count(), 1);
+        assert_eq!(dot.matches("0 -> ").count(), 4);
+        let color = format!("fillcolor=\"{}\"", render::PALETTE[*CHAR_ID % 8]);
+        assert_eq!(dot.matches(&color).count(), 4);
+    }
+
+    #[test]
+    fn render_reports_errors() {
+        let rule = char!('a');
+        let node = rule.parse("a").unwrap();
+        let error = node
+            .write_svg("/nonexistent/directory/tree.svg", &RenderOptions::new())
+            .unwrap_err();
+        assert!(matches!(error, RenderError::Io(_)));
+        assert!(render::dot_to_svg("digraph {").is_err());
+    }
+
+    #[test]
+    fn tree_view_default() {
+        let rule = seq!(star!(seq!(char!('a'), char!('b'))), str!("c\n"));
+        let input = "ababc\n";
+        let node = rule.parse(input).unwrap();
+        assert_eq!(
+            node.to_string(),
+            "Seq \"ababc\\n\"\n\
+            ├─ Star \"abab\"\n\
+            │  ├─ Seq \"ab\"\n\
+            │  │  ├─ Char \"a\"\n\
+            │  │  └─ Char \"b\"\n\
+            │  └─ Seq \"ab\"\n\
+            │     ├─ Char \"a\"\n\
+            │     └─ Char \"b\"\n\
+            └─ Str \"c\\n\"\n"
+        );
+    }
+
+    #[test]
+    fn tree_view_options() {
+        let rule = seq!("Pair" => plus!(char!('é')), opt!(char!('x')), str!("\"b"));
+        let input = "ééé\"b";
+        let node = rule.parse(input).unwrap();
+        let options = TreeOptions {
+            spans: true,
+            escape: false,
+            width: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            node.tree(options).to_string(),
+            "Pair 0..8 \"éé\"...\n\
+            └─ Seq 0..8 \"éé\"...\n   \
+               ├─ Plus 0..6 \"éé\"...\n   \
+               │  ├─ Char 0..2 \"é\"\n   \
+               │  ├─ Char 2..4 \"é\"\n   \
+               │  └─ Char 4..6 \"é\"\n   \
+               ├─ Opt 6..6 \"\"\n   \
+               └─ Str 6..8 \"\"b\"\n"
+        );
+        let options = TreeOptions {
+            ids: true,
+            colors: true,
+            width: None,
+            ..Default::default()
+        };
+        let char = rule::CHAR_ID.to_string();
+        let tree = node.tree(options).to_string();
+        assert!(tree.contains(&format!(
+            "\x1b[{}mChar\x1b[0m #{} \"é\"",
+            31 + *rule::CHAR_ID % 6,
+            char
+        )));
+        assert!(tree.starts_with(&format!("\x1b[{}mPair", 31 + rule.id % 6)));
+    }
+
+    #[test]
+    fn render_json_and_sexpr() {
+        let rule = seq!("Pair" => plus!(char!('é')), opt!(char!('x')), str!("\"<"));
+        let input = "éé\"<";
+        let node = rule.parse(input).unwrap();
+        assert_eq!(
+            node.to_sexpr(),
+            "(Pair (Seq (Plus (Char \"é\") (Char \"é\")) (Opt \"\") (Str \"\\\"<\")))"
+        );
+        let json = node.children[0].to_json();
+        assert!(json.starts_with("{\"rule\":\"Seq\",\"id\":"));
+        assert!(json.contains("\"rule\":\"Opt\",\"id\":"));
+        assert!(json.contains("\"start\":4,\"end\":4,\"content\":\"\",\"children\":[]}"));
+        assert!(
+            json.ends_with("\"start\":4,\"end\":6,\"content\":\"\\\"\\u003c\",\"children\":[]}]}")
+        );
+    }
+}
@@ -0,0 +1,580 @@
+//! Rules for binary data, parsing bytes instead of text.
+//!
+//! Rules are generic over their input (see `Slice`), so every combinator works on a
+//! `&[u8]` as it does on a `&str`, making nodes that point into the bytes. The rules here
+//! are the ones matching bytes: `byte!(0x7f)` matches a byte, `bytes!(b"PNG")` a byte string
+//! and `byte_range!(0x30..=0x39)` a byte in the range. `Integer` reads fixed-width unsigned
+//! integers, and `Prefixed` a field whose length in bytes is such an integer read before
+//! it, possibly with other rules in between.
+//!
+//! Grammars, compiled programs (see `vm`) and generated code only work with rules parsing
+//! text, as do the tools built on them.
+//!
+//! ```
+//! use yaast::rule::integer_value;
+//! use yaast::*;
+//!
+//! let input = b"YA\x03\x01abc\x00\x02";
+//! // A magic number, then records of a length, a type byte and that many bytes.
+//! let record = prefixed!(U8, then byte_range!(1..=3));
+//! let file = seq!(bytes!(b"YA"), star!(record), eof!());
+//! let node = file.parse(input).unwrap();
+//! let records = &node.children[1].children;
+//! assert_eq!(records[0].content, b"\x03\x01abc");
+//! assert_eq!(integer_value(&records[1].children[0]), Some(0));
+//! ```
+
+use crate::COUNTER;
+
+use super::*;
+use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use once_cell::sync::Lazy;
+
+pub static INTEGER_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+pub static PREFIXED_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+/// Matches one byte, like `One` matches a character.
+pub struct Byte {
+    pub b: u8,
+}
+
+impl<'a> Parsable<'a, [u8]> for Byte {
+    fn parse(&self, input: &'a [u8], id: usize, name: &Name) -> ParseResult<'a, [u8]> {
+        if input.first() == Some(&self.b) {
+            Ok(Node::new(&input[..1], id, name))
+        } else {
+            Err(Failure::Backtrack)
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a [u8],
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a, [u8]>,
+    ) -> ParseResult<'a, [u8]> {
+        handler.handle_pre_parse(id);
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, [u8]> {
+        RuleKind::Char(self.b)
+    }
+}
+
+/// Matches a byte string, like `Str` matches text.
+pub struct Bytes {
+    pub bytes: Vec<u8>,
+}
+
+impl<'a> Parsable<'a, [u8]> for Bytes {
+    fn parse(&self, input: &'a [u8], id: usize, name: &Name) -> ParseResult<'a, [u8]> {
+        if input.starts_with(&self.bytes) {
+            Ok(Node::new(&input[..self.bytes.len()], id, name))
+        } else {
+            Err(Failure::Backtrack)
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a [u8],
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a, [u8]>,
+    ) -> ParseResult<'a, [u8]> {
+        handler.handle_pre_parse(id);
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, [u8]> {
+        RuleKind::Str(&self.bytes)
+    }
+}
+
+/// Matches an unsigned integer of 1 to 8 bytes, making a node named after its format,
+/// such as `U16LE`. See `integer_value` for its value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Integer {
+    width: usize,
+    big_endian: bool,
+}
+
+impl Integer {
+    pub const U8: Integer = Integer::new(1, false);
+    pub const U16_LE: Integer = Integer::new(2, false);
+    pub const U16_BE: Integer = Integer::new(2, true);
+    pub const U32_LE: Integer = Integer::new(4, false);
+    pub const U32_BE: Integer = Integer::new(4, true);
+    pub const U64_LE: Integer = Integer::new(8, false);
+    pub const U64_BE: Integer = Integer::new(8, true);
+
+    /// The names of the formats by width, little endian first.
+    const NAMES: [[&'static str; 2]; 8] = [
+        ["U8", "U8"],
+        ["U16LE", "U16BE"],
+        ["U24LE", "U24BE"],
+        ["U32LE", "U32BE"],
+        ["U40LE", "U40BE"],
+        ["U48LE", "U48BE"],
+        ["U56LE", "U56BE"],
+        ["U64LE", "U64BE"],
+    ];
+
+    /// An integer of `width` bytes. Panics unless the width is 1 to 8, as the value has to
+    /// fit in a `u64`.
+    pub const fn new(width: usize, big_endian: bool) -> Integer {
+        assert!(width >= 1 && width <= 8, "integers are 1 to 8 bytes wide");
+        Integer { width, big_endian }
+    }
+
+    pub fn width(self) -> usize {
+        self.width
+    }
+
+    pub fn big_endian(self) -> bool {
+        self.big_endian
+    }
+
+    /// The name of its nodes.
+    pub fn name(self) -> &'static str {
+        Integer::NAMES[self.width - 1][usize::from(self.big_endian)]
+    }
+
+    /// The format with the name, if it is the name of one.
+    pub fn from_name(name: &str) -> Option<Integer> {
+        Integer::NAMES
+            .iter()
+            .enumerate()
+            .find_map(|(width, names)| {
+                let big_endian = names.iter().position(|format| *format == name)?;
+                Some(Integer::new(width + 1, big_endian == 1))
+            })
+    }
+
+    /// The value of the bytes of an integer in this format.
+    pub fn value(self, bytes: &[u8]) -> u64 {
+        let fold = |value: u64, byte: &u8| value << 8 | u64::from(*byte);
+        match self.big_endian {
+            true => bytes.iter().fold(0, fold),
+            false => bytes.iter().rev().fold(0, fold),
+        }
+    }
+
+    /// The bytes of the value in this format, if it fits.
+    pub fn format(self, value: u64) -> Option<Vec<u8>> {
+        if self.width < 8 && value >> (8 * self.width) != 0 {
+            return None;
+        }
+        let mut bytes = value.to_le_bytes()[..self.width].to_vec();
+        if self.big_endian {
+            bytes.reverse();
+        }
+        Some(bytes)
+    }
+
+    pub fn rule<'a>(self) -> Rule<'a, [u8]> {
+        Rule::new(Box::new(self), *INTEGER_ID, self.name())
+    }
+}
+
+/// The value of the node of an `Integer` rule.
+pub fn integer_value(node: &Node<[u8]>) -> Option<u64> {
+    if node.type_id != *INTEGER_ID {
+        return None;
+    }
+    Integer::from_name(&node.type_name).map(|format| format.value(node.content))
+}
+
+impl<'a> Parsable<'a, [u8]> for Integer {
+    fn parse(&self, input: &'a [u8], id: usize, name: &Name) -> ParseResult<'a, [u8]> {
+        if input.len() >= self.width {
+            Ok(Node::new(&input[..self.width], id, name))
+        } else {
+            Err(Failure::Backtrack)
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a [u8],
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a, [u8]>,
+    ) -> ParseResult<'a, [u8]> {
+        handler.handle_pre_parse(id);
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+}
+
+/// Matches an integer, then the `gap` rule if there is one, then a field of as many bytes
+/// as the value of the integer.
+///
+/// The node has the nodes of the integer, the gap and the field as children. The field is
+/// matched by the `field` rule on its bytes alone, failing unless it takes all of them, or
+/// by any bytes when there is none, making a `Bytes` node.
+pub struct Prefixed<'a> {
+    pub length: Integer,
+    pub gap: Option<Rule<'a, [u8]>>,
+    pub field: Option<Rule<'a, [u8]>>,
+    length_rule: Rule<'a, [u8]>,
+}
+
+impl<'a> Prefixed<'a> {
+    pub fn new(
+        length: Integer,
+        gap: Option<Rule<'a, [u8]>>,
+        field: Option<Rule<'a, [u8]>>,
+    ) -> Prefixed<'a> {
+        Prefixed {
+            length,
+            gap,
+            field,
+            length_rule: length.rule(),
+        }
+    }
+
+    fn parse_from(
+        &self,
+        input: &'a [u8],
+        id: usize,
+        name: &Name,
+        handler: Option<&Handler<'a, [u8]>>,
+    ) -> ParseResult<'a, [u8]> {
+        let apply = |rule: &Rule<'a, [u8]>, input: &'a [u8]| match handler {
+            Some(handler) => rule.apply_with_handler(input, handler),
+            None => rule.apply(input),
+        };
+        let add_child = |node: &mut Node<'a, [u8]>, child| match handler {
+            Some(handler) => handler.add_child(node, child),
+            None => node.add_child(child),
+        };
+        let mut node = Node::new_empty(id, name);
+        let length = apply(&self.length_rule, input)?;
+        let mut start = length.content.len();
+        let length_content = length.content;
+        add_child(&mut node, length);
+        if let Some(gap) = &self.gap {
+            let gap = apply(gap, &input[start..])?;
+            start += gap.content.len();
+            add_child(&mut node, gap);
+        }
+        let len = usize::try_from(self.length.value(length_content)).unwrap_or(usize::MAX);
+        if len > input.len() - start {
+            return Err(Failure::Backtrack);
+        }
+        match &self.field {
+            Some(field) => match apply(field, &input[start..start + len])? {
+                field if field.content.len() == len => add_child(&mut node, field),
+                _ => return Err(Failure::Backtrack),
+            },
+            // Made without a rule, so it is kept even when the tree is not built.
+            None => node.add_child(Node::new(&input[start..start + len], *PREFIXED_ID, "Bytes")),
+        }
+        node.content = &input[..start + len];
+        Ok(node)
+    }
+}
+
+impl<'a> Parsable<'a, [u8]> for Prefixed<'a> {
+    fn parse(&self, input: &'a [u8], id: usize, name: &Name) -> ParseResult<'a, [u8]> {
+        self.parse_from(input, id, name, None)
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a [u8],
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a, [u8]>,
+    ) -> ParseResult<'a, [u8]> {
+        handler.handle_pre_parse(id);
+        match self.parse_from(input, id, name, Some(handler)) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+}
+
+/// Matches one byte.
+#[macro_export]
+macro_rules! byte {
+    ($name:expr => $byte:expr) => {
+        $crate::custom!($name => $crate::byte!($byte))
+    };
+    ($byte:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Byte { b: $byte }),
+            *$crate::rule::CHAR_ID,
+            "Char",
+        )
+    };
+}
+
+/// Matches a byte string, such as `b"\x89PNG"`.
+#[macro_export]
+macro_rules! bytes {
+    ($name:expr => $bytes:expr) => {
+        $crate::custom!($name => $crate::bytes!($bytes))
+    };
+    ($bytes:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Bytes {
+                bytes: $bytes.to_vec(),
+            }),
+            *$crate::rule::STR_ID,
+            "Str",
+        )
+    };
+}
+
+/// Matches a byte in the inclusive range, such as `byte_range!(0x30..=0x39)`, like a `Sor`
+/// of its bytes.
+#[macro_export]
+macro_rules! byte_range {
+    ($name:expr => $range:expr) => {
+        $crate::custom!($name => $crate::byte_range!($range))
+    };
+    ($range:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Sor::new(
+                ($range).map(|byte: u8| $crate::byte!(byte)).collect(),
+            )),
+            *$crate::rule::SOR_ID,
+            "Sor",
+        )
+    };
+}
+
+/// Matches an unsigned integer in one of the formats of `Integer`, such as
+/// `integer!(U16_LE)`.
+#[macro_export]
+macro_rules! integer {
+    ($name:expr => $format:ident) => {
+        $crate::custom!($name => $crate::integer!($format))
+    };
+    ($format:ident) => {
+        $crate::rule::Integer::$format.rule()
+    };
+}
+
+/// Matches a field prefixed by its length, read as an `Integer` in the format:
+/// `prefixed!(U16_BE)` for any bytes, `prefixed!(U16_BE, field)` for a rule that takes all
+/// of them, and `prefixed!(U16_BE, then gap, field)` for a rule matched between the length
+/// and the field.
+#[macro_export]
+macro_rules! prefixed {
+    ($name:expr => $($rest:tt)*) => {
+        $crate::custom!($name => $crate::prefixed!($($rest)*))
+    };
+    ($format:ident) => {
+        $crate::prefixed!(@ $format, None, None)
+    };
+    ($format:ident, then $gap:expr, $field:expr) => {
+        $crate::prefixed!(@ $format, Some($gap), Some($field))
+    };
+    ($format:ident, then $gap:expr) => {
+        $crate::prefixed!(@ $format, Some($gap), None)
+    };
+    ($format:ident, $field:expr) => {
+        $crate::prefixed!(@ $format, None, Some($field))
+    };
+    (@ $format:ident, $gap:expr, $field:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Prefixed::new(
+                $crate::rule::Integer::$format,
+                $gap,
+                $field,
+            )),
+            *$crate::rule::PREFIXED_ID,
+            "Prefixed",
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rule::{integer_value, Integer};
+
+    #[test]
+    fn bytes_and_ranges() {
+        let input = b"\x89PNG\xff\x80\x00";
+        let rule = seq!(
+            bytes!(b"\x89PNG"),
+            plus!(byte_range!(0x80..=0xff)),
+            byte!(0)
+        );
+        assert_eq!(rule.parse(input).unwrap().content, input);
+        assert_eq!(rule.parse(b"\x89PNG\x7f\x00"), None);
+        // Errors are at offsets in the bytes, which need not be valid UTF-8.
+        let error = rule.try_parse(b"\x89PNG\xff\x01").unwrap_err();
+        assert_eq!(error.to_string(), "expected Char at offset 5");
+    }
+
+    #[test]
+    fn integers() {
+        let input = &[0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08];
+        fn value<'a>(rule: rule::Rule<'a, [u8]>, input: &'a [u8]) -> Option<u64> {
+            rule.parse(input).as_ref().and_then(integer_value)
+        }
+        assert_eq!(value(integer!(U8), input), Some(0x01));
+        assert_eq!(value(integer!(U16_LE), input), Some(0x0201));
+        assert_eq!(value(integer!(U16_BE), input), Some(0x0102));
+        assert_eq!(value(integer!(U32_LE), input), Some(0x04030201));
+        assert_eq!(value(integer!(U32_BE), input), Some(0x01020304));
+        assert_eq!(value(integer!(U64_BE), input), Some(0x0102030405060708));
+        assert_eq!(value(Integer::new(3, true).rule(), input), Some(0x010203));
+        assert_eq!(integer!(U32_BE).parse(&input[..3]), None);
+        let node = integer!("Length" => U8).parse(input).unwrap();
+        assert_eq!(node.type_name, "Length");
+        assert_eq!(integer_value(&node), None);
+        assert_eq!(integer_value(&node.children[0]), Some(1));
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!(Integer::new(3, false).name(), "U24LE");
+        assert_eq!(Integer::from_name("U64BE"), Some(Integer::U64_BE));
+        assert_eq!(Integer::from_name("U8"), Some(Integer::U8));
+        assert_eq!(Integer::from_name("U128LE"), None);
+        let bytes = Integer::U16_BE.format(0x1234).unwrap();
+        assert_eq!(bytes, [0x12, 0x34]);
+        assert_eq!(Integer::U16_BE.value(&bytes), 0x1234);
+        assert_eq!(Integer::U8.format(0x100), None);
+        assert_eq!(Integer::U64_LE.format(u64::MAX), Some(vec![0xff; 8]));
+    }
+
+    #[test]
+    #[should_panic(expected = "integers are 1 to 8 bytes wide")]
+    fn integers_wider_than_a_u64() {
+        Integer::new(9, false);
+    }
+
+    #[test]
+    fn length_prefixed_fields() {
+        let node = prefixed!(U16_BE).parse(b"\x00\x03\xe9t\xe9!").unwrap();
+        assert_eq!(node.content, b"\x00\x03\xe9t\xe9");
+        assert_eq!(node.children[1].type_name, "Bytes");
+        assert_eq!(prefixed!(U16_LE).parse(b"\x00\x03\xe9t\xe9!"), None);
+
+        // The field only sees its own bytes and has to match all of them.
+        let word = plus!(byte_range!(b'a'..=b'z'));
+        let rule = prefixed!("Name" => U8, word);
+        let node = rule.parse(b"\x02ab").unwrap();
+        assert_eq!(node.content, b"\x02ab");
+        assert_eq!(node.children[0].children[1].content, b"ab");
+        assert_eq!(rule.parse(b"\x02abc").unwrap().content, b"\x02ab");
+        assert_eq!(rule.parse(b"\x02a1"), None);
+        assert_eq!(rule.parse(b"\x01a").unwrap().content.len(), 2);
+        let handler = rule_handler::Handler::new();
+        assert_eq!(
+            rule.parse_with_handler(b"\x02ab", &handler),
+            rule.parse(b"\x02ab")
+        );
+    }
+
+    #[test]
+    fn rules_between_the_length_and_the_field() {
+        let input = b"\x02\x00#ab!";
+        let rule = prefixed!(U16_LE, then byte!(b'#'), plus!(byte_range!(b'a'..=b'z')));
+        let node = rule.parse(input).unwrap();
+        assert_eq!(node.content, b"\x02\x00#ab");
+        let names: Vec<_> = node
+            .children
+            .iter()
+            .map(|child| child.type_name.as_str())
+            .collect();
+        assert_eq!(names, ["U16LE", "Char", "Plus"]);
+        assert_eq!(rule.parse(b"\x02\x00ab"), None);
+        // A length of 35 bytes, which are not there.
+        assert_eq!(prefixed!(U8, then byte!(b'#')).parse(&input[2..]), None);
+        let node = prefixed!(U8, then byte!(b'#')).parse(&input[1..]).unwrap();
+        assert_eq!(
+            (
+                node.children[2].type_name.as_str(),
+                node.children[2].content
+            ),
+            ("Bytes", &b""[..])
+        );
+    }
+
+    #[test]
+    fn cuts_inside_fields() {
+        let rule = seq!(
+            prefixed!(U8, seq!(byte!(b'a'), cut!(), byte!(b'b'))),
+            byte!(b'c')
+        );
+        let error = rule.try_parse(b"\x02axc").unwrap_err();
+        assert_eq!((error.offset, error.committed), (2, true));
+
+        // Where a field backtracks is found in the same way.
+        let other = seq!(prefixed!(U8, seq!(byte!(b'a'), byte!(b'b'))), byte!(b'c'));
+        let error = other.try_parse(b"\x02axc").unwrap_err();
+        assert_eq!((error.offset, error.committed), (2, false));
+
+        // Recovering can skip past the end of the field.
+        let input = b"\x02axc;";
+        let rule = recover!(rule, byte!(b';'));
+        let node = rule.parse_with_recovery(input).unwrap().0;
+        assert_eq!(node.content, input);
+        assert_eq!(node.diagnostics(input)[0].offset, 2);
+    }
+
+    #[test]
+    fn sor_skips_options_by_their_first_byte() {
+        let input = b"\xffb";
+        let rule = sor!(seq!(byte!(0xfe), byte!(b'a')), bytes!(b"\xffb"));
+        let mut handler = rule_handler::Handler::new();
+        let skipped = std::rc::Rc::new(std::cell::Cell::new(0));
+        struct Count(std::rc::Rc<std::cell::Cell<usize>>);
+        impl<'a> rule_handler::Listener<'a, [u8]> for Count {
+            fn skipped(&self, options: &[rule::Rule<'a, [u8]>], _input: &'a [u8]) {
+                self.0.set(self.0.get() + options.len());
+            }
+        }
+        handler.add_listener(std::rc::Rc::new(Count(skipped.clone())));
+        let node = rule.parse_with_handler(input, &handler).unwrap();
+        assert_eq!(node.content, input);
+        assert_eq!(skipped.get(), 1);
+    }
+}
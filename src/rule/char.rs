@@ -1,9 +1,12 @@
 use crate::COUNTER;
 
-use crate::Node;
 use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
 use once_cell::sync::Lazy;
 
+use super::RuleKind;
+
 pub static CHAR_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
@@ -12,29 +15,36 @@ pub struct One {
 }
 
 impl<'a> Parsable<'a> for One {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
-        if input.chars().next() == Some(self.c) {
-            Some(Node::new(&input[0..1], id, &name))
+    fn parse(&self, input: &'a str, id: usize, name: &Name) -> ParseResult<'a> {
+        if input.starts_with(self.c) {
+            Ok(Node::new(&input[0..self.c.len_utf8()], id, name))
         } else {
-            None
+            Err(Failure::Backtrack)
         }
     }
     fn parse_with_handler(
         &self,
         input: &'a str,
         id: usize,
-        name: &String,
+        name: &Name,
         handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+    ) -> ParseResult<'a> {
         handler.handle_pre_parse(id);
-        if let Some(mut success) = self.parse(input, id, name) {
-            handler.handle_success(&mut success);
-            Some(success)
-        } else {
-            handler.handle_failure(id);
-            None
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
         }
     }
+
+    fn kind(&self) -> RuleKind<'_, 'a> {
+        RuleKind::Char(self.c)
+    }
 }
 
 #[macro_export]
@@ -43,7 +53,7 @@ macro_rules! char {
         $crate::rule::Rule::new(
             Box::new($crate::rule::One { c: $c }),
             *$crate::rule::CHAR_ID,
-            "Char".to_string()
+            "Char"
         )
     };
     ($name:expr => $c:expr) => {
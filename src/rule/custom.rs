@@ -1,59 +1,68 @@
 use crate::COUNTER;
 
-use super::Rule;
-use crate::Node;
-use crate::Parsable;
+use super::{Rule, RuleKind};
+use crate::ParseResult;
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 
-pub struct Custom<'a> {
-    pub rule: Rule<'a>,
+pub struct Custom<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
 }
 
-impl<'a> Parsable<'a> for Custom<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Custom<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
         // Forwards the parse call to the rule stored in the Custom struct
         // and wraps the result in its own Node struct
         // This is useful for filtering out unwanted nodes
-        if let Some(node) = self.rule.parse(input) {
-            let mut wrapper = Node::new(node.content, id, name);
-            wrapper.add_child(node);
-            Some(wrapper)
-        } else {
-            None
-        }
+        let node = self.rule.apply(input)?;
+        let mut wrapper = Node::new(node.content, id, name);
+        wrapper.add_child(node);
+        Ok(wrapper)
     }
 
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         // Forwards the parse call to the rule stored in the Custom struct
         // and wraps the result in its own Node struct
         // This is useful for filtering out unwanted nodes
         handler.handle_pre_parse(id);
-        if let Some(success) = self.rule.parse_with_handler(input, &handler) {
-            let mut wrapper = Node::new(success.content, id, name);
-            wrapper.add_child(success);
-            handler.handle_success(&mut wrapper);
-            Some(wrapper)
-        } else {
-            handler.handle_failure(id);
-            None
+        if let Some(mut reused) = handler.handle_reuse(id, input) {
+            handler.handle_success(&mut reused);
+            return Ok(reused);
         }
+        match self.rule.apply_with_handler(input, handler) {
+            Ok(success) => {
+                let mut wrapper = Node::new(success.content, id, name);
+                handler.add_child(&mut wrapper, success);
+                handler.handle_success(&mut wrapper);
+                Ok(wrapper)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Custom(&self.rule)
     }
 }
 
-impl<'a> Custom<'a> {
-    pub fn new(rule: Rule<'a>) -> Custom<'a> {
+impl<'a, I: ?Sized + Slice> Custom<'a, I> {
+    pub fn new(rule: Rule<'a, I>) -> Custom<'a, I> {
         Custom { rule }
     }
 
-    pub fn new_rule(rule: Rule<'a>, name: String) -> Rule<'a> {
-        let custom = Custom::<'a>::new(rule);
-        let x = Box::<Custom<'a>>::new(custom);
-        Rule::<'a>::new(
+    pub fn new_rule(rule: Rule<'a, I>, name: String) -> Rule<'a, I> {
+        let custom = Custom::new(rule);
+        let x = Box::<Custom<'a, I>>::new(custom);
+        Rule::new(
             x,
             COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
             name,
@@ -61,26 +70,26 @@ impl<'a> Custom<'a> {
     }
 }
 
-pub struct UninitializedRule<'a> {
-    rule: Rule<'a>,
+pub struct UninitializedRule<'a, I: ?Sized + Slice = str> {
+    rule: Rule<'a, I>,
 }
 
-impl<'a> UninitializedRule<'a> {
-    pub fn new(name: String) -> UninitializedRule<'a> {
+impl<'a, I: ?Sized + Slice> UninitializedRule<'a, I> {
+    pub fn new(name: String) -> UninitializedRule<'a, I> {
         UninitializedRule {
             rule: Rule::new_late_instantiated(name),
         }
     }
 
-    pub fn init(&self, rule: Rule<'a>) -> Rule<'a> {
-        if let Err(_) = self.rule.rule.set(Box::new(Custom::new(rule))) {
+    pub fn init(&self, rule: Rule<'a, I>) -> Rule<'a, I> {
+        if self.rule.rule.set(Box::new(Custom::new(rule))).is_err() {
             panic!("Rule already initialized")
         } else {
-            return self.rule.clone();
+            self.rule.clone()
         }
     }
 
-    pub fn get(&self) -> Rule<'a> {
+    pub fn get(&self) -> Rule<'a, I> {
         self.rule.clone()
     }
 }
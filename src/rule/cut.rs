@@ -0,0 +1,123 @@
+use crate::COUNTER;
+
+use crate::ParseResult;
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
+use once_cell::sync::Lazy;
+
+use super::RuleKind;
+
+pub static CUT_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+/// Always matches without consuming any input.
+/// Once a `Seq` has passed a cut, any later failure inside that sequence
+/// becomes a hard error, so enclosing `Sor`s will not try their other options.
+pub struct Cut {}
+
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Cut {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
+        Ok(Node::new(&input[0..0], id, name))
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a I,
+        id: usize,
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
+        handler.handle_pre_parse(id);
+        let mut node = Node::new(&input[0..0], id, name);
+        handler.handle_success(&mut node);
+        Ok(node)
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Cut
+    }
+}
+
+#[macro_export]
+macro_rules! cut {
+    () => {
+        $crate::rule::Rule::new(Box::new($crate::rule::Cut {}), *$crate::rule::CUT_ID, "Cut")
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rule::{CUT_ID, SEQ_ID, STR_ID};
+    use rule_handler::Handler;
+
+    fn function() -> rule::Rule<'static> {
+        sor!(
+            seq!(str!("function"), cut!(), char!('('), char!(')')),
+            str!("functional")
+        )
+    }
+
+    #[test]
+    fn cut_matches_empty_input() {
+        let rule = cut!();
+        let result = rule.parse("abc");
+        let result2 = rule.parse_with_handler("abc", &Handler::new());
+        assert_eq!(result, result2);
+        assert_eq!(result, Some(Node::new("", rule.id, &rule.name)));
+    }
+
+    #[test]
+    fn cut_does_not_affect_success() {
+        let rule = function();
+        let result = rule.parse("function()");
+        let result2 = rule.parse_with_handler("function()", &Handler::new());
+        assert_eq!(result, result2);
+        let node = result.unwrap();
+        assert_eq!(node.content, "function()");
+        assert_eq!(node.children[0].type_id, *SEQ_ID);
+        assert_eq!(node.children[0].children[1].type_id, *CUT_ID);
+    }
+
+    #[test]
+    fn cut_prevents_backtracking() {
+        let rule = function();
+        assert_eq!(rule.parse("functional"), None);
+        assert_eq!(rule.parse_with_handler("functional", &Handler::new()), None);
+        let error = rule.try_parse("functional").unwrap_err();
+        assert_eq!(error.offset, 8);
+        assert_eq!(error.expected, "Char");
+        assert!(error.committed);
+    }
+
+    #[test]
+    fn cut_before_cut_still_backtracks() {
+        let rule = sor!(
+            seq!(str!("fn"), char!(' '), cut!(), char!('x')),
+            str!("fnord")
+        );
+        let node = rule.parse("fnord").unwrap();
+        assert_eq!(node.children[0].type_id, *STR_ID);
+    }
+
+    #[test]
+    fn cut_error_passes_through_repetitions() {
+        let item = seq!(char!('['), cut!(), char!(']'));
+        let rule = seq!(star!(item), opt!(char!('[')));
+        let error = rule.try_parse("[][x").unwrap_err();
+        assert_eq!(error.offset, 3);
+        assert!(error.committed);
+        let rule = plus!(seq!(char!('['), cut!(), char!(']')));
+        assert_eq!(rule.try_parse("[][x").unwrap_err().offset, 3);
+    }
+
+    #[test]
+    fn failure_without_cut_reports_the_rule() {
+        let rule = custom!("Function" => function());
+        let error = rule.try_parse("procedure").unwrap_err();
+        assert_eq!(error.offset, 0);
+        assert_eq!(error.expected, "Function");
+        assert!(!error.committed);
+        assert_eq!(error.to_string(), "expected Function at offset 0");
+    }
+}
@@ -1,38 +1,48 @@
 use crate::COUNTER;
 
-use crate::Node;
-use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
+use super::RuleKind;
+
 pub static EOF_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
 pub struct Eof {}
 
-impl<'a> Parsable<'a> for Eof {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Eof {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
         if input.is_empty() {
-            Some(Node::new_empty(id, &name))
+            Ok(Node::new_empty(id, name))
         } else {
-            None
+            Err(Failure::Backtrack)
         }
     }
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
-        if let Some(mut success) = self.parse(input, id, name) {
-            handler.handle_success(&mut success);
-            Some(success)
-        } else {
-            handler.handle_failure(id);
-            None
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
         }
     }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Eof
+    }
 }
 
 #[macro_export]
@@ -41,7 +51,7 @@ macro_rules! eof {
         $crate::rule::Rule::new(
             Box::new($crate::rule::Eof { }),
             *$crate::rule::EOF_ID,
-            "Eof".to_string()
+            "Eof"
         )
     };
     ($name:expr) => {
@@ -0,0 +1,195 @@
+use super::{Rule, RuleKind, CUT_ID};
+use crate::Slice;
+
+/// A set of characters, with ASCII ones kept in a bit mask. Bytes are kept as the
+/// characters with their values, see `Slice::key`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FirstChars {
+    ascii: u128,
+    other: Vec<char>,
+}
+
+impl FirstChars {
+    fn insert(&mut self, c: char) {
+        if c.is_ascii() {
+            self.ascii |= 1 << c as u32;
+        } else if !self.other.contains(&c) {
+            self.other.push(c);
+        }
+    }
+
+    fn extend(&mut self, chars: &FirstChars) {
+        self.ascii |= chars.ascii;
+        for c in &chars.other {
+            self.insert(*c);
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        if c.is_ascii() {
+            self.ascii & (1 << c as u32) != 0
+        } else {
+            self.other.contains(&c)
+        }
+    }
+}
+
+/// What a rule does on input that does not start with one of `chars`: backtracks, or
+/// matches nothing if `nullable`. It never fails past a cut there.
+struct First {
+    chars: FirstChars,
+    nullable: bool,
+}
+
+/// The characters the rule can start with: on input starting with any other character the
+/// rule backtracks. `None` if that is not known, or if the rule can match without consuming
+/// anything or fail past a cut instead.
+pub fn first_chars<I: ?Sized + Slice>(rule: &Rule<I>) -> Option<FirstChars> {
+    match first(rule, &mut Vec::new())? {
+        First {
+            nullable: false,
+            chars,
+        } => Some(chars),
+        _ => None,
+    }
+}
+
+/// `visiting` holds the named rules being looked into, recursion gives up.
+fn first<I: ?Sized + Slice>(rule: &Rule<I>, visiting: &mut Vec<usize>) -> Option<First> {
+    let chars = |chars: &[char]| {
+        let mut set = FirstChars::default();
+        for c in chars {
+            set.insert(*c);
+        }
+        set
+    };
+    Some(match rule.kind()? {
+        RuleKind::Char(unit) => First {
+            chars: chars(&[I::key(unit)]),
+            nullable: false,
+        },
+        RuleKind::Str(s) => {
+            let start: Vec<char> = s
+                .first()
+                .map(|(unit, _)| I::key(unit))
+                .into_iter()
+                .collect();
+            First {
+                chars: chars(&start),
+                nullable: s.is_empty(),
+            }
+        }
+        // Does not match any input that starts with a character.
+        RuleKind::Eof => First {
+            chars: FirstChars::default(),
+            nullable: false,
+        },
+        RuleKind::Seq(rules) => {
+            let mut result = First {
+                chars: FirstChars::default(),
+                nullable: true,
+            };
+            for (i, rule) in rules.iter().enumerate() {
+                // The rules after a cut fail past it.
+                if rule.id == *CUT_ID && i + 1 < rules.len() {
+                    return None;
+                }
+                let first = first(rule, visiting)?;
+                result.chars.extend(&first.chars);
+                if !first.nullable {
+                    result.nullable = false;
+                    break;
+                }
+            }
+            result
+        }
+        RuleKind::Sor(rules) => {
+            let mut result = First {
+                chars: FirstChars::default(),
+                nullable: false,
+            };
+            for rule in rules {
+                let first = first(rule, visiting)?;
+                result.chars.extend(&first.chars);
+                result.nullable |= first.nullable;
+            }
+            result
+        }
+        RuleKind::Star(rule) | RuleKind::Opt(rule) => First {
+            nullable: true,
+            ..first(rule, visiting)?
+        },
+        RuleKind::Plus(rule) => first(rule, visiting)?,
+        RuleKind::Custom(inner) => {
+            if visiting.contains(&rule.instance_id()) {
+                return None;
+            }
+            visiting.push(rule.instance_id());
+            let first = first(inner, visiting);
+            visiting.pop();
+            first?
+        }
+        RuleKind::Cut => First {
+            chars: FirstChars::default(),
+            nullable: true,
+        },
+        // Ignoring case, characters other than the cased forms of a letter may match it.
+        RuleKind::Literals(literals) if literals.ignore_case => return None,
+        RuleKind::Literals(literals) => {
+            let starts: Vec<char> = literals
+                .literals
+                .iter()
+                .filter_map(|literal| literal.chars().next())
+                .collect();
+            First {
+                chars: chars(&starts),
+                nullable: literals.literals.iter().any(|literal| literal.is_empty()),
+            }
+        }
+        // Must fails past a cut where its rule backtracks, and Recover matches anything.
+        RuleKind::Must(_) | RuleKind::Recover(..) | RuleKind::Opaque => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grammar;
+
+    fn first_of(peg: &str) -> Option<String> {
+        let grammar = Grammar::from_peg(peg).unwrap();
+        let chars = first_chars(&grammar.start)?;
+        Some((' '..='~').filter(|c| chars.contains(*c)).collect())
+    }
+
+    #[test]
+    fn first_chars_of_expressions() {
+        assert_eq!(first_of("A <- 'a' 'b' ;").as_deref(), Some("a"));
+        assert_eq!(
+            first_of("A <- 'a'? \"bc\" / 'd'* 'e' ;").as_deref(),
+            Some("abde")
+        );
+        assert_eq!(first_of("A <- B+ ; B <- 'b' / EOF ;").as_deref(), Some("b"));
+        assert_eq!(first_of("A <- 'a' ^ 'b' ;").as_deref(), Some("a"));
+        assert_eq!(
+            first_of("A <- 'a' B ; B <- A / 'b' ;").as_deref(),
+            Some("a")
+        );
+        let grammar = Grammar::from_peg("A <- 'é' ;").unwrap();
+        assert!(first_chars(&grammar.start).unwrap().contains('é'));
+    }
+
+    #[test]
+    fn unknown_first_chars() {
+        // Matches without consuming anything.
+        assert_eq!(first_of("A <- 'a'* ;"), None);
+        assert_eq!(first_of("A <- \"\" ;"), None);
+        // Fails past a cut on any input.
+        assert_eq!(first_of("A <- ^ 'a' ;"), None);
+        assert_eq!(first_of("A <- 'a'? ^ 'b' ;"), None);
+        assert_eq!(first_of("A <- must('a') ;"), None);
+        assert_eq!(first_of("A <- recover('a', ';') ;"), None);
+        // Left recursion.
+        assert_eq!(first_of("A <- A 'a' / 'b' ;"), None);
+    }
+}
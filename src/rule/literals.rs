@@ -0,0 +1,304 @@
+use crate::COUNTER;
+
+use super::*;
+use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use once_cell::sync::Lazy;
+
+pub static LITERALS_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+#[derive(Debug, Clone, Default)]
+struct TrieNode {
+    /// Outgoing edges, sorted by character.
+    edges: Vec<(char, usize)>,
+    /// The first literal ending here.
+    literal: Option<usize>,
+}
+
+/// Matches one of many string literals at once, walking a trie of them instead of trying
+/// them one by one.
+///
+/// Either takes the literal listed first among those the input starts with, like a `Sor` of
+/// `Str`s would, or the longest of them, which is what a list of keywords usually wants.
+/// With `ignore_case`, letters match regardless of case.
+///
+/// The node of a match has one `Str` child with the same content; `matched` tells which
+/// literal it was.
+#[derive(Debug, Clone)]
+pub struct Literals {
+    pub literals: Vec<String>,
+    pub longest: bool,
+    pub ignore_case: bool,
+    trie: Vec<TrieNode>,
+}
+
+impl Literals {
+    pub fn new(literals: Vec<String>, longest: bool, ignore_case: bool) -> Literals {
+        let mut result = Literals {
+            literals,
+            longest,
+            ignore_case,
+            trie: vec![TrieNode::default()],
+        };
+        for index in 0..result.literals.len() {
+            let chars: Vec<char> = result.literals[index]
+                .chars()
+                .flat_map(|c| result.fold(c))
+                .collect();
+            let mut node = 0;
+            for c in chars {
+                node = match result.trie[node]
+                    .edges
+                    .binary_search_by_key(&c, |edge| edge.0)
+                {
+                    Ok(edge) => result.trie[node].edges[edge].1,
+                    Err(edge) => {
+                        result.trie.push(TrieNode::default());
+                        let next = result.trie.len() - 1;
+                        result.trie[node].edges.insert(edge, (c, next));
+                        next
+                    }
+                };
+            }
+            result.trie[node].literal.get_or_insert(index);
+        }
+        result
+    }
+
+    /// The characters a character of the input or of a literal is compared as.
+    fn fold(&self, c: char) -> impl Iterator<Item = char> {
+        let lower = if self.ignore_case {
+            Some(c.to_lowercase())
+        } else {
+            None
+        };
+        let same = if self.ignore_case { None } else { Some(c) };
+        lower.into_iter().flatten().chain(same)
+    }
+
+    /// The most bytes of input a match can take. Ignoring case, a character may take more
+    /// bytes in the input than in the literal.
+    pub fn max_len(&self) -> usize {
+        let len = |literal: &String| {
+            if self.ignore_case {
+                literal.chars().count() * 4
+            } else {
+                literal.len()
+            }
+        };
+        self.literals.iter().map(len).max().unwrap_or(0)
+    }
+
+    /// The length of the match at the start of the input and the index of the literal that
+    /// matched.
+    pub fn find(&self, input: &str) -> Option<(usize, usize)> {
+        let mut node = 0;
+        let mut found = self.trie[0].literal.map(|literal| (0, literal));
+        for (start, c) in input.char_indices() {
+            for c in self.fold(c) {
+                let edges = &self.trie[node].edges;
+                match edges.binary_search_by_key(&c, |edge| edge.0) {
+                    Ok(edge) => node = edges[edge].1,
+                    Err(_) => return found,
+                }
+            }
+            if let Some(literal) = self.trie[node].literal {
+                let end = start + c.len_utf8();
+                found = match found {
+                    Some((_, first)) if !self.longest && first < literal => found,
+                    _ => Some((end, literal)),
+                };
+            }
+        }
+        found
+    }
+
+    /// The literal a node of this rule, or its child, matched, as it was listed.
+    ///
+    /// It is looked up in the content again, as a node cannot carry the index: all it has
+    /// besides its content are the id and name of the rule that made it. The child keeps
+    /// those of `Str`, as per-literal ones would make it look like a rule of its own to
+    /// filters, `is_named` and the generated parsers, and a side table by id would be
+    /// shared by every `Literals` rule.
+    pub fn matched(&self, node: &Node) -> Option<&str> {
+        match self.find(node.content) {
+            Some((len, literal)) if len == node.content.len() => Some(&self.literals[literal]),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> Parsable<'a> for Literals {
+    fn parse(&self, input: &'a str, id: usize, name: &Name) -> ParseResult<'a> {
+        match self.find(input) {
+            Some((len, _)) => {
+                let mut node = Node::new(&input[..len], id, name);
+                node.add_child(Node::new(&input[..len], *STR_ID, "Str"));
+                Ok(node)
+            }
+            None => Err(Failure::Backtrack),
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a str,
+        id: usize,
+        name: &Name,
+        handler: &Handler<'a>,
+    ) -> ParseResult<'a> {
+        handler.handle_pre_parse(id);
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a> {
+        RuleKind::Literals(self)
+    }
+}
+
+/// Matches the first of the literals the input starts with, in the order they are listed.
+/// `literals!(ignore_case; ...)` ignores the case of letters.
+#[macro_export]
+macro_rules! literals {
+    ($name:expr => ignore_case; $($literal:expr),* $(,)?) => {
+        $crate::custom!($name => $crate::literals!(ignore_case; $($literal),*))
+    };
+    ($name:expr => $($literal:expr),* $(,)?) => {
+        $crate::custom!($name => $crate::literals!($($literal),*))
+    };
+    (ignore_case; $($literal:expr),* $(,)?) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Literals::new(
+                vec![$($literal.to_string()),*],
+                false,
+                true,
+            )),
+            *$crate::rule::LITERALS_ID,
+            "Literals",
+        )
+    };
+    ($($literal:expr),* $(,)?) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Literals::new(
+                vec![$($literal.to_string()),*],
+                false,
+                false,
+            )),
+            *$crate::rule::LITERALS_ID,
+            "Literals",
+        )
+    };
+}
+
+/// Matches the longest of the literals the input starts with.
+/// `keywords!(ignore_case; ...)` ignores the case of letters.
+#[macro_export]
+macro_rules! keywords {
+    ($name:expr => ignore_case; $($literal:expr),* $(,)?) => {
+        $crate::custom!($name => $crate::keywords!(ignore_case; $($literal),*))
+    };
+    ($name:expr => $($literal:expr),* $(,)?) => {
+        $crate::custom!($name => $crate::keywords!($($literal),*))
+    };
+    (ignore_case; $($literal:expr),* $(,)?) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Literals::new(
+                vec![$($literal.to_string()),*],
+                true,
+                true,
+            )),
+            *$crate::rule::LITERALS_ID,
+            "Literals",
+        )
+    };
+    ($($literal:expr),* $(,)?) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Literals::new(
+                vec![$($literal.to_string()),*],
+                true,
+                false,
+            )),
+            *$crate::rule::LITERALS_ID,
+            "Literals",
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rule::{Literals, Rule, RuleKind, STR_ID};
+
+    fn matched(rule: &Rule, node: &Node) -> Option<String> {
+        match rule.kind() {
+            Some(RuleKind::Literals(literals)) => literals.matched(node).map(str::to_string),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn literals_take_the_first_listed() {
+        let rule = literals!("in", "int", "i");
+        let node = rule.parse("integer").unwrap();
+        assert_eq!(node.content, "in");
+        assert_eq!(node.children[0].type_id, *STR_ID);
+        assert_eq!(node.children[0].type_name, "Str");
+        assert_eq!(matched(&rule, &node).as_deref(), Some("in"));
+        assert_eq!(rule.parse("i8").unwrap().content, "i");
+        assert_eq!(rule.parse("x"), None);
+        assert_eq!(rule.parse(""), None);
+
+        let rule = sor!(str!("in"), str!("int"), str!("i"));
+        assert_eq!(rule.parse("integer").unwrap().content, "in");
+    }
+
+    #[test]
+    fn keywords_take_the_longest() {
+        let rule = keywords!("Keyword" => "in", "int", "i", "interface");
+        let node = rule.parse("integer").unwrap();
+        assert_eq!(node.type_name, "Keyword");
+        assert_eq!(node.content, "int");
+        let literals = rule.kind().unwrap().children()[0].clone();
+        assert_eq!(
+            matched(&literals, &node.children[0]).as_deref(),
+            Some("int")
+        );
+        assert_eq!(matched(&literals, &Node::new("inte", 0, "")), None);
+        assert_eq!(rule.parse("interfaces").unwrap().content, "interface");
+        assert_eq!(rule.parse("inter").unwrap().content, "int");
+    }
+
+    #[test]
+    fn ignore_case() {
+        let rule = keywords!(ignore_case; "select", "from", "straße");
+        let node = rule.parse("SELECT *").unwrap();
+        assert_eq!(node.content, "SELECT");
+        assert_eq!(matched(&rule, &node).as_deref(), Some("select"));
+        assert_eq!(rule.parse("StRaSSE").map(|node| node.content), None);
+        assert_eq!(rule.parse("STRAßE").unwrap().content, "STRAßE");
+        assert_eq!(keywords!("from").parse("FROM"), None);
+    }
+
+    #[test]
+    fn empty_and_repeated_literals() {
+        let literals = Literals::new(vec!["".to_string(), "a".to_string()], false, false);
+        assert_eq!(literals.find("a"), Some((0, 0)));
+        let literals = Literals::new(vec!["".to_string(), "a".to_string()], true, false);
+        assert_eq!(literals.find("a"), Some((1, 1)));
+        assert_eq!(literals.find("b"), Some((0, 0)));
+        let literals = Literals::new(vec!["a".to_string(), "a".to_string()], true, false);
+        assert_eq!(literals.find("a"), Some((1, 0)));
+        assert_eq!(Literals::new(Vec::new(), true, false).find("a"), None);
+    }
+}
@@ -1,16 +1,27 @@
 #[macro_use]
+pub mod binary;
+#[macro_use]
 pub mod char;
 #[macro_use]
 pub mod eof;
+pub mod first;
+#[macro_use]
+pub mod literals;
 #[macro_use]
 pub mod opt;
 #[macro_use]
 pub mod plus;
 #[macro_use]
+pub mod recover;
+#[macro_use]
 pub mod seq;
 #[macro_use]
 pub mod custom;
 #[macro_use]
+pub mod cut;
+#[macro_use]
+pub mod must;
+#[macro_use]
 pub mod sor;
 #[macro_use]
 pub mod star;
@@ -18,12 +29,18 @@ pub mod star;
 pub mod str;
 // #[macro_use]
 // pub mod ranges;
+pub use binary::{integer_value, Byte, Bytes, Integer, Prefixed, INTEGER_ID, PREFIXED_ID};
 pub use char::*;
 pub use eof::*;
+pub use first::*;
+pub use literals::*;
 pub use opt::*;
 pub use plus::*;
 // pub use ranges::*;
 pub use custom::*;
+pub use cut::*;
+pub use must::*;
+pub use recover::*;
 pub use seq::*;
 pub use sor::*;
 pub use star::*;
@@ -33,30 +50,166 @@ use std::cell::OnceCell;
 use std::rc::Rc;
 
 use crate::rule_handler::Handler;
-use crate::Parsable;
+use crate::{Diagnostic, Failure, Furthest, Name, Node, Parsable, ParseError, ParseResult, Slice};
+
+/// The ids of the rules that are not named, with the name of their nodes and of the static
+/// holding the id.
+pub fn unnamed_ids() -> [(usize, &'static str, &'static str); 15] {
+    [
+        (*CHAR_ID, "Char", "CHAR_ID"),
+        (*STR_ID, "Str", "STR_ID"),
+        (*SEQ_ID, "Seq", "SEQ_ID"),
+        (*SOR_ID, "Sor", "SOR_ID"),
+        (*STAR_ID, "Star", "STAR_ID"),
+        (*PLUS_ID, "Plus", "PLUS_ID"),
+        (*OPT_ID, "Opt", "OPT_ID"),
+        (*EOF_ID, "Eof", "EOF_ID"),
+        (*CUT_ID, "Cut", "CUT_ID"),
+        (*MUST_ID, "Must", "MUST_ID"),
+        (*RECOVER_ID, "Recover", "RECOVER_ID"),
+        (*ERROR_ID, "Error", "ERROR_ID"),
+        (*LITERALS_ID, "Literals", "LITERALS_ID"),
+        (*INTEGER_ID, "Integer", "INTEGER_ID"),
+        (*PREFIXED_ID, "Prefixed", "PREFIXED_ID"),
+    ]
+}
+
+/// What a rule matches, as returned by `Rule::kind`.
+pub enum RuleKind<'r, 'a, I: ?Sized + Slice = str> {
+    Char(I::Unit),
+    Str(&'r I),
+    Eof,
+    Cut,
+    Seq(&'r [Rule<'a, I>]),
+    Sor(&'r [Rule<'a, I>]),
+    Star(&'r Rule<'a, I>),
+    Plus(&'r Rule<'a, I>),
+    Opt(&'r Rule<'a, I>),
+    Must(&'r Rule<'a, I>),
+    Recover(&'r Rule<'a, I>, &'r Rule<'a, I>),
+    Custom(&'r Rule<'a, I>),
+    Literals(&'r Literals),
+    /// A rule that does not describe itself.
+    Opaque,
+}
+
+impl<I: ?Sized + Slice> Clone for RuleKind<'_, '_, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<I: ?Sized + Slice> Copy for RuleKind<'_, '_, I> {}
+
+impl<'r, 'a, I: ?Sized + Slice> RuleKind<'r, 'a, I> {
+    /// The rules this rule is made of, in order.
+    pub fn children(&self) -> Vec<&'r Rule<'a, I>> {
+        match *self {
+            RuleKind::Seq(rules) | RuleKind::Sor(rules) => rules.iter().collect(),
+            RuleKind::Star(rule)
+            | RuleKind::Plus(rule)
+            | RuleKind::Opt(rule)
+            | RuleKind::Must(rule)
+            | RuleKind::Custom(rule) => vec![rule],
+            RuleKind::Recover(rule, sync) => vec![rule, sync],
+            _ => Vec::new(),
+        }
+    }
+}
 
-#[derive(Clone)]
-pub struct Rule<'a> {
-    pub rule: Rc<OnceCell<Box<dyn crate::Parsable<'a> + 'a>>>,
+/// A rule parsing text, or bytes for binary data (see `binary`).
+pub struct Rule<'a, I: ?Sized + Slice = str> {
+    pub rule: Rc<OnceCell<Box<dyn crate::Parsable<'a, I> + 'a>>>,
     pub id: usize,
-    pub name: String,
+    pub name: Name,
 }
 
-impl<'a> Rule<'a> {
-    pub fn new_late_instantiated(name: String) -> Self {
+impl<I: ?Sized + Slice> Clone for Rule<'_, I> {
+    fn clone(&self) -> Self {
+        Rule {
+            rule: self.rule.clone(),
+            id: self.id,
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl<'a, I: ?Sized + Slice> Rule<'a, I> {
+    pub fn new_late_instantiated(name: impl Into<Name>) -> Self {
         Self {
             rule: Rc::new(OnceCell::new()),
             id: crate::COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
-            name,
+            name: name.into(),
         }
     }
 
-    pub fn new(rule: Box<dyn Parsable<'a> + 'a>, id: usize, name: String) -> Rule<'a> {
+    pub fn new(
+        rule: Box<dyn Parsable<'a, I> + 'a>,
+        id: usize,
+        name: impl Into<Name>,
+    ) -> Rule<'a, I> {
         let rule = Rc::new(OnceCell::from(rule));
+        let name = name.into();
         Self { rule, id, name }
     }
 
-    pub fn parse(&self, input: &'a str) -> Option<crate::Node<'a>> {
+    pub fn parse(&self, input: &'a I) -> Option<Node<'a, I>> {
+        self.apply(input).ok()
+    }
+
+    pub fn parse_with_handler(
+        &self,
+        input: &'a I,
+        handler: &Handler<'a, I>,
+    ) -> Option<Node<'a, I>> {
+        self.apply_with_handler(input, handler).ok()
+    }
+
+    /// Parses the input and reports where and why it failed if it did not match.
+    pub fn try_parse(&self, input: &'a I) -> Result<Node<'a, I>, ParseError> {
+        self.apply(input)
+            .map_err(|failure| self.error(input, failure))
+    }
+
+    /// Like `try_parse`, but the rule has to match all of the input: a match that ends
+    /// early fails where it ends, expecting the end of the input.
+    pub fn parse_all(&self, input: &'a I) -> Result<Node<'a, I>, ParseError> {
+        let tree = self.try_parse(input)?;
+        if tree.content.len() == input.len() {
+            return Ok(tree);
+        }
+        Err(ParseError {
+            offset: tree.content.len(),
+            expected: "end of input".to_string(),
+            committed: false,
+        })
+    }
+
+    /// Turns the failure of the rule on `input` into a `ParseError`, parsing the input
+    /// again to find where it got furthest if it backtracked (see `Furthest`).
+    pub fn error(&self, input: &'a I, failure: Failure<'a, I>) -> ParseError {
+        let furthest = match failure {
+            Failure::Backtrack => Furthest::find(self, input),
+            Failure::Cut { .. } => None,
+        };
+        failure.into_error(input, &self.name, furthest)
+    }
+
+    /// Parses the input, recovering from syntax errors inside `recover!` rules.
+    /// Returns the tree, containing an `Error` node for every recovered error,
+    /// together with a diagnostic for each of them.
+    pub fn parse_with_recovery(
+        &self,
+        input: &'a I,
+    ) -> Result<(Node<'a, I>, Vec<Diagnostic>), ParseError> {
+        let node = self.try_parse(input)?;
+        let diagnostics = node.diagnostics(input);
+        Ok((node, diagnostics))
+    }
+
+    /// Like `parse`, but keeps the reason of the failure.
+    /// This is what rules use to parse their sub-rules.
+    pub fn apply(&self, input: &'a I) -> ParseResult<'a, I> {
         if let Some(rule) = self.rule.get() {
             rule.parse(input, self.id, &self.name)
         } else {
@@ -64,25 +217,32 @@ impl<'a> Rule<'a> {
         }
     }
 
-    pub fn parse_with_handler(
-        &self,
-        input: &'a str,
-        handler: &Handler<'a>,
-    ) -> Option<crate::Node<'a>> {
-        handler.handle_pre_parse(self.id);
+    /// Like `apply`, but calls the handlers of this rule and all of its sub-rules.
+    pub fn apply_with_handler(&self, input: &'a I, handler: &Handler<'a, I>) -> ParseResult<'a, I> {
         if let Some(rule) = self.rule.get() {
-            if let Some(mut node) = rule.parse(input, self.id, &self.name) {
-                handler.handle_success(&mut node);
-                Some(node)
-            } else {
-                handler.handle_failure(self.id);
-                None
+            handler.notify_pre_parse(self, input);
+            let result = rule.parse_with_handler(input, self.id, &self.name, handler);
+            match &result {
+                Ok(node) => handler.notify_success(self, input, node),
+                Err(failure) => handler.notify_failure(self, input, failure),
             }
+            result
         } else {
             panic!("Rule not initialized")
         }
     }
 
+    /// Describes what the rule matches, `None` if it has not been initialized yet.
+    pub fn kind(&self) -> Option<RuleKind<'_, 'a, I>> {
+        self.rule.get().map(|rule| rule.kind())
+    }
+
+    /// Identifies this rule and its clones, unlike `id`, which is shared
+    /// by all rules of the same kind unless they are named.
+    pub fn instance_id(&self) -> usize {
+        Rc::as_ptr(&self.rule) as *const () as usize
+    }
+
     pub fn get(&self) -> Self {
         self.clone()
     }
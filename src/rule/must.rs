@@ -0,0 +1,124 @@
+use crate::COUNTER;
+
+use crate::{Failure, ParseResult, Slice};
+use crate::{Name, Parsable};
+use once_cell::sync::Lazy;
+
+use super::{Rule, RuleKind};
+
+pub static MUST_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+/// Matches the inner rule, and turns its failure into a hard error.
+/// `must!(r)` behaves like `r` placed right after a cut.
+pub struct Must<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
+}
+
+impl<'a, I: ?Sized + Slice> Must<'a, I> {
+    fn commit(&self, failure: Failure<'a, I>, input: &'a I) -> Failure<'a, I> {
+        match failure {
+            Failure::Backtrack => Failure::Cut {
+                rest: input,
+                id: self.rule.id,
+                name: self.rule.name.clone(),
+            },
+            failure => failure,
+        }
+    }
+}
+
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Must<'a, I> {
+    fn parse(&self, input: &'a I, _id: usize, _name: &Name) -> ParseResult<'a, I> {
+        self.rule
+            .apply(input)
+            .map_err(|failure| self.commit(failure, input))
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a I,
+        id: usize,
+        _name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
+        handler.handle_pre_parse(id);
+        match self.rule.apply_with_handler(input, handler) {
+            Ok(mut success) => {
+                handler.handle_success_of(id, &mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(self.commit(failure, input))
+            }
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Must(&self.rule)
+    }
+}
+
+#[macro_export]
+macro_rules! must {
+    ($name:expr => $rule:expr) => {
+        $crate::custom!($name => $crate::must!($rule))
+    };
+    ($rule:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Must { rule: $rule }),
+            *$crate::rule::MUST_ID,
+            "Must",
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rule::CHAR_ID;
+    use rule_handler::Handler;
+
+    #[test]
+    fn must_matches_like_inner_rule() {
+        let rule = must!(char!('a'));
+        let result = rule.parse("ab");
+        let result2 = rule.parse_with_handler("ab", &Handler::new());
+        assert_eq!(result, result2);
+        assert_eq!(result, Some(Node::new("a", *CHAR_ID, "Char")));
+    }
+
+    #[test]
+    fn must_calls_its_success_handlers() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static MUST: AtomicUsize = AtomicUsize::new(0);
+        static CHAR: AtomicUsize = AtomicUsize::new(0);
+        let mut handler = Handler::new();
+        handler.add_success_handler(*rule::MUST_ID, |_| {
+            MUST.fetch_add(1, Ordering::SeqCst);
+        });
+        handler.add_success_handler(*CHAR_ID, |_| {
+            CHAR.fetch_add(1, Ordering::SeqCst);
+        });
+        assert!(must!(char!('a'))
+            .parse_with_handler("a", &handler)
+            .is_some());
+        assert_eq!(
+            (MUST.load(Ordering::SeqCst), CHAR.load(Ordering::SeqCst)),
+            (1, 1)
+        );
+    }
+
+    #[test]
+    fn must_failure_is_not_backtracked() {
+        let semicolon = char!("Semicolon" => ';');
+        let rule = sor!(seq!(str!("return"), must!(semicolon)), str!("returns"));
+        assert_eq!(rule.parse("returns"), None);
+        assert_eq!(rule.parse_with_handler("returns", &Handler::new()), None);
+        let error = rule.try_parse("returns").unwrap_err();
+        assert_eq!(error.offset, 6);
+        assert_eq!(error.expected, "Semicolon");
+        assert!(error.committed);
+    }
+}
@@ -1,43 +1,51 @@
 use crate::COUNTER;
 
-use crate::Node;
-use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
-use super::Rule;
+use super::{Rule, RuleKind};
 
 pub static OPT_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
-pub struct Opt<'a> {
-    pub rule: Rule<'a>,
+pub struct Opt<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
 }
 
-impl<'a> Parsable<'a> for Opt<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
-        if let Some(node) = self.rule.parse(input) {
-            Some(node)
-        } else {
-            Some(Node::new_empty(id, &name))
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Opt<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
+        match self.rule.apply(input) {
+            Err(Failure::Backtrack) => Ok(Node::new_empty(id, name)),
+            result => result,
         }
     }
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
-        if let Some(mut success) = self.rule.parse_with_handler(input, handler) {
-            handler.handle_success(&mut success);
-            Some(success)
-        } else {
-            let mut failure = Node::new_empty(id, name);
-            handler.handle_success(&mut failure);
-            Some(failure)
+        match self.rule.apply_with_handler(input, handler) {
+            Ok(success) => Ok(success),
+            Err(Failure::Backtrack) => {
+                let mut failure = Node::new_empty(id, name);
+                handler.handle_success(&mut failure);
+                Ok(failure)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
         }
     }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Opt(&self.rule)
+    }
 }
 
 #[macro_export]
@@ -49,7 +57,7 @@ macro_rules! opt {
         $crate::rule::Rule::new(
             Box::new($crate::rule::Opt { rule: $rule }),
             *$crate::rule::OPT_ID,
-            "Opt".to_string()
+            "Opt"
         )
     };
 }
@@ -94,10 +102,10 @@ mod tests {
         let result2 = rule.parse_with_handler(input, &Handler::new());
         assert_eq!(result, result2);
 
-        let mut expected_node = Node::new("a", rule.id, &"test".to_string());
+        let mut expected_node = Node::new("a", rule.id, "test");
         expected_node
             .children
-            .push(Node::new("a", *crate::rule::CHAR_ID, &"Char".to_string()));
+            .push(Node::new("a", *crate::rule::CHAR_ID, "Char"));
         assert_eq!(result, Some(expected_node));
     }
 
@@ -1,59 +1,78 @@
 use crate::COUNTER;
 
-use super::Rule;
-use crate::Node;
-use crate::Parsable;
+use super::{Rule, RuleKind};
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
 pub static PLUS_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
-pub struct Plus<'a> {
-    pub rule: Rule<'a>,
+pub struct Plus<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
 }
 
-impl<'a> Parsable<'a> for Plus<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
-        let mut node = Node::new_empty(id, &name);
-        let mut size = 0;
-        if let Some(child) = self.rule.parse(input) {
-            size += child.content.len();
-            node.add_child(child);
-        } else {
-            return None;
-        }
-        while let Some(child) = self.rule.parse(&input[size..]) {
-            size += child.content.len();
-            node.add_child(child);
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Plus<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
+        let mut node = Node::new_empty(id, name);
+        let child = self.rule.apply(input)?;
+        let mut size = child.content.len();
+        node.add_child(child);
+        loop {
+            match self.rule.apply(&input[size..]) {
+                Ok(child) => {
+                    size += child.content.len();
+                    node.add_child(child);
+                }
+                Err(Failure::Backtrack) => break,
+                Err(failure) => return Err(failure),
+            }
         }
         node.content = &input[0..size];
-        Some(node)
+        Ok(node)
     }
 
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
         let mut node = Node::new_empty(id, name);
         let mut size = 0;
-        if let Some(child) = self.rule.parse_with_handler(input, handler) {
-            size += child.content.len();
-            node.add_child(child);
-        } else {
-            handler.handle_failure(id);
-            return None;
+        match self.rule.apply_with_handler(input, handler) {
+            Ok(child) => {
+                size += child.content.len();
+                handler.add_child(&mut node, child);
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                return Err(failure);
+            }
         }
-        while let Some(child) = self.rule.parse_with_handler(&input[size..], handler) {
-            size += child.content.len();
-            node.add_child(child);
+        loop {
+            match self.rule.apply_with_handler(&input[size..], handler) {
+                Ok(child) => {
+                    size += child.content.len();
+                    handler.add_child(&mut node, child);
+                }
+                Err(Failure::Backtrack) => break,
+                Err(failure) => {
+                    handler.handle_failure(id);
+                    return Err(failure);
+                }
+            }
         }
         node.content = &input[0..size];
         handler.handle_success(&mut node);
-        Some(node)
+        Ok(node)
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Plus(&self.rule)
     }
 }
 
@@ -66,7 +85,7 @@ macro_rules! plus {
         $crate::rule::Rule::new(
             Box::new($crate::rule::Plus { rule: $rule }),
             *$crate::rule::PLUS_ID,
-            "Plus".to_string(),
+            "Plus",
         )
     };
 }
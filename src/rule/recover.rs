@@ -0,0 +1,282 @@
+use crate::COUNTER;
+
+use crate::{Diagnostic, Failure, ParseResult};
+use crate::{InputBounds, Name, Node};
+use crate::{Parsable, Slice};
+use once_cell::sync::Lazy;
+
+use super::{Rule, RuleKind};
+
+pub static RECOVER_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+pub static ERROR_ID: Lazy<usize> =
+    Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
+
+/// Matches the inner rule. If it fails, skips input up to and including the
+/// next match of `sync` and returns an `Error` node covering the skipped text.
+/// The only child of the `Error` node is an empty node of the rule that was
+/// expected, placed where it failed to match.
+pub struct Recover<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
+    pub sync: Rule<'a, I>,
+}
+
+impl<'a, I: ?Sized + Slice> Recover<'a, I> {
+    fn recover(&self, input: &'a I, failure: Failure<'a, I>) -> ParseResult<'a, I> {
+        recover_from(input, failure, self.rule.id, &self.rule.name, |rest| {
+            self.sync.apply(rest)
+        })
+    }
+}
+
+/// What `Recover` does after its rule, with id `id` and name `name`, failed on `input`:
+/// skips up to and including the next match of `sync` and returns the `Error` node.
+/// Generated parsers call this with their own function for the sync rule.
+pub fn recover_from<'a, I: ?Sized + Slice>(
+    input: &'a I,
+    failure: Failure<'a, I>,
+    id: usize,
+    name: &Name,
+    sync: impl Fn(&'a I) -> ParseResult<'a, I>,
+) -> ParseResult<'a, I> {
+    if input.is_empty() {
+        // Nothing to skip, recovering would not make any progress
+        return Err(failure);
+    }
+    let (rest, id, name) = match failure {
+        Failure::Backtrack => (input, id, name.clone()),
+        Failure::Cut { rest, id, name } => (rest, id, name),
+    };
+    let mut end = input.len();
+    // A failure in the field of a `Prefixed` rule stops at a slice ending with the field.
+    let start = InputBounds::of(input).offset(rest);
+    let rest = &input[start..];
+    let mut pos = 0;
+    while let Some((_, len)) = rest[pos..].first() {
+        if let Ok(sync) = sync(&rest[pos..]) {
+            let sync_end = start + pos + sync.content.len();
+            if sync_end > 0 {
+                end = sync_end;
+                break;
+            }
+        }
+        pos += len;
+    }
+    let mut error = Node::new(&input[0..end], *ERROR_ID, "Error");
+    error.add_child(Node::new(&rest[0..0], id, name));
+    Ok(error)
+}
+
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Recover<'a, I> {
+    fn parse(&self, input: &'a I, _id: usize, _name: &Name) -> ParseResult<'a, I> {
+        match self.rule.apply(input) {
+            Ok(node) => Ok(node),
+            Err(failure) => self.recover(input, failure),
+        }
+    }
+
+    fn parse_with_handler(
+        &self,
+        input: &'a I,
+        id: usize,
+        _name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
+        handler.handle_pre_parse(id);
+        match self.rule.apply_with_handler(input, handler) {
+            Ok(node) => Ok(node),
+            Err(failure) => match self.recover(input, failure) {
+                Ok(mut error) => {
+                    handler.handle_success(&mut error);
+                    Ok(error)
+                }
+                Err(failure) => {
+                    handler.handle_failure(id);
+                    Err(failure)
+                }
+            },
+        }
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Recover(&self.rule, &self.sync)
+    }
+}
+
+impl<I: ?Sized + Slice> Node<'_, I> {
+    /// Collects a diagnostic for every `Error` node in the tree.
+    /// `input` must be the input the tree was parsed from, `Error` nodes that do not
+    /// point into it are left out.
+    pub fn diagnostics(&self, input: &I) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        self.collect_diagnostics(input, &mut diagnostics);
+        diagnostics
+    }
+
+    fn collect_diagnostics(&self, input: &I, diagnostics: &mut Vec<Diagnostic>) {
+        if self.type_id == *ERROR_ID {
+            let expected = &self.children[0];
+            if let (Some(start), Some(offset)) = (self.offset_in(input), expected.offset_in(input))
+            {
+                diagnostics.push(Diagnostic {
+                    offset,
+                    expected: expected.type_name.to_string(),
+                    skipped: start..start + self.content.len(),
+                });
+            }
+            return;
+        }
+        for child in &self.children {
+            child.collect_diagnostics(input, diagnostics);
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! recover {
+    ($name:expr => $rule:expr, $sync:expr) => {
+        $crate::custom!($name => $crate::recover!($rule, $sync))
+    };
+    ($rule:expr, $sync:expr) => {
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Recover {
+                rule: $rule.clone(),
+                sync: $sync.clone(),
+            }),
+            *$crate::rule::RECOVER_ID,
+            "Recover",
+        )
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use rule::{Rule, ERROR_ID};
+    use rule_handler::Handler;
+
+    fn statements() -> Rule<'static> {
+        let letter = sor!(char!('a'), char!('b'), char!('c'));
+        let name = plus!("Name" => letter);
+        let statement = seq!("Statement" => str!("let "), cut!(), name, char!(';'));
+        seq!(star!(recover!(statement, char!(';'))), eof!())
+    }
+
+    #[test]
+    fn recover_is_transparent_on_success() {
+        let rule = recover!(char!('a'), char!(';'));
+        let result = rule.parse("a");
+        let result2 = rule.parse_with_handler("a", &Handler::new());
+        assert_eq!(result, result2);
+        assert_eq!(result.unwrap().type_name, "Char");
+    }
+
+    #[test]
+    fn recover_skips_to_sync() {
+        let rule = recover!(char!('a'), char!(';'));
+        let input = "xyz;b";
+        let node = rule.parse(input).unwrap();
+        assert_eq!(
+            node,
+            rule.parse_with_handler(input, &Handler::new()).unwrap()
+        );
+        assert_eq!(node.type_id, *ERROR_ID);
+        assert_eq!(node.type_name, "Error");
+        assert_eq!(node.content, "xyz;");
+        assert_eq!(node.children.len(), 1);
+        assert_eq!(node.children[0].type_name, "Char");
+        assert_eq!(node.children[0].content, "");
+        assert_eq!(
+            node.diagnostics(input),
+            vec![Diagnostic {
+                offset: 0,
+                expected: "Char".to_string(),
+                skipped: 0..4,
+            }]
+        );
+    }
+
+    #[test]
+    fn recover_skips_to_end_without_sync() {
+        let rule = recover!(char!('a'), char!(';'));
+        let node = rule.parse("xyz").unwrap();
+        assert_eq!(node.type_id, *ERROR_ID);
+        assert_eq!(node.content, "xyz");
+    }
+
+    #[test]
+    fn recover_fails_on_empty_input() {
+        let rule = star!(recover!(char!('a'), char!(';')));
+        let node = rule.parse("").unwrap();
+        assert_eq!(node.children.len(), 0);
+        assert_eq!(recover!(char!('a'), eof!()).parse(""), None);
+    }
+
+    #[test]
+    fn recover_continues_after_errors() {
+        let rule = statements();
+        let input = "let ab;let 1;let c;let ;";
+        let (tree, diagnostics) = rule.parse_with_recovery(input).unwrap();
+        assert_eq!(tree.content, input);
+        let statements = &tree.children[0].children;
+        assert_eq!(statements.len(), 4);
+        assert_eq!(statements[0].type_name, "Statement");
+        assert_eq!(statements[1].type_id, *ERROR_ID);
+        assert_eq!(statements[1].content, "let 1;");
+        assert_eq!(statements[2].type_name, "Statement");
+        assert_eq!(statements[3].content, "let ;");
+        assert_eq!(
+            diagnostics,
+            vec![
+                Diagnostic {
+                    offset: 11,
+                    expected: "Name".to_string(),
+                    skipped: 7..13,
+                },
+                Diagnostic {
+                    offset: 23,
+                    expected: "Name".to_string(),
+                    skipped: 19..24,
+                },
+            ]
+        );
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "expected Name at offset 11, skipped 7..13"
+        );
+    }
+
+    #[test]
+    fn recover_sync_search_starts_at_failure() {
+        let item = seq!(char!('a'), char!(';'));
+        let block = seq!(char!('{'), cut!(), star!(item), char!('}'));
+        let rule = recover!(block, char!(';'));
+        let input = "{a;a;b;c";
+        let node = rule.parse(input).unwrap();
+        assert_eq!(node.type_id, *ERROR_ID);
+        assert_eq!(node.content, "{a;a;b;");
+        assert_eq!(node.children[0].type_id, *rule::CHAR_ID);
+        assert_eq!(node.diagnostics(input)[0].offset, 5);
+        // Nodes of another input are not offsets into this one.
+        let copy = String::from(input);
+        assert_eq!(node.diagnostics(&copy), []);
+    }
+
+    #[test]
+    fn parse_with_recovery_without_errors() {
+        let rule = statements();
+        let (tree, diagnostics) = rule.parse_with_recovery("let a;").unwrap();
+        assert!(diagnostics.is_empty());
+        assert_eq!(tree.content, "let a;");
+        assert_eq!(tree.children[0].children[0].type_name, "Statement");
+    }
+
+    #[test]
+    fn parse_with_recovery_reports_unrecoverable_errors() {
+        let rule = seq!(recover!(char!('a'), char!(';')), char!('b'));
+        let error = rule.parse_with_recovery("x;c").unwrap_err();
+        assert_eq!(error.expected, "Char");
+        assert_eq!(error.offset, 2);
+    }
+}
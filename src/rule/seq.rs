@@ -1,54 +1,88 @@
 use crate::COUNTER;
 
-use crate::Node;
-use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
+use super::{Rule, RuleKind, CUT_ID};
+
 pub static SEQ_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
-pub struct Seq<'a> {
-    pub rules: Vec<crate::rule::Rule<'a>>,
+pub struct Seq<'a, I: ?Sized + Slice = str> {
+    pub rules: Vec<Rule<'a, I>>,
 }
 
-impl<'a> Parsable<'a> for Seq<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Seq<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
         let mut node = Node::new_empty(id, name);
         let mut size = 0;
+        let mut committed = false;
         for rule in &self.rules {
-            if let Some(child) = rule.parse(&input[size..]) {
-                size += child.content.len();
-                node.add_child(child);
-            } else {
-                return None;
+            match rule.apply(&input[size..]) {
+                Ok(child) => {
+                    committed |= rule.id == *CUT_ID;
+                    size += child.content.len();
+                    node.add_child(child);
+                }
+                Err(failure) => return Err(Self::commit(failure, committed, &input[size..], rule)),
             }
         }
         node.content = &input[0..size];
-        Some(node)
+        Ok(node)
     }
 
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
         let mut node = Node::new_empty(id, name);
         let mut size = 0;
+        let mut committed = false;
         for rule in &self.rules {
-            if let Some(child) = rule.parse_with_handler(&input[size..], handler) {
-                size += child.content.len();
-                node.add_child(child);
-            } else {
-                handler.handle_failure(id);
-                return None;
+            match rule.apply_with_handler(&input[size..], handler) {
+                Ok(child) => {
+                    committed |= rule.id == *CUT_ID;
+                    size += child.content.len();
+                    handler.add_child(&mut node, child);
+                }
+                Err(failure) => {
+                    handler.handle_failure(id);
+                    return Err(Self::commit(failure, committed, &input[size..], rule));
+                }
             }
         }
         node.content = &input[0..size];
         handler.handle_success(&mut node);
-        Some(node)
+        Ok(node)
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Seq(&self.rules)
+    }
+}
+
+impl<'a, I: ?Sized + Slice> Seq<'a, I> {
+    /// Once a cut has been passed, a failing rule may no longer backtrack.
+    fn commit(
+        failure: Failure<'a, I>,
+        committed: bool,
+        rest: &'a I,
+        rule: &Rule<'a, I>,
+    ) -> Failure<'a, I> {
+        match failure {
+            Failure::Backtrack if committed => Failure::Cut {
+                rest,
+                id: rule.id,
+                name: rule.name.clone(),
+            },
+            failure => failure,
+        }
     }
 }
 
@@ -56,11 +90,11 @@ impl<'a> Parsable<'a> for Seq<'a> {
 macro_rules! seq {
     ($($rule:expr),*) => {
         $crate::rule::Rule::new(
-            Box::new(crate::rule::Seq {
+            Box::new($crate::rule::Seq {
                 rules: vec![$($rule.clone()),*],
             }),
-            *crate::rule::SEQ_ID,
-            "Seq".to_string(),
+            *$crate::rule::SEQ_ID,
+            "Seq",
         )
     };
     ($name:expr => $($rule:expr),*) => {
@@ -1,46 +1,129 @@
+use std::cell::{Cell, OnceCell};
+
 use crate::COUNTER;
 
-use crate::Node;
-use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
+use super::{first_chars, FirstChars, Rule, RuleKind};
+
 pub static SOR_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
 
-pub struct Sor<'a> {
-    pub options: Vec<crate::rule::Rule<'a>>,
+thread_local! {
+    static DISPATCH: Cell<bool> = const { Cell::new(true) };
+}
+
+/// Turns skipping options by their first characters on or off for the current thread,
+/// for benchmarking it. On by default.
+#[doc(hidden)]
+pub fn set_dispatch(dispatch: bool) {
+    DISPATCH.with(|cell| cell.set(dispatch));
+}
+
+/// Tries the options in order, returning the first match.
+///
+/// Options that cannot start with the next character of the input are skipped without
+/// being tried. Their first characters are worked out on the first parse, once all rules
+/// are initialized. Parses with a handler skip the same options, and tell the listeners
+/// about them with `Listener::skipped`.
+pub struct Sor<'a, I: ?Sized + Slice = str> {
+    pub options: Vec<Rule<'a, I>>,
+    first: OnceCell<Vec<Option<FirstChars>>>,
+}
+
+impl<'a, I: ?Sized + Slice> Sor<'a, I> {
+    pub fn new(options: Vec<Rule<'a, I>>) -> Sor<'a, I> {
+        Sor {
+            options,
+            first: OnceCell::new(),
+        }
+    }
+
+    /// The next character of the input, if options are skipped by their first characters.
+    fn next(input: &I) -> Option<char> {
+        if DISPATCH.with(Cell::get) {
+            input.first().map(|(unit, _)| I::key(unit))
+        } else {
+            None
+        }
+    }
+
+    /// The first characters of each option, `None` for options that can start with any.
+    fn first(&self) -> &[Option<FirstChars>] {
+        self.first
+            .get_or_init(|| self.options.iter().map(first_chars).collect())
+    }
+}
+
+/// Whether an option with the first characters can match before `next`.
+fn may_start(next: Option<char>, first: &Option<FirstChars>) -> bool {
+    match (next, first) {
+        (Some(c), Some(first)) => first.contains(c),
+        _ => true,
+    }
 }
 
-impl<'a> Parsable<'a> for Sor<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
-        for rule in &self.options {
-            if let Some(node) = rule.parse(input) {
-                let mut sor_node = Node::new(node.content, id, &name);
-                sor_node.add_child(node);
-                return Some(sor_node);
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Sor<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
+        let next = Self::next(input);
+        for (rule, first) in self.options.iter().zip(self.first()) {
+            if !may_start(next, first) {
+                continue;
+            }
+            match rule.apply(input) {
+                Ok(node) => {
+                    let mut sor_node = Node::new(node.content, id, name);
+                    sor_node.add_child(node);
+                    return Ok(sor_node);
+                }
+                Err(Failure::Backtrack) => continue,
+                Err(failure) => return Err(failure),
             }
         }
-        None
+        Err(Failure::Backtrack)
     }
 
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
-        for rule in &self.options {
-            if let Some(node) = rule.parse_with_handler(input, handler) {
-                let mut sor_node = Node::new(node.content, id, name);
-                sor_node.add_child(node);
-                handler.handle_success(&mut sor_node);
-                return Some(sor_node);
+        let next = Self::next(input);
+        // Start of the options skipped since the last one tried.
+        let mut skipped = 0;
+        for (i, (rule, first)) in self.options.iter().zip(self.first()).enumerate() {
+            if !may_start(next, first) {
+                continue;
+            }
+            handler.notify_skipped(&self.options[skipped..i], input);
+            skipped = i + 1;
+            match rule.apply_with_handler(input, handler) {
+                Ok(node) => {
+                    let mut sor_node = Node::new(node.content, id, name);
+                    handler.add_child(&mut sor_node, node);
+                    handler.handle_success(&mut sor_node);
+                    return Ok(sor_node);
+                }
+                Err(Failure::Backtrack) => continue,
+                Err(failure) => {
+                    handler.handle_failure(id);
+                    return Err(failure);
+                }
             }
         }
+        handler.notify_skipped(&self.options[skipped..], input);
         handler.handle_failure(id);
-        None
+        Err(Failure::Backtrack)
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Sor(&self.options)
     }
 }
 
@@ -50,12 +133,10 @@ macro_rules! sor {
         $crate::custom!($name => $crate::sor!($($rule),*))
     };
     ($($rule:expr),*) => {
-        crate::rule::Rule::new(
-            Box::new(crate::rule::Sor {
-                options: vec![$($rule.clone()),*],
-            }),
-            *crate::rule::SOR_ID,
-            "Sor".to_string(),
+        $crate::rule::Rule::new(
+            Box::new($crate::rule::Sor::new(vec![$($rule.clone()),*])),
+            *$crate::rule::SOR_ID,
+            "Sor",
         )
     };
 
@@ -69,7 +150,7 @@ mod tests {
 
     #[test]
     fn macro_sor_works() {
-        let rule = sor!("test" =>
+        let rule: rule::Rule = sor!("test" =>
             sor!(),
             sor!()
         );
@@ -81,7 +162,7 @@ mod tests {
         let rule = sor!(char!('a'), char!('b'));
         let input = "a";
         let mut sor_node = Node::new("a", rule.id, &rule.name);
-        sor_node.add_child(Node::new("a", *CHAR_ID, &"Char".to_string()));
+        sor_node.add_child(Node::new("a", *CHAR_ID, "Char"));
 
         let result = rule.parse(input);
 
@@ -93,7 +174,7 @@ mod tests {
         let rule = sor!(char!('a'), char!('b'));
         let input = "b";
         let mut sor_node = Node::new("b", rule.id, &rule.name);
-        sor_node.add_child(Node::new("b", *CHAR_ID, &"Char".to_string()));
+        sor_node.add_child(Node::new("b", *CHAR_ID, "Char"));
 
         let result = rule.parse(input);
         let result2 = rule.parse_with_handler(input, &Handler::new());
@@ -131,7 +212,7 @@ mod tests {
         let rule = sor!(char!('a'), char!('b'));
         let input = "abc";
         let mut sor_node = Node::new("a", rule.id, &rule.name);
-        sor_node.add_child(Node::new("a", *CHAR_ID, &"Char".to_string()));
+        sor_node.add_child(Node::new("a", *CHAR_ID, "Char"));
 
         let result = rule.parse(input);
         let result2 = rule.parse_with_handler(input, &Handler::new());
@@ -139,4 +220,35 @@ mod tests {
 
         assert_eq!(result, Some(sor_node));
     }
+
+    #[test]
+    fn sor_rule_skips_only_options_that_cannot_match() {
+        let rule = sor!(
+            seq!(char!('a'), char!('b')),
+            seq!(opt!(char!('a')), char!('c')),
+            str!("bc")
+        );
+        for input in ["ab", "ac", "c", "bc", "b", ""] {
+            let result = rule.parse(input);
+            let result2 = rule.parse_with_handler(input, &Handler::new());
+            assert_eq!(result, result2, "{:?}", input);
+        }
+        assert_eq!(rule.parse("ac").unwrap().content, "ac");
+
+        // An option that passes a cut before consuming anything fails on any input.
+        let rule = sor!(seq!(cut!(), char!('a')), char!('b'));
+        assert!(matches!(rule.apply("b"), Err(Failure::Cut { .. })));
+    }
+
+    #[test]
+    fn sor_tries_every_option_without_dispatch() {
+        let rule = sor!(char!('a'), char!('b'));
+        let (_, trace) = trace::Trace::record(&rule, "b");
+        assert_eq!(trace.attempts[1].outcome, trace::Outcome::Skipped);
+        rule::set_dispatch(false);
+        let (node, trace) = trace::Trace::record(&rule, "b");
+        rule::set_dispatch(true);
+        assert_eq!(node.unwrap().content, "b");
+        assert_eq!(trace.attempts[1].outcome, trace::Outcome::Failure);
+    }
 }
@@ -1,46 +1,66 @@
 use crate::COUNTER;
 
-use crate::Node;
-use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
+use crate::{Parsable, Slice};
 use once_cell::sync::Lazy;
 
-use super::Rule;
+use super::{Rule, RuleKind};
 
 pub static STAR_ID: Lazy<usize> =
     Lazy::new(|| COUNTER.fetch_add(1, std::sync::atomic::Ordering::SeqCst));
-pub struct Star<'a> {
-    pub rule: Rule<'a>,
+pub struct Star<'a, I: ?Sized + Slice = str> {
+    pub rule: Rule<'a, I>,
 }
 
-impl<'a> Parsable<'a> for Star<'a> {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
-        let mut node = Node::new_empty(id, &name);
+impl<'a, I: ?Sized + Slice> Parsable<'a, I> for Star<'a, I> {
+    fn parse(&self, input: &'a I, id: usize, name: &Name) -> ParseResult<'a, I> {
+        let mut node = Node::new_empty(id, name);
         let mut size = 0;
-        while let Some(child) = self.rule.parse(&input[size..]) {
-            size += child.content.len();
-            node.add_child(child);
+        loop {
+            match self.rule.apply(&input[size..]) {
+                Ok(child) => {
+                    size += child.content.len();
+                    node.add_child(child);
+                }
+                Err(Failure::Backtrack) => break,
+                Err(failure) => return Err(failure),
+            }
         }
         node.content = &input[0..size];
-        Some(node)
+        Ok(node)
     }
 
     fn parse_with_handler(
         &self,
-        input: &'a str,
+        input: &'a I,
         id: usize,
-        name: &String,
-        handler: &crate::rule_handler::Handler<'a>,
-    ) -> Option<Node<'a>> {
+        name: &Name,
+        handler: &crate::rule_handler::Handler<'a, I>,
+    ) -> ParseResult<'a, I> {
         handler.handle_pre_parse(id);
         let mut node = Node::new_empty(id, name);
         let mut size = 0;
-        while let Some(child) = self.rule.parse_with_handler(&input[size..], handler) {
-            size += child.content.len();
-            node.add_child(child);
+        loop {
+            match self.rule.apply_with_handler(&input[size..], handler) {
+                Ok(child) => {
+                    size += child.content.len();
+                    handler.add_child(&mut node, child);
+                }
+                Err(Failure::Backtrack) => break,
+                Err(failure) => {
+                    handler.handle_failure(id);
+                    return Err(failure);
+                }
+            }
         }
         node.content = &input[0..size];
         handler.handle_success(&mut node);
-        Some(node)
+        Ok(node)
+    }
+
+    fn kind(&self) -> RuleKind<'_, 'a, I> {
+        RuleKind::Star(&self.rule)
     }
 }
 
@@ -53,7 +73,7 @@ macro_rules! star {
         $crate::rule::Rule::new(
             Box::new($crate::rule::Star { rule: $rule }),
             *$crate::rule::STAR_ID,
-            "Star".to_string(),
+            "Star",
         )
     };
 }
@@ -97,10 +117,10 @@ mod tests {
         let rule = star!(char!('a'));
         let input = "aaaab";
         let mut expected_node = Node::new("aaaa", rule.id, &rule.name);
-        expected_node.add_child(Node::new("a", rule.id, &"Char".to_string()));
-        expected_node.add_child(Node::new("a", rule.id, &"Char".to_string()));
-        expected_node.add_child(Node::new("a", rule.id, &"Char".to_string()));
-        expected_node.add_child(Node::new("a", rule.id, &"Char".to_string()));
+        expected_node.add_child(Node::new("a", rule.id, "Char"));
+        expected_node.add_child(Node::new("a", rule.id, "Char"));
+        expected_node.add_child(Node::new("a", rule.id, "Char"));
+        expected_node.add_child(Node::new("a", rule.id, "Char"));
 
         let result = rule.parse(input);
         let result2 = rule.parse_with_handler(input, &Handler::new());
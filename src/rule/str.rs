@@ -1,8 +1,9 @@
 use crate::COUNTER;
 
 use super::*;
-use crate::Node;
 use crate::Parsable;
+use crate::{Failure, ParseResult};
+use crate::{Name, Node};
 use once_cell::sync::Lazy;
 
 pub static STR_ID: Lazy<usize> =
@@ -13,11 +14,11 @@ pub struct Str {
 }
 
 impl<'a> Parsable<'a> for Str {
-    fn parse(&self, input: &'a str, id: usize, name: &String) -> Option<Node<'a>> {
+    fn parse(&self, input: &'a str, id: usize, name: &Name) -> ParseResult<'a> {
         if input.starts_with(&self.s) {
-            Some(Node::new(&input[0..self.s.len()], id, &name))
+            Ok(Node::new(&input[0..self.s.len()], id, name))
         } else {
-            None
+            Err(Failure::Backtrack)
         }
     }
 
@@ -25,18 +26,25 @@ impl<'a> Parsable<'a> for Str {
         &self,
         input: &'a str,
         id: usize,
-        name: &String,
+        name: &Name,
         handler: &Handler<'a>,
-    ) -> Option<Node<'a>> {
+    ) -> ParseResult<'a> {
         handler.handle_pre_parse(id);
-        if let Some(mut success) = self.parse(input, id, name) {
-            handler.handle_success(&mut success);
-            Some(success)
-        } else {
-            handler.handle_failure(id);
-            None
+        match self.parse(input, id, name) {
+            Ok(mut success) => {
+                handler.handle_success(&mut success);
+                Ok(success)
+            }
+            Err(failure) => {
+                handler.handle_failure(id);
+                Err(failure)
+            }
         }
     }
+
+    fn kind(&self) -> RuleKind<'_, 'a> {
+        RuleKind::Str(&self.s)
+    }
 }
 
 #[macro_export]
@@ -45,7 +53,7 @@ macro_rules! str {
         $crate::rule::Rule::new(
             Box::new($crate::rule::Str { s: $s.to_string() }),
             *$crate::rule::STR_ID,
-            "Str".to_string(),
+            "Str",
         )
     };
     ($name:expr => $s:expr) => {
@@ -1,27 +1,83 @@
 use std::collections::HashMap;
+use std::rc::Rc;
 
-use crate::Node;
+use crate::rule::Rule;
+use crate::{Failure, Node, Slice};
 
 type PreParseFn<'a> = fn() -> ();
-type SuccessFn<'a> = fn(&mut Node<'a>) -> ();
+type SuccessFn<'a, I> = fn(&mut Node<'a, I>) -> ();
 type FailureFn<'a> = fn() -> ();
+type ReuseFn<'a, I> = Box<dyn Fn(usize, &'a I) -> Option<Node<'a, I>> + 'a>;
+
+/// Gets notified about every rule that is applied while parsing with a `Handler`,
+/// at the same points as the pre-parse, success and failure handlers.
+/// `input` is the remaining input at the point the rule is applied.
+pub trait Listener<'a, I: ?Sized + Slice = str> {
+    fn pre_parse(&self, _rule: &Rule<'a, I>, _input: &'a I) {}
+    fn success(&self, _rule: &Rule<'a, I>, _input: &'a I, _node: &Node<'a, I>) {}
+    fn failure(&self, _rule: &Rule<'a, I>, _input: &'a I, _failure: &Failure<'a, I>) {}
+    /// Options of a `Sor` it did not try, as they cannot start with the next character.
+    /// They are told about in order, in between the options that are tried.
+    fn skipped(&self, _options: &[Rule<'a, I>], _input: &'a I) {}
+}
 
-pub struct Handler<'a> {
+pub struct Handler<'a, I: ?Sized + Slice = str> {
     pre_parse_map: HashMap<usize, Vec<PreParseFn<'a>>>,
-    success_map: HashMap<usize, Vec<SuccessFn<'a>>>,
+    success_map: HashMap<usize, Vec<SuccessFn<'a, I>>>,
     failure_map: HashMap<usize, Vec<FailureFn<'a>>>,
+    reuse: Option<ReuseFn<'a, I>>,
+    listeners: Vec<Rc<dyn Listener<'a, I> + 'a>>,
+    build_tree: bool,
+}
+
+impl<I: ?Sized + Slice> Default for Handler<'_, I> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<'a> Handler<'a> {
-    pub fn new() -> Handler<'a> {
+impl<'a, I: ?Sized + Slice> Handler<'a, I> {
+    pub fn new() -> Handler<'a, I> {
         Handler {
             success_map: HashMap::new(),
             failure_map: HashMap::new(),
             pre_parse_map: HashMap::new(),
+            reuse: None,
+            listeners: Vec::new(),
+            build_tree: true,
+        }
+    }
+
+    /// With `false`, rules drop the nodes of their sub-rules instead of adding them as
+    /// children, so that only the nodes of the rules being parsed are kept in memory.
+    /// For listeners that take what they need from each node as it is made.
+    pub fn set_build_tree(&mut self, build_tree: bool) {
+        self.build_tree = build_tree;
+    }
+
+    /// What rules do with the nodes of their sub-rules, see `set_build_tree`.
+    pub fn add_child(&self, node: &mut Node<'a, I>, child: Node<'a, I>) {
+        if self.build_tree {
+            node.add_child(child);
         }
     }
 
-    pub fn add_success_handler(&mut self, id: usize, handler: SuccessFn<'a>) {
+    /// Adds a listener that is notified about every rule applied while parsing.
+    /// Keep a clone of the `Rc` to read the state it collected afterwards.
+    pub fn add_listener(&mut self, listener: Rc<dyn Listener<'a, I> + 'a>) {
+        self.listeners.push(listener);
+    }
+
+    /// Sets a function that `Custom` rules ask for an already parsed node
+    /// before parsing the input themselves.
+    pub fn set_reuse_handler(
+        &mut self,
+        handler: impl Fn(usize, &'a I) -> Option<Node<'a, I>> + 'a,
+    ) {
+        self.reuse = Some(Box::new(handler));
+    }
+
+    pub fn add_success_handler(&mut self, id: usize, handler: SuccessFn<'a, I>) {
         if let Some(vec) = self.success_map.get_mut(&id) {
             vec.push(handler);
         } else {
@@ -45,8 +101,14 @@ impl<'a> Handler<'a> {
         }
     }
 
-    pub fn handle_success(&self, node: &mut Node<'a>) {
-        if let Some(vec) = self.success_map.get(&node.type_id) {
+    pub fn handle_success(&self, node: &mut Node<'a, I>) {
+        self.handle_success_of(node.type_id, node);
+    }
+
+    /// Calls the success handlers of the rule with the id, for rules like `Must` that
+    /// return the node of their sub-rule as their own.
+    pub fn handle_success_of(&self, id: usize, node: &mut Node<'a, I>) {
+        if let Some(vec) = self.success_map.get(&id) {
             for handler in vec {
                 handler(node);
             }
@@ -61,6 +123,37 @@ impl<'a> Handler<'a> {
         }
     }
 
+    pub fn handle_reuse(&self, type_id: usize, input: &'a I) -> Option<Node<'a, I>> {
+        self.reuse.as_ref().and_then(|reuse| reuse(type_id, input))
+    }
+
+    pub fn notify_pre_parse(&self, rule: &Rule<'a, I>, input: &'a I) {
+        for listener in &self.listeners {
+            listener.pre_parse(rule, input);
+        }
+    }
+
+    pub fn notify_success(&self, rule: &Rule<'a, I>, input: &'a I, node: &Node<'a, I>) {
+        for listener in &self.listeners {
+            listener.success(rule, input, node);
+        }
+    }
+
+    pub fn notify_failure(&self, rule: &Rule<'a, I>, input: &'a I, failure: &Failure<'a, I>) {
+        for listener in &self.listeners {
+            listener.failure(rule, input, failure);
+        }
+    }
+
+    pub fn notify_skipped(&self, options: &[Rule<'a, I>], input: &'a I) {
+        if options.is_empty() {
+            return;
+        }
+        for listener in &self.listeners {
+            listener.skipped(options, input);
+        }
+    }
+
     pub fn handle_pre_parse(&self, type_id: usize) {
         if let Some(vec) = self.pre_parse_map.get(&type_id) {
             for handler in vec {
@@ -88,7 +181,7 @@ mod tests {
     fn handler_adds_success_handler() {
         let mut handler = Handler::new();
         let id = 1;
-        let mut node = Node::new_empty(id, &"Test".to_string());
+        let mut node = Node::new_empty(id, "Test");
 
         handler.add_success_handler(id, success_fn);
         handler.handle_success(&mut node);
@@ -99,7 +192,7 @@ mod tests {
     #[test]
     #[should_panic(expected = "Failure")]
     fn handler_adds_failure_handler() {
-        let mut handler = Handler::new();
+        let mut handler: Handler = Handler::new();
         let id = 1;
         handler.add_failure_handler(id, failure_fn);
         handler.handle_failure(id);
@@ -108,9 +201,8 @@ mod tests {
     #[test]
     #[should_panic(expected = "Pre-parse")]
     fn handler_adds_pre_parse_handler() {
-        let mut handler = Handler::new();
+        let mut handler: Handler = Handler::new();
         let id = 1;
-        let mut node = Node::new_empty(id, &"Test".to_string());
         let pre_parse_fn = || {
             panic!("Pre-parse");
         };
@@ -123,7 +215,7 @@ mod tests {
     fn handler_handles_multiple_success_handlers() {
         let mut handler = Handler::new();
         let id = 1;
-        let mut node = Node::new_empty(id, &"Test".to_string());
+        let mut node = Node::new_empty(id, "Test");
 
         handler.add_success_handler(id, success_fn);
         handler.add_success_handler(id, success_fn);
@@ -0,0 +1,91 @@
+use std::fmt;
+use std::ops::{Index, Range, RangeFrom, RangeTo};
+
+/// What rules parse: text, or bytes for binary data (see `rule::binary`).
+/// Offsets into either are in bytes.
+pub trait Slice:
+    fmt::Debug
+    + PartialEq
+    + Index<Range<usize>, Output = Self>
+    + Index<RangeFrom<usize>, Output = Self>
+    + Index<RangeTo<usize>, Output = Self>
+    + 'static
+{
+    /// What a `Char` rule matches: a character of text, or a byte.
+    type Unit: fmt::Debug + Clone + Copy + PartialEq;
+
+    fn empty() -> &'static Self;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn as_ptr(&self) -> *const u8;
+
+    /// The first unit and its length in bytes.
+    fn first(&self) -> Option<(Self::Unit, usize)>;
+
+    fn starts_with(&self, prefix: &Self) -> bool;
+
+    /// The character a unit is known as in `FirstChars`, a byte being the character with
+    /// its value.
+    fn key(unit: Self::Unit) -> char;
+}
+
+impl Slice for str {
+    type Unit = char;
+
+    fn empty() -> &'static str {
+        ""
+    }
+
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        str::as_ptr(self)
+    }
+
+    fn first(&self) -> Option<(char, usize)> {
+        self.chars().next().map(|c| (c, c.len_utf8()))
+    }
+
+    fn starts_with(&self, prefix: &str) -> bool {
+        str::starts_with(self, prefix)
+    }
+
+    fn key(unit: char) -> char {
+        unit
+    }
+}
+
+impl Slice for [u8] {
+    type Unit = u8;
+
+    fn empty() -> &'static [u8] {
+        &[]
+    }
+
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn as_ptr(&self) -> *const u8 {
+        <[u8]>::as_ptr(self)
+    }
+
+    fn first(&self) -> Option<(u8, usize)> {
+        <[u8]>::first(self).map(|&byte| (byte, 1))
+    }
+
+    fn starts_with(&self, prefix: &[u8]) -> bool {
+        <[u8]>::starts_with(self, prefix)
+    }
+
+    fn key(unit: u8) -> char {
+        char::from(unit)
+    }
+}
@@ -0,0 +1,61 @@
+//! Helpers for the tests that check other engines against the rules.
+
+use std::cell::Cell;
+use std::io::{self, Read};
+use std::rc::Rc;
+
+use crate::generate::{GenerateOptions, Generator, Sentences};
+use crate::rule::{unnamed_ids, Rule};
+use crate::Node;
+
+/// The fixed inputs, followed by `count` sentences of the rule and as many near misses.
+pub fn inputs<'a>(
+    rule: &Rule<'a>,
+    sentences: &'a Sentences,
+    fixed: &[&'a str],
+    count: usize,
+) -> Vec<&'a str> {
+    let mut generator = Generator::new(GenerateOptions::new());
+    let mut inputs = fixed.to_vec();
+    for _ in 0..count {
+        inputs.extend(generator.sentence(rule, sentences));
+        inputs.extend(generator.near_miss(rule, sentences));
+    }
+    inputs
+}
+
+/// The tree with the ids of named rules left out, as other engines may number them
+/// differently.
+pub fn shape(node: &Node) -> String {
+    let id = unnamed_ids()
+        .iter()
+        .find(|(id, _, _)| *id == node.type_id)
+        .map_or("named", |(_, _, name)| name);
+    let children: Vec<_> = node.children.iter().map(shape).collect();
+    format!(
+        "({} {} {:?} [{}])",
+        node.type_name,
+        id,
+        node.content,
+        children.join(" ")
+    )
+}
+
+/// Reads a few bytes at a time, to split characters, counting them in the cell.
+pub struct Trickle<'a>(pub &'a [u8], pub Rc<Cell<usize>>);
+
+impl Trickle<'_> {
+    pub fn new(bytes: &[u8]) -> Trickle<'_> {
+        Trickle(bytes, Rc::default())
+    }
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.0.len().min(buf.len()).min(3);
+        buf[..len].copy_from_slice(&self.0[..len]);
+        self.0 = &self.0[len..];
+        self.1.set(self.1.get() + len);
+        Ok(len)
+    }
+}
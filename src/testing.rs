@@ -0,0 +1,394 @@
+//! Helpers for testing grammars, enabled by the `testing` feature.
+//!
+//! The assertion macros panic with the rendered parse error, so a failing test shows
+//! where the input went wrong. `RoundTrip` checks a grammar against sentences generated
+//! from it.
+
+use std::collections::HashSet;
+
+use crate::check::collect_all;
+use crate::coverage::Coverage;
+use crate::generate::{GenerateOptions, Generator, Sentences};
+use crate::rule::{Rule, RuleKind};
+use crate::{Filter, Grammar, Node, ParseError};
+
+/// Parses the whole input, or explains why it did not.
+pub fn check_parses<'a>(rule: &Rule<'a>, input: &'a str) -> Result<Node<'a>, String> {
+    match rule.try_parse(input) {
+        Ok(tree) if tree.content.len() == input.len() => Ok(tree),
+        Ok(tree) => Err(format!(
+            "{} only matched {} of {} bytes, the rest is {:?}",
+            rule.name,
+            tree.content.len(),
+            input.len(),
+            &input[tree.content.len()..]
+        )),
+        Err(error) => Err(error.render(input, "<input>")),
+    }
+}
+
+/// Checks that parsing fails at `offset`, returning the error.
+/// A rule that stops early without failing counts as failing where it stopped.
+pub fn check_fails_at<'a>(
+    rule: &Rule<'a>,
+    input: &'a str,
+    offset: usize,
+) -> Result<ParseError, String> {
+    let error = match rule.parse_all(input) {
+        Ok(_) => return Err(format!("{} parsed {:?}", rule.name, input)),
+        Err(error) => error,
+    };
+    if error.offset == offset {
+        Ok(error)
+    } else {
+        Err(format!(
+            "expected a failure at offset {}, got one at {}:\n{}",
+            offset,
+            error.offset,
+            error.render(input, "<input>")
+        ))
+    }
+}
+
+/// Checks that the tree of the input, filtered if there is a filter, is the given
+/// S-expression. Whitespace between the parts of the S-expression does not matter.
+/// A filtered tree keeps the root node the filter adds only if it has several children.
+pub fn check_tree<'a>(
+    rule: &Rule<'a>,
+    input: &'a str,
+    filter: Option<&Filter>,
+    sexpr: &str,
+) -> Result<(), String> {
+    let tree = check_parses(rule, input)?;
+    let tree = match filter {
+        Some(filter) => filter.filter_tree(tree),
+        None => tree,
+    };
+    let actual = tree.to_sexpr();
+    if normalize(&actual) == normalize(sexpr) {
+        Ok(())
+    } else {
+        Err(format!(
+            "trees differ\n  expected: {}\n    actual: {}",
+            normalize(sexpr),
+            actual
+        ))
+    }
+}
+
+/// Collapses whitespace outside of strings to single spaces, and removes it next to parentheses.
+fn normalize(sexpr: &str) -> String {
+    let mut result = String::new();
+    let mut chars = sexpr.chars();
+    let mut space = false;
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            space = true;
+            continue;
+        }
+        if space && c != ')' && !result.is_empty() && !result.ends_with('(') {
+            result.push(' ');
+        }
+        space = false;
+        result.push(c);
+        if c == '"' {
+            while let Some(c) = chars.next() {
+                result.push(c);
+                match c {
+                    '\\' => result.extend(chars.next()),
+                    '"' => break,
+                    _ => {}
+                }
+            }
+        }
+    }
+    result
+}
+
+/// The contents of the leaves of the tree, in order.
+/// For an unfiltered tree this is the input it was parsed from, filters may leave parts out.
+pub fn unparse(node: &Node) -> String {
+    fn collect(node: &Node, result: &mut String) {
+        if node.children.is_empty() {
+            result.push_str(node.content);
+        }
+        for child in &node.children {
+            collect(child, result);
+        }
+    }
+    let mut result = String::new();
+    collect(node, &mut result);
+    result
+}
+
+/// Parses sentences generated from a rule, then checks that unparsing each tree and
+/// parsing the result gives the same tree again. Only sentences the rule parses are
+/// generated (see `Generator::sentence`), so it also fails if an alternative the generator
+/// walked through never matched in them, as the rule rejects or misreads its sentences.
+///
+/// The sentences and unparsed texts are kept here, as the rules can only parse text that
+/// outlives them, so create the round trip before the grammar and run it once.
+pub struct RoundTrip {
+    /// Number of sentences to generate, some may be left out if they exceed the depth limit
+    /// or the rule parsed none of the walks the generator tried.
+    pub count: usize,
+    /// Trees are compared after filtering, so that unparsing can leave out
+    /// or normalize hidden parts like whitespace.
+    pub filter: Option<Filter>,
+    /// Turns a filtered tree back into text, `unparse` by default.
+    pub unparse: fn(&Node) -> String,
+    generator: Generator,
+    sentences: Sentences,
+    unparsed: Vec<String>,
+}
+
+impl RoundTrip {
+    pub fn new(options: GenerateOptions) -> RoundTrip {
+        RoundTrip {
+            count: 100,
+            filter: None,
+            unparse,
+            generator: Generator::new(options),
+            sentences: Sentences::new(),
+            unparsed: Vec::new(),
+        }
+    }
+
+    fn filter<'a>(filter: &Option<Filter>, tree: Node<'a>) -> Node<'a> {
+        match filter {
+            Some(filter) => filter.filter_ast(tree),
+            None => tree,
+        }
+    }
+
+    /// Runs the checks, returning the number of sentences checked or a description
+    /// of the first problem.
+    pub fn run<'a>(&'a mut self, rule: &Rule<'a>) -> Result<usize, String> {
+        let RoundTrip {
+            count,
+            filter,
+            unparse,
+            generator,
+            sentences,
+            unparsed,
+        } = self;
+        let sentences: Vec<&'a str> = (0..*count)
+            .filter_map(|_| generator.sentence(rule, sentences))
+            .collect();
+        if sentences.is_empty() {
+            return Err(format!(
+                "no sentences could be generated from {}",
+                rule.name
+            ));
+        }
+
+        let mut coverage = Coverage::new();
+        let mut trees = Vec::new();
+        for sentence in &sentences {
+            let tree = coverage
+                .record(rule, sentence)
+                .filter(|tree| tree.content.len() == sentence.len())
+                .expect("the generator only returns sentences the rule parses");
+            let tree = Self::filter(filter, tree);
+            unparsed.push(unparse(&tree));
+            trees.push(tree);
+        }
+        let unparsed: &'a Vec<String> = unparsed;
+
+        for ((sentence, text), tree) in sentences.iter().zip(unparsed).zip(&trees) {
+            let again = check_parses(rule, text).map_err(|error| {
+                format!(
+                    "{:?} was unparsed as {:?}, which failed:\n{}",
+                    sentence, text, error
+                )
+            })?;
+            let (before, after) = (tree.to_sexpr(), Self::filter(filter, again).to_sexpr());
+            if before != after {
+                return Err(format!(
+                    "{:?} was unparsed as {:?}, which parses differently\n  before: {}\n   after: {}",
+                    sentence, text, before, after
+                ));
+            }
+        }
+
+        let mut rules = Vec::new();
+        collect_all(rule, &mut HashSet::new(), &mut rules);
+        for sor in &rules {
+            let options = match sor.kind() {
+                Some(RuleKind::Sor(options)) => options,
+                _ => continue,
+            };
+            for (i, option) in options.iter().enumerate() {
+                let walks = generator.walks(sor, i);
+                if walks.tried > 0 && coverage.alternative(sor, i) == 0 {
+                    return Err(format!(
+                        "alternative {} `{}` of `{}` never matched, the rule rejected {} of {} walks through it",
+                        i + 1,
+                        Grammar::expression(option),
+                        Grammar::expression(sor),
+                        walks.tried - walks.parsed,
+                        walks.tried
+                    ));
+                }
+            }
+        }
+        Ok(sentences.len())
+    }
+}
+
+/// Asserts that the rule parses the whole input and returns the tree.
+#[macro_export]
+macro_rules! assert_parses {
+    ($rule:expr, $input:expr) => {
+        match $crate::testing::check_parses(&$rule, $input) {
+            Ok(tree) => tree,
+            Err(message) => panic!("assertion failed: parses\n{}", message),
+        }
+    };
+}
+
+/// Asserts that parsing the input fails at the byte offset and returns the error.
+#[macro_export]
+macro_rules! assert_fails_at {
+    ($rule:expr, $input:expr, $offset:expr) => {
+        match $crate::testing::check_fails_at(&$rule, $input, $offset) {
+            Ok(error) => error,
+            Err(message) => panic!("assertion failed: fails at\n{}", message),
+        }
+    };
+}
+
+/// Asserts that the tree of the input is the S-expression, see `check_tree`.
+/// With a filter as the third argument the tree is filtered first.
+#[macro_export]
+macro_rules! assert_tree {
+    ($rule:expr, $input:expr, $sexpr:expr) => {
+        if let Err(message) = $crate::testing::check_tree(&$rule, $input, None, $sexpr) {
+            panic!("assertion failed: tree\n{}", message)
+        }
+    };
+    ($rule:expr, $input:expr, $filter:expr, $sexpr:expr) => {
+        if let Err(message) = $crate::testing::check_tree(&$rule, $input, Some(&$filter), $sexpr) {
+            panic!("assertion failed: tree\n{}", message)
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grammar;
+
+    const LIST: &str = "
+        List <- '[' ^ Space (Item (',' Space Item)*)? ']' Space ;
+        Item <- Digit / List ;
+        Digit <- '0' / '1' / '2' ;
+        Space <- ' '* ;
+    ";
+
+    #[test]
+    fn assertions() {
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        let tree = assert_parses!(grammar.start, "[1, [2]]");
+        assert_eq!(tree.content, "[1, [2]]");
+        assert_fails_at!(grammar.start, "[1, x]", 2);
+        // The list ends before the second bracket.
+        assert_fails_at!(grammar.start, "[]]", 2);
+
+        let digits = Filter::new_with_list(false, vec![grammar.get("Digit").unwrap().id]);
+        assert_tree!(
+            grammar.start,
+            "[0,1]",
+            digits,
+            r#"(Root (Digit "0") (Digit "1"))"#
+        );
+        assert_tree!(
+            grammar.get("Item").unwrap(),
+            "2",
+            r#"(Item
+                 (Sor (Digit (Sor (Char "2")))))"#
+        );
+    }
+
+    #[test]
+    fn failure_messages() {
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        assert_eq!(
+            check_parses(&grammar.start, "[]]").err().unwrap(),
+            "List only matched 2 of 3 bytes, the rest is \"]\""
+        );
+        assert_eq!(
+            check_fails_at(&grammar.start, "[1]", 0).err().unwrap(),
+            "List parsed \"[1]\""
+        );
+        assert!(check_fails_at(&grammar.start, "[1, x]", 4)
+            .err()
+            .unwrap()
+            .starts_with("expected a failure at offset 4, got one at 2:\nerror: expected"));
+        assert_eq!(
+            check_tree(&grammar.start, "[]", None, "(List)").err().unwrap(),
+            "trees differ\n  expected: (List)\n    actual: (List (Seq (Char \"[\") (Cut \"\") (Space (Star \"\")) (Opt \"\") (Char \"]\") (Space (Star \"\"))))"
+        );
+        assert_eq!(
+            normalize("( A\n  (B \"x  y\\\"\" ) )"),
+            "(A (B \"x  y\\\"\"))"
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut round_trip = RoundTrip::new(GenerateOptions::new());
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        assert_eq!(round_trip.run(&grammar.start), Ok(100));
+    }
+
+    #[test]
+    fn round_trip_with_filter() {
+        let mut without_spaces = RoundTrip::new(GenerateOptions::new());
+        let mut without_chars = RoundTrip::new(GenerateOptions::new());
+        let grammar = Grammar::from_peg(LIST).unwrap();
+        // Spaces are left out when unparsing, but also hidden when comparing.
+        let space = grammar.get("Space").unwrap().id;
+        without_spaces.filter = Some(Filter::new_with_list(true, vec![space]));
+        assert_eq!(without_spaces.run(&grammar.start), Ok(100));
+
+        // Hiding the brackets and commas loses them.
+        without_chars.filter = Some(Filter::new_with_list(true, vec![*crate::rule::CHAR_ID]));
+        let error = without_chars.run(&grammar.start).err().unwrap();
+        assert!(error.contains("which failed"), "{}", error);
+    }
+
+    #[test]
+    fn round_trip_fails_on_shadowed_alternatives() {
+        let mut round_trip = RoundTrip::new(GenerateOptions::new());
+        // '<' matches the start of "<=", which is never parsed.
+        let grammar = Grammar::from_peg("Op <- '<' / \"<=\" ;").unwrap();
+        let error = round_trip.run(&grammar.start).err().unwrap();
+        assert!(
+            error.starts_with(
+                "alternative 2 `\"<=\"` of `'<' / \"<=\"` never matched, the rule rejected"
+            ),
+            "{}",
+            error
+        );
+
+        // Both alternatives parse "a", but it always matches the first one.
+        let mut round_trip = RoundTrip::new(GenerateOptions::new());
+        let grammar = Grammar::from_peg("Word <- 'a' / 'a' ;").unwrap();
+        let error = round_trip.run(&grammar.start).err().unwrap();
+        assert!(
+            error.ends_with("the rule rejected 0 of 56 walks through it"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn round_trip_fails_on_sentences_taken_by_repetitions() {
+        let mut round_trip = RoundTrip::new(GenerateOptions::new());
+        // Walks of the first alternative are never parsed, 'a'* takes the last 'a' too.
+        let grammar = Grammar::from_peg("Word <- 'a'* 'a' / 'b'+ ;").unwrap();
+        let error = round_trip.run(&grammar.start).err().unwrap();
+        assert!(error.starts_with("alternative 1 `'a'* 'a'`"), "{}", error);
+    }
+}
@@ -0,0 +1,391 @@
+use std::cell::RefCell;
+use std::fmt::Write;
+use std::rc::Rc;
+
+use crate::render::{json_string, preview};
+use crate::rule::Rule;
+use crate::rule_handler::{Handler, Listener};
+use crate::{Failure, Node};
+
+/// How a rule attempt ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    /// The rule was still being parsed when the trace was taken.
+    Pending,
+    Success,
+    Failure,
+    /// The rule failed after passing a cut.
+    Cut,
+    /// A `Sor` did not try the rule, as it cannot start with the next character.
+    Skipped,
+}
+
+/// A single application of a rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attempt {
+    pub id: usize,
+    pub name: String,
+    /// Byte offset in the input at which the rule was applied.
+    pub offset: usize,
+    /// Number of enclosing attempts.
+    pub depth: usize,
+    pub outcome: Outcome,
+    /// Number of bytes matched, zero unless the attempt succeeded.
+    pub consumed: usize,
+}
+
+/// Every rule attempt of a parse, in the order they were started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trace {
+    pub input: String,
+    pub attempts: Vec<Attempt>,
+}
+
+/// A `Listener` recording a `Trace`.
+pub struct Tracer {
+    input_len: usize,
+    attempts: RefCell<Vec<Attempt>>,
+    stack: RefCell<Vec<usize>>,
+}
+
+impl Tracer {
+    /// Creates a tracer for parsing `input`, which has to be the whole input
+    /// passed to the rule, as offsets are computed relative to it.
+    pub fn new(input: &str) -> Tracer {
+        Tracer {
+            input_len: input.len(),
+            attempts: RefCell::new(Vec::new()),
+            stack: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the attempts recorded so far.
+    pub fn trace(&self, input: &str) -> Trace {
+        Trace {
+            input: input.to_string(),
+            attempts: self.attempts.borrow().clone(),
+        }
+    }
+
+    fn finish(&self, outcome: Outcome, consumed: usize) {
+        if let Some(index) = self.stack.borrow_mut().pop() {
+            let attempt = &mut self.attempts.borrow_mut()[index];
+            attempt.outcome = outcome;
+            attempt.consumed = consumed;
+        }
+    }
+}
+
+impl<'a> Listener<'a> for Tracer {
+    fn pre_parse(&self, rule: &Rule<'a>, input: &'a str) {
+        let mut stack = self.stack.borrow_mut();
+        let mut attempts = self.attempts.borrow_mut();
+        stack.push(attempts.len());
+        attempts.push(Attempt {
+            id: rule.id,
+            name: rule.name.to_string(),
+            offset: self.input_len - input.len(),
+            depth: stack.len() - 1,
+            outcome: Outcome::Pending,
+            consumed: 0,
+        });
+    }
+
+    fn success(&self, _rule: &Rule<'a>, _input: &'a str, node: &Node<'a>) {
+        self.finish(Outcome::Success, node.content.len());
+    }
+
+    fn failure(&self, _rule: &Rule<'a>, _input: &'a str, failure: &Failure<'a>) {
+        match failure {
+            Failure::Backtrack => self.finish(Outcome::Failure, 0),
+            Failure::Cut { .. } => self.finish(Outcome::Cut, 0),
+        }
+    }
+
+    fn skipped(&self, options: &[Rule<'a>], input: &'a str) {
+        let depth = self.stack.borrow().len();
+        let mut attempts = self.attempts.borrow_mut();
+        for option in options {
+            attempts.push(Attempt {
+                id: option.id,
+                name: option.name.to_string(),
+                offset: self.input_len - input.len(),
+                depth,
+                outcome: Outcome::Skipped,
+                consumed: 0,
+            });
+        }
+    }
+}
+
+impl Trace {
+    /// Parses the input with the rule, recording every rule attempt.
+    pub fn record<'a>(rule: &Rule<'a>, input: &'a str) -> (Option<Node<'a>>, Trace) {
+        let tracer = Rc::new(Tracer::new(input));
+        let mut handler = Handler::new();
+        handler.add_listener(tracer.clone());
+        let node = rule.parse_with_handler(input, &handler);
+        (node, tracer.trace(input))
+    }
+
+    /// Renders the trace as one line per attempt, indented by depth.
+    pub fn to_text(&self) -> String {
+        let mut result = String::new();
+        for attempt in &self.attempts {
+            let indent = "  ".repeat(attempt.depth);
+            let _ = match attempt.outcome {
+                Outcome::Success => writeln!(
+                    result,
+                    "{}{} at {}: matched {} {}",
+                    indent,
+                    attempt.name,
+                    attempt.offset,
+                    attempt.consumed,
+                    preview(
+                        &self.input[attempt.offset..attempt.offset + attempt.consumed],
+                        20
+                    )
+                ),
+                Outcome::Failure => {
+                    writeln!(
+                        result,
+                        "{}{} at {}: failed",
+                        indent, attempt.name, attempt.offset
+                    )
+                }
+                Outcome::Cut => writeln!(
+                    result,
+                    "{}{} at {}: failed after cut",
+                    indent, attempt.name, attempt.offset
+                ),
+                Outcome::Pending => writeln!(
+                    result,
+                    "{}{} at {}: unfinished",
+                    indent, attempt.name, attempt.offset
+                ),
+                Outcome::Skipped => writeln!(
+                    result,
+                    "{}{} at {}: skipped",
+                    indent, attempt.name, attempt.offset
+                ),
+            };
+        }
+        result
+    }
+
+    /// Renders the trace as a standalone HTML page that steps through the
+    /// attempts and highlights the part of the input each of them covers.
+    pub fn to_html(&self) -> String {
+        // Offsets in the page are in UTF-16 code units, like JavaScript strings
+        let mut utf16 = vec![0; self.input.len() + 1];
+        let mut units = 0;
+        for (offset, c) in self.input.char_indices() {
+            utf16[offset] = units;
+            units += c.len_utf16();
+        }
+        utf16[self.input.len()] = units;
+
+        let mut attempts = String::from("[");
+        for (i, attempt) in self.attempts.iter().enumerate() {
+            if i > 0 {
+                attempts += ",";
+            }
+            let outcome = match attempt.outcome {
+                Outcome::Pending => "pending",
+                Outcome::Success => "success",
+                Outcome::Failure => "failure",
+                Outcome::Cut => "cut",
+                Outcome::Skipped => "skipped",
+            };
+            let _ = write!(
+                attempts,
+                "[{},{},{},{},\"{}\"]",
+                json_string(&attempt.name),
+                utf16[attempt.offset],
+                utf16[attempt.offset + attempt.consumed],
+                attempt.depth,
+                outcome
+            );
+        }
+        attempts += "]";
+
+        HTML_TEMPLATE
+            .replace("/*INPUT*/", &json_string(&self.input))
+            .replace("/*ATTEMPTS*/", &attempts)
+    }
+}
+
+const HTML_TEMPLATE: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Parse trace</title>
+<style>
+body { font-family: sans-serif; margin: 0; display: flex; flex-direction: column; height: 100vh; }
+header { padding: 8px; border-bottom: 1px solid #ccc; }
+main { display: flex; flex: 1; min-height: 0; }
+#attempts { flex: 1; overflow: auto; margin: 0; padding: 8px; font-family: monospace; list-style: none; }
+#attempts li { white-space: pre; cursor: pointer; }
+#attempts li.current { background: #ffe08a; }
+#input { flex: 1; overflow: auto; margin: 0; padding: 8px; border-left: 1px solid #ccc; white-space: pre-wrap; }
+.success { color: #1a7f37; }
+.failure { color: #cf222e; }
+.cut { color: #cf222e; font-weight: bold; }
+.pending, .skipped { color: #888; }
+mark.success { background: #aceebb; color: inherit; }
+mark.failure, mark.cut, mark.pending, mark.skipped { background: #ffcecb; color: inherit; }
+</style>
+</head>
+<body>
+<header>
+<button id="first">&#x23EE;</button>
+<button id="prev">&#x25C0;</button>
+<button id="next">&#x25B6;</button>
+<button id="last">&#x23ED;</button>
+<span id="position"></span>
+</header>
+<main>
+<ol id="attempts"></ol>
+<pre id="input"></pre>
+</main>
+<script>
+const input = /*INPUT*/;
+const attempts = /*ATTEMPTS*/;
+const list = document.getElementById("attempts");
+const text = document.getElementById("input");
+const position = document.getElementById("position");
+let current = 0;
+
+function escape(s) {
+  return s.replace(/&/g, "&amp;").replace(/</g, "&lt;").replace(/>/g, "&gt;");
+}
+
+attempts.forEach(([name, start, end, depth, outcome], i) => {
+  const item = document.createElement("li");
+  item.textContent = "  ".repeat(depth) + name + " at " + start + (outcome === "success" ? ": " + JSON.stringify(input.slice(start, end)) : ": " + outcome);
+  item.className = outcome;
+  item.onclick = () => show(i);
+  list.appendChild(item);
+});
+
+function show(i) {
+  if (attempts.length === 0) return;
+  current = Math.max(0, Math.min(attempts.length - 1, i));
+  const [name, start, end, depth, outcome] = attempts[current];
+  const marked = outcome === "success" ? input.slice(start, end) : input.slice(start, start + 1) || " ";
+  const rest = outcome === "success" ? input.slice(end) : input.slice(start + 1);
+  text.innerHTML = escape(input.slice(0, start)) + '<mark class="' + outcome + '">' + escape(marked) + "</mark>" + escape(rest);
+  list.querySelectorAll(".current").forEach(item => item.classList.remove("current"));
+  list.children[current].classList.add("current");
+  list.children[current].scrollIntoView({ block: "nearest" });
+  position.textContent = (current + 1) + " / " + attempts.length + ": " + name + " (" + outcome + ")";
+}
+
+document.getElementById("first").onclick = () => show(0);
+document.getElementById("prev").onclick = () => show(current - 1);
+document.getElementById("next").onclick = () => show(current + 1);
+document.getElementById("last").onclick = () => show(attempts.length - 1);
+document.onkeydown = event => {
+  if (event.key === "ArrowDown" || event.key === "ArrowRight") show(current + 1);
+  if (event.key === "ArrowUp" || event.key === "ArrowLeft") show(current - 1);
+};
+show(0);
+</script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use trace::{Outcome, Trace};
+
+    #[test]
+    fn trace_records_every_attempt() {
+        let rule = sor!(seq!(char!('a'), char!('c')), seq!(char!('a'), char!('b')));
+        let (node, trace) = Trace::record(&rule, "ab");
+        assert_eq!(node.unwrap().content, "ab");
+        let summary: Vec<_> = trace
+            .attempts
+            .iter()
+            .map(|a| (a.name.as_str(), a.offset, a.depth, a.outcome, a.consumed))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Sor", 0, 0, Outcome::Success, 2),
+                ("Seq", 0, 1, Outcome::Failure, 0),
+                ("Char", 0, 2, Outcome::Success, 1),
+                ("Char", 1, 2, Outcome::Failure, 0),
+                ("Seq", 0, 1, Outcome::Success, 2),
+                ("Char", 0, 2, Outcome::Success, 1),
+                ("Char", 1, 2, Outcome::Success, 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn trace_shows_skipped_options() {
+        let rule = sor!(
+            char!('a'),
+            custom!("B" => char!('b')),
+            char!('c'),
+            char!('d')
+        );
+        let (_, trace) = Trace::record(&rule, "c");
+        let summary: Vec<_> = trace
+            .attempts
+            .iter()
+            .map(|a| (a.name.as_str(), a.offset, a.depth, a.outcome))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Sor", 0, 0, Outcome::Success),
+                ("Char", 0, 1, Outcome::Skipped),
+                ("B", 0, 1, Outcome::Skipped),
+                ("Char", 0, 1, Outcome::Success),
+            ]
+        );
+        let (_, trace) = Trace::record(&rule, "x");
+        assert_eq!(trace.attempts.len(), 5);
+        assert!(trace.to_text().ends_with("  Char at 0: skipped\n"));
+    }
+
+    #[test]
+    fn trace_records_cut_failures() {
+        let rule = custom!("Call" => seq!(str!("f"), cut!(), char!('(')));
+        let (node, trace) = Trace::record(&rule, "fx");
+        assert!(node.is_none());
+        assert_eq!(trace.attempts[0].name, "Call");
+        assert_eq!(trace.attempts[0].outcome, Outcome::Cut);
+        assert_eq!(trace.attempts[4].name, "Char");
+        assert_eq!(trace.attempts[4].offset, 1);
+        assert_eq!(trace.attempts[4].outcome, Outcome::Failure);
+    }
+
+    #[test]
+    fn trace_to_text() {
+        let rule = custom!("Word" => plus!(char!('a')));
+        let (_, trace) = Trace::record(&rule, "aab");
+        assert_eq!(
+            trace.to_text(),
+            "Word at 0: matched 2 \"aa\"\n\
+            \x20 Plus at 0: matched 2 \"aa\"\n\
+            \x20   Char at 0: matched 1 \"a\"\n\
+            \x20   Char at 1: matched 1 \"a\"\n\
+            \x20   Char at 2: failed\n"
+        );
+    }
+
+    #[test]
+    fn trace_to_html() {
+        let rule = seq!(str!("é<"), char!('"'));
+        let (_, trace) = Trace::record(&rule, "é<\"");
+        let html = trace.to_html();
+        assert!(html.contains("const input = \"é\\u003c\\\"\";"));
+        assert!(html.contains(
+            "const attempts = [[\"Seq\",0,3,0,\"success\"],[\"Str\",0,2,1,\"success\"],[\"Char\",2,3,1,\"success\"]];"
+        ));
+    }
+}
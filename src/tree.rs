@@ -0,0 +1,250 @@
+//! Parse trees kept in a single vector, with nodes referring to their children by index
+//! instead of owning them, so a whole tree takes one allocation instead of one per node.
+//!
+//! Nodes are stored in pre-order: the children of a node follow it, each followed by its
+//! own descendants, and every node records where its subtree ends.
+//!
+//! `Rule::parse_tree` and `Program::run_tree` parse straight into a tree.
+//!
+//! ```
+//! use yaast::vm::Program;
+//! use yaast::Grammar;
+//!
+//! let grammar = Grammar::from_peg("List <- '[' Item* ']' ; Item <- 'x' ;").unwrap();
+//! let program = Program::compile(&grammar.start).unwrap();
+//! let tree = program.run_tree("[xx]").unwrap();
+//! let list = tree.root();
+//! assert_eq!(tree[list].type_name, "List");
+//! let seq = tree.children(list).next().unwrap();
+//! assert_eq!(tree.children(seq).count(), 3);
+//! ```
+
+use std::cell::RefCell;
+use std::ops::{Index, IndexMut};
+use std::rc::Rc;
+
+use crate::events::Event;
+use crate::rule::Rule;
+use crate::{Name, Node, ParseError};
+
+/// Index of a node in its `Tree`.
+pub type NodeId = usize;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode<'a> {
+    pub type_id: usize,
+    pub type_name: Name,
+    pub content: &'a str,
+    /// Index past the last node of the subtree.
+    end: NodeId,
+}
+
+/// A parse tree in one vector, see the module documentation.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tree<'a> {
+    nodes: Vec<TreeNode<'a>>,
+    /// Nodes opened but not closed yet, innermost last.
+    open: Vec<NodeId>,
+}
+
+impl<'a> Tree<'a> {
+    pub fn new() -> Tree<'a> {
+        Tree::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Tree<'a> {
+        Tree {
+            nodes: Vec::with_capacity(capacity),
+            open: Vec::new(),
+        }
+    }
+
+    /// Adds a node as the last child of the innermost open node, or as the root.
+    /// The nodes added until it is closed become its children.
+    pub fn open(&mut self, type_id: usize, type_name: impl Into<Name>, content: &'a str) -> NodeId {
+        self.nodes.push(TreeNode {
+            type_id,
+            type_name: type_name.into(),
+            content,
+            end: NodeId::MAX,
+        });
+        let id = self.nodes.len() - 1;
+        self.open.push(id);
+        id
+    }
+
+    /// Closes the innermost open node.
+    pub fn close(&mut self) -> NodeId {
+        let id = self.open.pop().expect("close without open");
+        self.nodes[id].end = self.nodes.len();
+        id
+    }
+
+    /// Adds a node without children.
+    pub fn leaf(&mut self, type_id: usize, type_name: impl Into<Name>, content: &'a str) -> NodeId {
+        self.open(type_id, type_name, content);
+        self.close()
+    }
+
+    /// The first node added, which holds all the others once every node is closed.
+    pub fn root(&self) -> NodeId {
+        0
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The children of a node, in order. A node that is still open has none yet.
+    pub fn children(&self, id: NodeId) -> Children<'_, 'a> {
+        let end = match self.nodes[id].end {
+            NodeId::MAX => id + 1,
+            end => end,
+        };
+        Children {
+            tree: self,
+            next: id + 1,
+            end,
+        }
+    }
+
+    /// Copies the subtree of the node into a `Node`.
+    pub fn to_node(&self, id: NodeId) -> Node<'a> {
+        let node = &self.nodes[id];
+        let mut result = Node::new(node.content, node.type_id, &node.type_name);
+        result.children = self.children(id).map(|child| self.to_node(child)).collect();
+        result
+    }
+
+    fn add(&mut self, node: &Node<'a>) {
+        self.open(node.type_id, &node.type_name, node.content);
+        for child in &node.children {
+            self.add(child);
+        }
+        self.close();
+    }
+}
+
+impl<'a> Rule<'a> {
+    /// Parses the input into a tree in one vector. The nodes of the rules are passed on as
+    /// events (see `parse_events`) instead of being collected by their parents.
+    pub fn parse_tree(&self, input: &'a str) -> Result<Tree<'a>, ParseError> {
+        let tree = Rc::new(RefCell::new(Tree::new()));
+        let sink = tree.clone();
+        self.parse_events(input, move |event| {
+            let mut tree = sink.borrow_mut();
+            match event {
+                Event::Enter { id, name, .. } => {
+                    tree.open(id, name, "");
+                }
+                Event::Exit { span, .. } => {
+                    let node = tree.close();
+                    tree[node].content = &input[span];
+                }
+                Event::Token { id, name, span } => {
+                    tree.leaf(id, name, &input[span]);
+                }
+            }
+        })?;
+        let tree = Rc::try_unwrap(tree).expect("the parse dropped the consumer");
+        Ok(tree.into_inner())
+    }
+}
+
+impl<'a> From<&Node<'a>> for Tree<'a> {
+    fn from(node: &Node<'a>) -> Tree<'a> {
+        let mut tree = Tree::new();
+        tree.add(node);
+        tree
+    }
+}
+
+impl<'a> Index<NodeId> for Tree<'a> {
+    type Output = TreeNode<'a>;
+
+    fn index(&self, id: NodeId) -> &TreeNode<'a> {
+        &self.nodes[id]
+    }
+}
+
+impl<'a> IndexMut<NodeId> for Tree<'a> {
+    fn index_mut(&mut self, id: NodeId) -> &mut TreeNode<'a> {
+        &mut self.nodes[id]
+    }
+}
+
+/// Iterator over the children of a node, returned by `Tree::children`.
+pub struct Children<'t, 'a> {
+    tree: &'t Tree<'a>,
+    next: NodeId,
+    end: NodeId,
+}
+
+impl Iterator for Children<'_, '_> {
+    type Item = NodeId;
+
+    fn next(&mut self) -> Option<NodeId> {
+        if self.next >= self.end {
+            return None;
+        }
+        let child = self.next;
+        self.next = self.tree.nodes[child].end;
+        Some(child)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Grammar;
+
+    #[test]
+    fn build_and_walk() {
+        let mut tree = Tree::new();
+        let root = tree.open(0, "Pair", "a=1");
+        assert_eq!(tree.children(root).count(), 0);
+        tree.leaf(1, "Name", "a");
+        tree.open(2, "Value", "=1");
+        let equals = tree.leaf(3, "Char", "=");
+        tree.leaf(4, "Digit", "1");
+        tree.close();
+        tree.open(5, "Empty", "");
+        tree.close();
+        assert_eq!(tree.close(), root);
+
+        let children: Vec<_> = tree
+            .children(root)
+            .map(|child| tree[child].type_name.as_str())
+            .collect();
+        assert_eq!(children, ["Name", "Value", "Empty"]);
+        assert_eq!(tree.children(equals).count(), 0);
+        assert_eq!(tree.len(), 6);
+        let value = tree.children(root).nth(1).unwrap();
+        assert_eq!(tree.children(value).collect::<Vec<_>>(), [3, 4]);
+    }
+
+    #[test]
+    fn same_as_the_node() {
+        let grammar = Grammar::from_peg("List <- '[' (Item ','?)* ']' ; Item <- 'x' ;").unwrap();
+        let node = grammar.start.parse("[x,x]").unwrap();
+        let tree = Tree::from(&node);
+        assert_eq!(tree.to_node(tree.root()).to_sexpr(), node.to_sexpr());
+        assert_eq!(tree[tree.root()].content, "[x,x]");
+    }
+
+    #[test]
+    fn parse_into_a_tree() {
+        let grammar =
+            Grammar::from_peg("List <- '[' (Item ','?)* ']' ; Item <- 'x' / 'y' ;").unwrap();
+        for input in ["[]", "[x,y]", "[xy,x]"] {
+            let node = grammar.start.parse(input).unwrap();
+            assert_eq!(grammar.start.parse_tree(input), Ok(Tree::from(&node)));
+        }
+        let error = grammar.start.parse_tree("[x,z]").unwrap_err();
+        assert_eq!(error.to_string(), "expected Item at offset 3");
+    }
+}
@@ -0,0 +1,998 @@
+//! A parsing machine in the style of LPeg: a grammar is compiled once into a flat list of
+//! instructions, which are then run against inputs with an explicit backtrack stack.
+//!
+//! Named rules become subroutines, everything else is inlined into them. Matches are
+//! recorded as a list of captures that is cut back when backtracking, and the tree is only
+//! built once the whole parse succeeded, so failed alternatives allocate nothing. The tree
+//! and errors are the same as those of the rule the program was compiled from. `run_tree`
+//! builds the same tree into a single `Tree` instead of a `Node` per match.
+//!
+//! `recognize_input` and `run_events` read their input as they go, from an `Input` that
+//! does not have to hold all of it (see `input`). `run_events` passes the tree on as
+//! events, like `Rule::parse_events`, as soon as no choice left can undo them. Only
+//! programs read from an `Input`: rules parse a `&str`, so a grammar that does not compile,
+//! like one with `recover!` or opaque rules, needs its whole input in memory.
+//!
+//! ```
+//! use yaast::vm::Program;
+//! use yaast::Grammar;
+//!
+//! let grammar = Grammar::from_peg("List <- '[' Item* ']' ; Item <- 'x' ;").unwrap();
+//! let program = Program::compile(&grammar.start).unwrap();
+//! let tree = program.run("[xx]").unwrap();
+//! assert_eq!(tree.type_name, "List");
+//! assert_eq!(program.run("[x").unwrap_err().to_string(), "expected Item at offset 2");
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::events::Event;
+use crate::input::{Input, InputError, ReadError};
+use crate::rule::{Literals, Rule, RuleKind, CHAR_ID, CUT_ID, STR_ID};
+use crate::tree::Tree;
+use crate::{Failure, Furthest, Name, Node, ParseError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompileError {
+    /// A rule that does not describe itself, so there is nothing to compile it from.
+    Opaque(String),
+    Uninitialized(String),
+    /// A rule the machine has no instructions for.
+    Unsupported(String),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompileError::Opaque(name) => {
+                write!(
+                    f,
+                    "rule {} does not describe itself, cannot compile it",
+                    name
+                )
+            }
+            CompileError::Uninitialized(name) => write!(f, "rule {} was never initialized", name),
+            CompileError::Unsupported(name) => write!(f, "rule {} cannot be compiled", name),
+        }
+    }
+}
+
+impl std::error::Error for CompileError {}
+
+/// Index into `Program::kinds`, the id and name of the nodes an instruction makes.
+type Kind = usize;
+
+#[derive(Debug, Clone)]
+enum Instruction {
+    /// Matches the character, making a node for it.
+    Char(char, Kind),
+    Str(Box<str>, Kind),
+    /// Matches one of the characters of the set, making the node of a `Sor` with the node of
+    /// the `Char` that matched below it.
+    Set(Box<[char]>, Kind, Kind),
+    /// Matches one of the literals, making the node of the rule with a `Str` node below it,
+    /// of the second kind.
+    Literals(Box<Literals>, Kind, Kind),
+    /// Matches the end of the input, making an empty node.
+    Eof(Kind),
+    /// Makes an empty node, of an `Opt` that did not match.
+    Empty(Kind),
+    /// Makes a node without content at the current position, of a cut.
+    Mark(Kind),
+    /// Starts a node, ended by the next `Close` that is not part of a nested node.
+    Open(Kind),
+    Close,
+    /// Continues with the next instruction, but when it fails backtracks to the label.
+    Choice(usize),
+    /// Drops the choice on top of the stack and jumps to the label.
+    Commit(usize),
+    /// Moves the choice on top of the stack to the current position and jumps to the label,
+    /// for loops.
+    PartialCommit(usize),
+    /// Calls the subroutine at the label, which makes a node of the kind.
+    Call(usize, Kind),
+    Return,
+    /// Backtracks to the last choice.
+    Fail,
+    /// Fails the whole parse, expecting a node of the kind at the current position.
+    Abort(Kind),
+    End,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Char(c, _) => write!(f, "char {:?}", c),
+            Instruction::Str(s, _) => write!(f, "str {:?}", s),
+            Instruction::Set(chars, _, _) => {
+                write!(f, "set {:?}", chars.iter().collect::<String>())
+            }
+            Instruction::Literals(literals, _, _) => {
+                write!(f, "literals {:?}", literals.literals)
+            }
+            Instruction::Eof(_) => write!(f, "eof"),
+            Instruction::Empty(kind) => write!(f, "empty {}", kind),
+            Instruction::Mark(kind) => write!(f, "mark {}", kind),
+            Instruction::Open(kind) => write!(f, "open {}", kind),
+            Instruction::Close => write!(f, "close"),
+            Instruction::Choice(label) => write!(f, "choice {}", label),
+            Instruction::Commit(label) => write!(f, "commit {}", label),
+            Instruction::PartialCommit(label) => write!(f, "partial commit {}", label),
+            Instruction::Call(label, _) => write!(f, "call {}", label),
+            Instruction::Return => write!(f, "return"),
+            Instruction::Fail => write!(f, "fail"),
+            Instruction::Abort(kind) => write!(f, "abort {}", kind),
+            Instruction::End => write!(f, "end"),
+        }
+    }
+}
+
+/// What the machine recorded while matching, turned into nodes once it is done.
+#[derive(Clone, Copy)]
+enum Capture {
+    Open(Kind, usize),
+    Close(usize),
+    Leaf(Kind, usize, usize),
+    Empty(Kind, usize),
+}
+
+impl Capture {
+    fn start(&self) -> usize {
+        match *self {
+            Capture::Open(_, start)
+            | Capture::Close(start)
+            | Capture::Leaf(_, start, _)
+            | Capture::Empty(_, start) => start,
+        }
+    }
+}
+
+/// The captures made so far, or just their count when no tree is built.
+struct Captures {
+    list: Vec<Capture>,
+    record: bool,
+    /// Captures already passed on as events and dropped from the list.
+    passed: usize,
+}
+
+impl Captures {
+    fn new(record: bool) -> Captures {
+        Captures {
+            list: Vec::new(),
+            record,
+            passed: 0,
+        }
+    }
+
+    fn push(&mut self, capture: Capture) {
+        if self.record {
+            self.list.push(capture);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.passed + self.list.len()
+    }
+
+    fn truncate(&mut self, len: usize) {
+        self.list.truncate(len - self.passed);
+    }
+}
+
+/// Where `run_events` passes the captures on to.
+struct Events<'c> {
+    consumer: &'c mut dyn FnMut(Event, &str),
+    /// Kinds and starts of the nodes entered and not exited yet.
+    open: Vec<(Kind, usize)>,
+}
+
+/// How much further the machine gets before it passes on events and releases the input.
+const CHECKPOINT: usize = 4096;
+
+enum Frame {
+    Choice {
+        label: usize,
+        position: usize,
+        captures: usize,
+    },
+    /// Where to continue after a subroutine, which started at `start`.
+    Return {
+        next: usize,
+        start: usize,
+        kind: Kind,
+    },
+}
+
+/// A compiled rule, see the module documentation.
+pub struct Program {
+    code: Vec<Instruction>,
+    /// Ids and names of the nodes the program makes.
+    kinds: Vec<(usize, Name)>,
+    /// Name of the rule the program was compiled from, for errors.
+    name: Name,
+}
+
+/// Compiles the rules into a program, numbering kinds and placing subroutines.
+struct Compiler<'a> {
+    code: Vec<Instruction>,
+    kinds: Vec<(usize, Name)>,
+    kind_numbers: HashMap<(usize, Name), Kind>,
+    /// Start of the subroutine of each named rule, by instance id.
+    subroutines: HashMap<usize, usize>,
+    /// Named rules whose subroutines still have to be written.
+    pending: Vec<Rule<'a>>,
+    /// Calls whose subroutine was not placed yet, by instance id of the rule.
+    calls: Vec<(usize, usize)>,
+}
+
+impl<'a> Compiler<'a> {
+    fn kind(&mut self, rule: &Rule<'a>) -> Kind {
+        self.kind_of(rule.id, &rule.name)
+    }
+
+    fn kind_of(&mut self, id: usize, name: &Name) -> Kind {
+        let key = (id, name.clone());
+        if let Some(kind) = self.kind_numbers.get(&key) {
+            return *kind;
+        }
+        self.kinds.push(key.clone());
+        self.kind_numbers.insert(key, self.kinds.len() - 1);
+        self.kinds.len() - 1
+    }
+
+    fn emit(&mut self, instruction: Instruction) -> usize {
+        self.code.push(instruction);
+        self.code.len() - 1
+    }
+
+    /// Points the jump at `at` to the next instruction.
+    fn patch(&mut self, at: usize) {
+        let here = self.code.len();
+        match &mut self.code[at] {
+            Instruction::Choice(label)
+            | Instruction::Commit(label)
+            | Instruction::PartialCommit(label) => *label = here,
+            _ => unreachable!("only jumps are patched"),
+        }
+    }
+
+    /// The characters of a choice between single characters, which is matched with a set.
+    fn set(rules: &[Rule<'a>]) -> Option<(Vec<char>, Rule<'a>)> {
+        let first = rules.first()?;
+        let mut chars = Vec::new();
+        for rule in rules {
+            match rule.kind()? {
+                RuleKind::Char(c) if rule.id == *CHAR_ID && rule.name == first.name => {
+                    chars.push(c)
+                }
+                _ => return None,
+            }
+        }
+        Some((chars, first.clone()))
+    }
+
+    /// Matches the rule, failing or aborting like `apply` would.
+    fn rule(&mut self, rule: &Rule<'a>) -> Result<(), CompileError> {
+        let kind = match rule.kind() {
+            Some(kind) => kind,
+            None => return Err(CompileError::Uninitialized(rule.name.to_string())),
+        };
+        let this = self.kind(rule);
+        match kind {
+            RuleKind::Char(c) => {
+                self.emit(Instruction::Char(c, this));
+            }
+            RuleKind::Str(s) => {
+                self.emit(Instruction::Str(s.into(), this));
+            }
+            RuleKind::Literals(literals) => {
+                let child = self.kind_of(*STR_ID, &Name::Static("Str"));
+                self.emit(Instruction::Literals(
+                    Box::new(literals.clone()),
+                    this,
+                    child,
+                ));
+            }
+            RuleKind::Eof => {
+                self.emit(Instruction::Eof(this));
+            }
+            RuleKind::Cut => {
+                self.emit(Instruction::Mark(this));
+            }
+            RuleKind::Seq(rules) => {
+                self.emit(Instruction::Open(this));
+                let mut committed = false;
+                for child in rules {
+                    if committed {
+                        self.committed(child)?;
+                    } else {
+                        self.rule(child)?;
+                    }
+                    committed |= child.id == *CUT_ID;
+                }
+                self.emit(Instruction::Close);
+            }
+            RuleKind::Sor([]) => {
+                self.emit(Instruction::Fail);
+            }
+            RuleKind::Sor(rules) => {
+                if let Some((chars, char_rule)) = Compiler::set(rules) {
+                    let char_kind = self.kind(&char_rule);
+                    self.emit(Instruction::Set(chars.into(), this, char_kind));
+                    return Ok(());
+                }
+                self.emit(Instruction::Open(this));
+                let mut commits = Vec::new();
+                for option in &rules[..rules.len() - 1] {
+                    let choice = self.emit(Instruction::Choice(0));
+                    self.rule(option)?;
+                    commits.push(self.emit(Instruction::Commit(0)));
+                    self.patch(choice);
+                }
+                self.rule(&rules[rules.len() - 1])?;
+                for commit in commits {
+                    self.patch(commit);
+                }
+                self.emit(Instruction::Close);
+            }
+            RuleKind::Star(child) => {
+                self.emit(Instruction::Open(this));
+                self.repeat(child)?;
+                self.emit(Instruction::Close);
+            }
+            RuleKind::Plus(child) => {
+                self.emit(Instruction::Open(this));
+                self.rule(child)?;
+                self.repeat(child)?;
+                self.emit(Instruction::Close);
+            }
+            RuleKind::Opt(child) => {
+                let choice = self.emit(Instruction::Choice(0));
+                self.rule(child)?;
+                let commit = self.emit(Instruction::Commit(0));
+                self.patch(choice);
+                self.emit(Instruction::Empty(this));
+                self.patch(commit);
+            }
+            RuleKind::Must(child) => self.committed(child)?,
+            RuleKind::Custom(_) => {
+                let call = self.emit(Instruction::Call(0, this));
+                match self.subroutines.get(&rule.instance_id()) {
+                    Some(start) => self.code[call] = Instruction::Call(*start, this),
+                    None => {
+                        if !self.calls.iter().any(|(_, id)| *id == rule.instance_id()) {
+                            self.pending.push(rule.clone());
+                        }
+                        self.calls.push((call, rule.instance_id()));
+                    }
+                }
+            }
+            RuleKind::Recover(..) => return Err(CompileError::Unsupported(rule.name.to_string())),
+            RuleKind::Opaque => return Err(CompileError::Opaque(rule.name.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Matches the rule, aborting where it would have backtracked.
+    fn committed(&mut self, rule: &Rule<'a>) -> Result<(), CompileError> {
+        let kind = self.kind(rule);
+        let choice = self.emit(Instruction::Choice(0));
+        self.rule(rule)?;
+        let commit = self.emit(Instruction::Commit(0));
+        self.patch(choice);
+        self.emit(Instruction::Abort(kind));
+        self.patch(commit);
+        Ok(())
+    }
+
+    /// Matches the rule as often as possible.
+    fn repeat(&mut self, rule: &Rule<'a>) -> Result<(), CompileError> {
+        let choice = self.emit(Instruction::Choice(0));
+        let body = self.code.len();
+        self.rule(rule)?;
+        self.emit(Instruction::PartialCommit(body));
+        self.patch(choice);
+        Ok(())
+    }
+
+    /// Writes the subroutine of a named rule: its node around the rule it wraps.
+    fn subroutine(&mut self, rule: &Rule<'a>) -> Result<(), CompileError> {
+        let start = self.code.len();
+        self.subroutines.insert(rule.instance_id(), start);
+        let kind = self.kind(rule);
+        self.emit(Instruction::Open(kind));
+        if let Some(RuleKind::Custom(inner)) = rule.kind() {
+            self.rule(inner)?;
+        }
+        self.emit(Instruction::Close);
+        self.emit(Instruction::Return);
+        Ok(())
+    }
+}
+
+impl Program {
+    /// Compiles the rule and every rule it refers to. Rules with error recovery and rules
+    /// that do not describe themselves cannot be compiled.
+    pub fn compile(rule: &Rule) -> Result<Program, CompileError> {
+        let mut compiler = Compiler {
+            code: Vec::new(),
+            kinds: Vec::new(),
+            kind_numbers: HashMap::new(),
+            subroutines: HashMap::new(),
+            pending: Vec::new(),
+            calls: Vec::new(),
+        };
+        compiler.rule(rule)?;
+        compiler.emit(Instruction::End);
+        while let Some(rule) = compiler.pending.pop() {
+            compiler.subroutine(&rule)?;
+        }
+        for (call, id) in std::mem::take(&mut compiler.calls) {
+            if let Instruction::Call(label, _) = &mut compiler.code[call] {
+                *label = compiler.subroutines[&id];
+            }
+        }
+        Ok(Program {
+            code: compiler.code,
+            kinds: compiler.kinds,
+            name: rule.name.clone(),
+        })
+    }
+
+    /// Parses the start of the input, like `Rule::try_parse` with the rule the program was
+    /// compiled from.
+    pub fn run<'a>(&self, input: &'a str) -> Result<Node<'a>, ParseError> {
+        let mut captures = Captures::new(true);
+        self.execute(&mut { input }, &mut captures, None)
+            .map_err(parse_error)?;
+        Ok(self.build(input, &captures.list))
+    }
+
+    /// Like `run`, but builds the tree in one vector, see `Tree`.
+    pub fn run_tree<'a>(&self, input: &'a str) -> Result<Tree<'a>, ParseError> {
+        let mut captures = Captures::new(true);
+        self.execute(&mut { input }, &mut captures, None)
+            .map_err(parse_error)?;
+        Ok(self.build_tree(input, &captures.list))
+    }
+
+    /// Matches the start of the input without building the tree, returning the length of
+    /// the match.
+    pub fn recognize(&self, input: &str) -> Result<usize, ParseError> {
+        self.execute(&mut { input }, &mut Captures::new(false), None)
+            .map_err(parse_error)
+    }
+
+    /// Like `recognize`, reading the input as it goes.
+    pub fn recognize_input(&self, input: &mut impl Input) -> Result<usize, ReadError> {
+        self.execute(input, &mut Captures::new(false), None)
+    }
+
+    /// Parses the start of the input as it is read, passing the tree on as events with the
+    /// text of each token, and the empty string with the other events. The events passed on
+    /// before the parse fails are those of the nodes no choice could undo anymore.
+    pub fn run_events(
+        &self,
+        input: &mut impl Input,
+        mut consumer: impl FnMut(Event, &str),
+    ) -> Result<usize, ReadError> {
+        let mut captures = Captures::new(true);
+        let mut events = Events {
+            consumer: &mut consumer,
+            open: Vec::new(),
+        };
+        let end = self.execute(input, &mut captures, Some(&mut events))?;
+        let all = captures.len();
+        self.pass(input, &mut captures, all, &mut events)?;
+        Ok(end)
+    }
+
+    /// Runs the program, returning where it ended.
+    fn execute<I: Input + ?Sized>(
+        &self,
+        input: &mut I,
+        captures: &mut Captures,
+        mut events: Option<&mut Events>,
+    ) -> Result<usize, ReadError> {
+        let mut pc = 0;
+        let mut position = 0;
+        let mut stack: Vec<Frame> = Vec::new();
+        let mut checkpoint = CHECKPOINT;
+        // Where the parse got furthest before backtracking, for its error.
+        let mut furthest = None;
+        loop {
+            // The kind of the leaf the instruction did not match.
+            let mut failed = None;
+            let matched = match &self.code[pc] {
+                Instruction::Char(c, kind) => match input.text(position, 4)?.chars().next() {
+                    Some(next) if next == *c => {
+                        let end = position + c.len_utf8();
+                        captures.push(Capture::Leaf(*kind, position, end));
+                        position = end;
+                        true
+                    }
+                    _ => {
+                        failed = Some(*kind);
+                        false
+                    }
+                },
+                Instruction::Str(s, kind) => {
+                    if input.text(position, s.len())?.starts_with(&**s) {
+                        captures.push(Capture::Leaf(*kind, position, position + s.len()));
+                        position += s.len();
+                        true
+                    } else {
+                        failed = Some(*kind);
+                        false
+                    }
+                }
+                Instruction::Set(chars, sor, char_kind) => {
+                    match input.text(position, 4)?.chars().next() {
+                        Some(next) if chars.contains(&next) => {
+                            // The characters before it were tried first, and failed here.
+                            if chars[0] != next {
+                                let name = &self.kinds[*char_kind].1;
+                                Furthest::record(&mut furthest, position, name, false);
+                            }
+                            let end = position + next.len_utf8();
+                            captures.push(Capture::Open(*sor, position));
+                            captures.push(Capture::Leaf(*char_kind, position, end));
+                            captures.push(Capture::Close(end));
+                            position = end;
+                            true
+                        }
+                        _ => {
+                            failed = Some(*char_kind);
+                            false
+                        }
+                    }
+                }
+                Instruction::Literals(literals, kind, child) => {
+                    match literals.find(input.text(position, literals.max_len())?) {
+                        Some((len, _)) => {
+                            let end = position + len;
+                            captures.push(Capture::Open(*kind, position));
+                            captures.push(Capture::Leaf(*child, position, end));
+                            captures.push(Capture::Close(end));
+                            position = end;
+                            true
+                        }
+                        None => {
+                            failed = Some(*kind);
+                            false
+                        }
+                    }
+                }
+                Instruction::Eof(kind) => {
+                    captures.push(Capture::Empty(*kind, position));
+                    failed = Some(*kind);
+                    input.text(position, 1)?.is_empty()
+                }
+                Instruction::Empty(kind) => {
+                    captures.push(Capture::Empty(*kind, position));
+                    true
+                }
+                Instruction::Mark(kind) => {
+                    captures.push(Capture::Leaf(*kind, position, position));
+                    true
+                }
+                Instruction::Open(kind) => {
+                    captures.push(Capture::Open(*kind, position));
+                    true
+                }
+                Instruction::Close => {
+                    captures.push(Capture::Close(position));
+                    true
+                }
+                Instruction::Choice(label) => {
+                    stack.push(Frame::Choice {
+                        label: *label,
+                        position,
+                        captures: captures.len(),
+                    });
+                    true
+                }
+                Instruction::Commit(label) => {
+                    stack.pop();
+                    if position >= checkpoint {
+                        checkpoint = position + CHECKPOINT;
+                        self.checkpoint(input, &stack, position, captures, events.as_deref_mut())?;
+                    }
+                    pc = *label;
+                    continue;
+                }
+                Instruction::PartialCommit(label) => {
+                    if let Some(Frame::Choice {
+                        position: saved,
+                        captures: saved_captures,
+                        ..
+                    }) = stack.last_mut()
+                    {
+                        *saved = position;
+                        *saved_captures = captures.len();
+                    }
+                    if position >= checkpoint {
+                        checkpoint = position + CHECKPOINT;
+                        self.checkpoint(input, &stack, position, captures, events.as_deref_mut())?;
+                    }
+                    pc = *label;
+                    continue;
+                }
+                Instruction::Call(label, kind) => {
+                    stack.push(Frame::Return {
+                        next: pc + 1,
+                        start: position,
+                        kind: *kind,
+                    });
+                    pc = *label;
+                    continue;
+                }
+                Instruction::Return => {
+                    if let Some(Frame::Return { next, .. }) = stack.pop() {
+                        pc = next;
+                    }
+                    continue;
+                }
+                Instruction::Fail => false,
+                Instruction::Abort(kind) => {
+                    return Err(ParseError {
+                        offset: position,
+                        expected: self.kinds[*kind].1.to_string(),
+                        committed: true,
+                    }
+                    .into())
+                }
+                Instruction::End => return Ok(position),
+            };
+            if matched {
+                pc += 1;
+                continue;
+            }
+            if let Some(kind) = failed {
+                Furthest::record(&mut furthest, position, &self.kinds[kind].1, false);
+            }
+            // Backtracks to the last choice, leaving the subroutines entered since, which
+            // failed where they started.
+            loop {
+                match stack.pop() {
+                    Some(Frame::Choice {
+                        label,
+                        position: saved,
+                        captures: saved_captures,
+                    }) => {
+                        pc = label;
+                        position = saved;
+                        captures.truncate(saved_captures);
+                        break;
+                    }
+                    Some(Frame::Return { start, kind, .. }) => {
+                        Furthest::record(&mut furthest, start, &self.kinds[kind].1, true);
+                    }
+                    None => {
+                        let error = Failure::Backtrack.into_error("", &self.name, furthest);
+                        return Err(error.into());
+                    }
+                }
+            }
+        }
+    }
+
+    /// Passes on the captures no choice on the stack can undo anymore, and releases the
+    /// input no choice can go back to and no capture still needs.
+    fn checkpoint<I: Input + ?Sized>(
+        &self,
+        input: &mut I,
+        stack: &[Frame],
+        position: usize,
+        captures: &mut Captures,
+        events: Option<&mut Events>,
+    ) -> Result<(), InputError> {
+        let oldest = stack.iter().find_map(|frame| match frame {
+            Frame::Choice {
+                position, captures, ..
+            } => Some((*position, *captures)),
+            Frame::Return { .. } => None,
+        });
+        let (mut keep, settled) = oldest.unwrap_or((position, captures.len()));
+        if let Some(events) = events {
+            self.pass(input, captures, settled, events)?;
+            if let Some(first) = captures.list.first() {
+                keep = keep.min(first.start());
+            }
+        }
+        input.release(keep);
+        Ok(())
+    }
+
+    /// Passes the captures before `until` on as events, but for a last `Open`, which may
+    /// still turn out to be a token.
+    fn pass<I: Input + ?Sized>(
+        &self,
+        input: &mut I,
+        captures: &mut Captures,
+        until: usize,
+        events: &mut Events,
+    ) -> Result<(), InputError> {
+        let until = until - captures.passed;
+        let token = |kind: Kind, span: std::ops::Range<usize>| {
+            let (id, name) = self.kinds[kind].clone();
+            Event::Token { id, name, span }
+        };
+        let mut index = 0;
+        while index < until {
+            let event = match captures.list[index] {
+                Capture::Open(kind, start) if index + 1 < until => {
+                    if let Capture::Close(end) = captures.list[index + 1] {
+                        index += 1;
+                        token(kind, start..end)
+                    } else {
+                        let (id, name) = self.kinds[kind].clone();
+                        events.open.push((kind, start));
+                        Event::Enter {
+                            id,
+                            name,
+                            offset: start,
+                        }
+                    }
+                }
+                Capture::Open(..) => break,
+                Capture::Close(end) => {
+                    let (kind, start) = events.open.pop().expect("close without open");
+                    let (id, name) = self.kinds[kind].clone();
+                    Event::Exit {
+                        id,
+                        name,
+                        span: start..end,
+                    }
+                }
+                Capture::Leaf(kind, start, end) => token(kind, start..end),
+                Capture::Empty(kind, position) => token(kind, position..position),
+            };
+            let text = match &event {
+                Event::Token { span, .. } => &input.text(span.start, span.len())?[..span.len()],
+                _ => "",
+            };
+            (events.consumer)(event, text);
+            index += 1;
+        }
+        captures.list.drain(..index);
+        captures.passed += index;
+        Ok(())
+    }
+
+    /// Turns the captures of a successful parse into the tree.
+    fn build<'a>(&self, input: &'a str, captures: &[Capture]) -> Node<'a> {
+        let mut open: Vec<(Node<'a>, usize)> = Vec::new();
+        let mut done = Vec::new();
+        for capture in captures {
+            let node = match *capture {
+                Capture::Open(kind, start) => {
+                    let (id, name) = &self.kinds[kind];
+                    open.push((Node::new_empty(*id, name), start));
+                    continue;
+                }
+                Capture::Close(end) => {
+                    let (mut node, start) = open.pop().expect("close without open");
+                    node.content = &input[start..end];
+                    node
+                }
+                Capture::Leaf(kind, start, end) => {
+                    let (id, name) = &self.kinds[kind];
+                    Node::new(&input[start..end], *id, name)
+                }
+                Capture::Empty(kind, _) => {
+                    let (id, name) = &self.kinds[kind];
+                    Node::new_empty(*id, name)
+                }
+            };
+            match open.last_mut() {
+                Some((parent, _)) => parent.add_child(node),
+                None => done.push(node),
+            }
+        }
+        done.pop().expect("a successful parse makes one node")
+    }
+
+    fn build_tree<'a>(&self, input: &'a str, captures: &[Capture]) -> Tree<'a> {
+        let mut tree = Tree::with_capacity(captures.len());
+        let mut starts = Vec::new();
+        for capture in captures {
+            match *capture {
+                Capture::Open(kind, start) => {
+                    let (id, name) = &self.kinds[kind];
+                    tree.open(*id, name, "");
+                    starts.push(start);
+                }
+                Capture::Close(end) => {
+                    let node = tree.close();
+                    let start = starts.pop().expect("close without open");
+                    tree[node].content = &input[start..end];
+                }
+                Capture::Leaf(kind, start, end) => {
+                    let (id, name) = &self.kinds[kind];
+                    tree.leaf(*id, name, &input[start..end]);
+                }
+                Capture::Empty(kind, _) => {
+                    let (id, name) = &self.kinds[kind];
+                    tree.leaf(*id, name, "");
+                }
+            }
+        }
+        tree
+    }
+}
+
+/// The error of a run over a string, which cannot fail to be read.
+fn parse_error(error: ReadError) -> ParseError {
+    match error {
+        ReadError::Parse(error) => error,
+        ReadError::Input(error) => unreachable!("a string cannot fail to be read: {}", error),
+    }
+}
+
+/// Lists the instructions with their addresses and the kinds of nodes they make.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (address, instruction) in self.code.iter().enumerate() {
+            writeln!(f, "{:4}  {}", address, instruction)?;
+        }
+        for (kind, (_, name)) in self.kinds.iter().enumerate() {
+            writeln!(f, "kind {}: {}", kind, name)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generate::Sentences;
+    use crate::input::ReaderInput;
+    use crate::test_support::{inputs, shape, Trickle};
+    use crate::Grammar;
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    const VALUES: &str = r#"
+        Values <- Value (',' Space Value)* EOF ;
+        Value <- literals("null", "nil", "none") / List / Number / Word ;
+        List <- '[' ^ Space (Value Space)* must(']') ;
+        Number <- '-'? Digit+ ;
+        Digit <- '0' / '1' / '2' ;
+        Word <- ('a' / 'b' / "cd")+ ;
+        Space <- ' '* ;
+    "#;
+
+    #[test]
+    fn same_results_as_the_rules() {
+        let sentences = Sentences::new();
+        let grammar = Grammar::from_peg(VALUES).unwrap();
+        let fixed = ["", "[1 [null] -2], ab", "[1 2", "[1 2, 1", "-"];
+        let inputs = inputs(&grammar.start, &sentences, &fixed, 200);
+        for name in ["Values", "List", "Number"] {
+            let rule = grammar.get(name).unwrap();
+            let program = Program::compile(rule).unwrap();
+            for input in &inputs {
+                let interpreted = rule.try_parse(input);
+                let length = match &interpreted {
+                    Ok(node) => Ok(node.content.len()),
+                    Err(error) => Err(error.clone()),
+                };
+                assert_eq!(program.recognize(input), length, "{:?}", input);
+                match (interpreted, program.run(input)) {
+                    (Ok(interpreted), Ok(compiled)) => {
+                        assert_eq!(shape(&interpreted), shape(&compiled), "{:?}", input);
+                        let tree = program.run_tree(input).unwrap();
+                        assert_eq!(shape(&tree.to_node(tree.root())), shape(&compiled));
+                    }
+                    (interpreted, compiled) => {
+                        assert_eq!(interpreted.err(), compiled.err(), "{:?}", input)
+                    }
+                }
+            }
+        }
+    }
+
+    fn reader(input: &str, limit: usize) -> ReaderInput<Trickle<'_>> {
+        ReaderInput::with_limit(Trickle::new(input.as_bytes()), limit)
+    }
+
+    #[test]
+    fn same_events_as_the_rules() {
+        let sentences = Sentences::new();
+        let grammar = Grammar::from_peg(VALUES).unwrap();
+        let program = Program::compile(&grammar.start).unwrap();
+        let fixed = ["[1 [null] -2], ab", "[1 2"];
+        for input in inputs(&grammar.start, &sentences, &fixed, 100) {
+            let expected = Rc::new(RefCell::new(Vec::new()));
+            let sink = expected.clone();
+            let parsed = grammar
+                .start
+                .parse_events(input, move |event| sink.borrow_mut().push(event));
+            let mut events = Vec::new();
+            let result = program.run_events(&mut reader(input, 64), |event, text| {
+                if let Event::Token { span, .. } = &event {
+                    assert_eq!(text, &input[span.clone()]);
+                }
+                events.push(event);
+            });
+            match (parsed, result) {
+                (Ok(expected_length), Ok(length)) => {
+                    assert_eq!(length, expected_length);
+                    assert_eq!(events, *expected.borrow(), "{:?}", input);
+                }
+                (Err(expected), Err(ReadError::Parse(error))) => assert_eq!(error, expected),
+                (parsed, result) => panic!("{:?} against {:?} for {:?}", parsed, result, input),
+            }
+        }
+    }
+
+    #[test]
+    fn events_are_passed_on_while_reading() {
+        let grammar =
+            Grammar::from_peg("Lines <- Line* EOF ; Line <- ('a' / 'b')+ '\n' ;").unwrap();
+        let program = Program::compile(&grammar.start).unwrap();
+        let input = "abba\n".repeat(100_000);
+        let read = Rc::new(Cell::new(0));
+        let trickle = Trickle(input.as_bytes(), read.clone());
+        let mut reader = ReaderInput::new(trickle);
+        let mut first = None;
+        let mut lines = 0;
+        let length = program.run_events(&mut reader, |event, _| {
+            if matches!(event, Event::Exit { name, .. } if name == "Line") {
+                first.get_or_insert(read.get());
+                lines += 1;
+            }
+        });
+        assert_eq!(length.unwrap(), input.len());
+        assert_eq!(lines, 100_000);
+        assert!(first.unwrap() < 2 * CHECKPOINT);
+        // Released as it went, without reaching the limit.
+        assert!(reader.retained() > input.len() - 2 * CHECKPOINT);
+        let mut reader = self::reader(&input, 64);
+        assert_eq!(program.recognize_input(&mut reader).unwrap(), input.len());
+    }
+
+    #[test]
+    fn backtracking_past_the_limit() {
+        let grammar = Grammar::from_peg("Line <- 'a'* ';' / 'a'* '.' ;").unwrap();
+        let program = Program::compile(&grammar.start).unwrap();
+        let input = format!("{}.", "a".repeat(100_000));
+        assert_eq!(program.recognize(&input), Ok(input.len()));
+        assert!(matches!(
+            program.recognize_input(&mut reader(&input, 64)),
+            Err(ReadError::Input(InputError::Discarded { offset: 0, .. }))
+        ));
+        let limit = ReaderInput::<&[u8]>::DEFAULT_LIMIT;
+        assert_eq!(
+            program.recognize_input(&mut reader(&input, limit)).unwrap(),
+            input.len()
+        );
+    }
+
+    #[test]
+    fn instructions() {
+        let grammar = Grammar::from_peg("Digits <- ('0' / '1')+ '.'? ;").unwrap();
+        let program = Program::compile(&grammar.start).unwrap();
+        assert_eq!(
+            program.to_string(),
+            "   0  call 2\n   1  end\n   2  open 0\n   3  open 1\n   4  open 2\n   5  set \"01\"\n   6  choice 9\n   7  set \"01\"\n   8  partial commit 7\n   9  close\n  10  choice 13\n  11  char '.'\n  12  commit 14\n  13  empty 5\n  14  close\n  15  close\n  16  return\nkind 0: Digits\nkind 1: Seq\nkind 2: Plus\nkind 3: Sor\nkind 4: Char\nkind 5: Opt\n"
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let grammar = Grammar::from_peg("Line <- recover('a', ';') ;").unwrap();
+        assert_eq!(
+            Program::compile(&grammar.start).err().unwrap().to_string(),
+            "rule Recover cannot be compiled"
+        );
+        let grammar = Grammar::from_peg("Line <- 'a' FAIL ;").unwrap();
+        let program = Program::compile(&grammar.start).unwrap();
+        assert_eq!(
+            program.run("a").unwrap_err().to_string(),
+            "expected Line at offset 0"
+        );
+    }
+}
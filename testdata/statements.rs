@@ -0,0 +1,738 @@
+// Parser for the grammar starting with Program, generated by yaast::codegen.
+// Do not edit, change the grammar instead.
+
+/// Names of the named rules, in the order of their ids in `ids`.
+pub const NAMES: [&str; 10] = ["Program", "Space", "Statement", "Assignment", "Sync", "Value", "Name", "List", "Number", "Digit"];
+
+/// Ids of the named rules, assigned on first use.
+pub fn ids() -> &'static [usize; 10] {
+    static IDS: ::std::sync::OnceLock<[usize; 10]> = ::std::sync::OnceLock::new();
+    IDS.get_or_init(|| {
+        ::std::array::from_fn(|_| {
+            ::yaast::COUNTER.fetch_add(1, ::std::sync::atomic::Ordering::SeqCst)
+        })
+    })
+}
+
+/// The id of the named rule.
+pub fn id(name: &str) -> Option<usize> {
+    NAMES.iter().position(|other| *other == name).map(|index| ids()[index])
+}
+
+/// Parses with `Program`, the start rule.
+pub fn parse(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r0, "Program")
+}
+
+/// Parses with `Program`, like `Rule::try_parse`.
+pub fn parse_program(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r0, "Program")
+}
+
+/// Parses with `Statement`, like `Rule::try_parse`.
+pub fn parse_statement(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r6, "Statement")
+}
+
+/// Parses with `Assignment`, like `Rule::try_parse`.
+pub fn parse_assignment(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r9, "Assignment")
+}
+
+/// Parses with `Sync`, like `Rule::try_parse`.
+pub fn parse_sync(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r10, "Sync")
+}
+
+/// Parses with `Value`, like `Rule::try_parse`.
+pub fn parse_value(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r16, "Value")
+}
+
+/// Parses with `List`, like `Rule::try_parse`.
+pub fn parse_list(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r24, "List")
+}
+
+/// Parses with `Number`, like `Rule::try_parse`.
+pub fn parse_number(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r25, "Number")
+}
+
+/// Parses with `Digit`, like `Rule::try_parse`.
+pub fn parse_digit(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r34, "Digit")
+}
+
+/// Parses with `Name`, like `Rule::try_parse`.
+pub fn parse_name(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r13, "Name")
+}
+
+/// Parses with `Space`, like `Rule::try_parse`.
+pub fn parse_space(input: &str) -> Result<::yaast::Node<'_>, ::yaast::ParseError> {
+    run(input, r2, "Space")
+}
+
+/// Turns backtracking into a failure that stops at the cut before the rule.
+#[allow(dead_code)]
+fn commit<'a>(
+    failure: ::yaast::Failure<'a>,
+    rest: &'a str,
+    id: usize,
+    name: &'static str,
+) -> ::yaast::Failure<'a> {
+    match failure {
+        ::yaast::Failure::Backtrack => ::yaast::Failure::Cut {
+            rest,
+            id,
+            name: ::yaast::Name::Static(name),
+        },
+        failure => failure,
+    }
+}
+
+thread_local! {
+    /// Length of the input being parsed and where the parse got furthest, see
+    /// `::yaast::Furthest`.
+    static FURTHEST: ::std::cell::RefCell<(usize, Option<::yaast::Furthest>)> =
+        const { ::std::cell::RefCell::new((0, None)) };
+}
+
+/// Parses the input with the rule, named `name`, for the public functions.
+fn run<'a>(
+    input: &'a str,
+    rule: fn(&'a str) -> ::yaast::ParseResult<'a>,
+    name: &str,
+) -> Result<::yaast::Node<'a>, ::yaast::ParseError> {
+    FURTHEST.with(|furthest| *furthest.borrow_mut() = (input.len(), None));
+    rule(input).map_err(|failure| {
+        failure.into_error(input, name, FURTHEST.with(|furthest| furthest.take().1))
+    })
+}
+
+/// Records that the rule named `name` backtracked on `rest`.
+#[allow(dead_code)]
+fn backtrack(rest: &str, name: &'static str, named: bool) -> ::yaast::Failure<'static> {
+    FURTHEST.with(|cell| {
+        let (len, furthest) = &mut *cell.borrow_mut();
+        let name = ::yaast::Name::Static(name);
+        ::yaast::Furthest::record(furthest, *len - rest.len(), &name, named);
+    });
+    ::yaast::Failure::Backtrack
+}
+
+/// Parses with the sync rule of a `recover!`, which does not count for errors.
+#[allow(dead_code)]
+fn sync<'a>(
+    rule: fn(&'a str) -> ::yaast::ParseResult<'a>,
+    rest: &'a str,
+) -> ::yaast::ParseResult<'a> {
+    let furthest = FURTHEST.with(|furthest| furthest.borrow().clone());
+    let result = rule(rest);
+    FURTHEST.with(|cell| *cell.borrow_mut() = furthest);
+    result
+}
+
+/// `Program <- Space Statement* EOF`
+fn r0<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r1(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Program", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[0], "Program");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Space Statement* EOF`
+fn r1<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r3(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r4(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `Space <- ' '*`
+fn r2<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r5(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Space", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[1], "Space");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Statement*`
+fn r3<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::STAR_ID, "Star");
+    let mut size = 0;
+    loop {
+        match r6(&input[size..]) {
+            Ok(child) => {
+                size += child.content.len();
+                node.add_child(child);
+            }
+            Err(::yaast::Failure::Backtrack) => break,
+            Err(failure) => return Err(failure),
+        }
+    }
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `EOF`
+fn r4<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.is_empty() {
+        Ok(::yaast::Node::new_empty(*::yaast::rule::EOF_ID, "Eof"))
+    } else {
+        Err(backtrack(input, "Eof", false))
+    }
+}
+
+/// `' '*`
+fn r5<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::STAR_ID, "Star");
+    let mut size = 0;
+    loop {
+        match r7(&input[size..]) {
+            Ok(child) => {
+                size += child.content.len();
+                node.add_child(child);
+            }
+            Err(::yaast::Failure::Backtrack) => break,
+            Err(failure) => return Err(failure),
+        }
+    }
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `Statement <- recover(Assignment, Sync)`
+fn r6<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r8(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Statement", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[2], "Statement");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `' '`
+fn r7<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with(' ') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `recover(Assignment, Sync)`
+fn r8<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    r9(input).or_else(|failure| {
+        ::yaast::rule::recover_from(input, failure, ids()[3], &::yaast::Name::Static("Assignment"), |rest| sync(r10, rest))
+    })
+}
+
+/// `Assignment <- Name Space '=' ^ Space Value must(';') Space`
+fn r9<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r11(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Assignment", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[3], "Assignment");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Sync <- ';' Space`
+fn r10<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r12(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Sync", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[4], "Sync");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Name Space '=' ^ Space Value must(';') Space`
+fn r11<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r13(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r14(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r15(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], ids()[1], "Space"))?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r16(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], ids()[5], "Value"))?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r17(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], *::yaast::rule::MUST_ID, "Must"))?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], ids()[1], "Space"))?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `';' Space`
+fn r12<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r18(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `Name <- ('a' / 'b')+`
+fn r13<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r19(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Name", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[6], "Name");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `'='`
+fn r14<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('=') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `^`
+fn r15<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    Ok(::yaast::Node::new(&input[..0], *::yaast::rule::CUT_ID, "Cut"))
+}
+
+/// `Value <- keywords("true", "truest", "false") / List / Number / Name`
+fn r16<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r20(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Value", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[5], "Value");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `must(';')`
+fn r17<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    r21(input).map_err(|failure| commit(failure, input, *::yaast::rule::CHAR_ID, "Char"))
+}
+
+/// `';'`
+fn r18<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with(';') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `('a' / 'b')+`
+fn r19<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::PLUS_ID, "Plus");
+    let child = r22(input)?;
+    let mut size = child.content.len();
+    node.add_child(child);
+    loop {
+        match r22(&input[size..]) {
+            Ok(child) => {
+                size += child.content.len();
+                node.add_child(child);
+            }
+            Err(::yaast::Failure::Backtrack) => break,
+            Err(failure) => return Err(failure),
+        }
+    }
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `keywords("true", "truest", "false") / List / Number / Name`
+fn r20<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    match r23(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r24(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r25(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r13(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    Err(::yaast::Failure::Backtrack)
+}
+
+/// `';'`
+fn r21<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with(';') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `'a' / 'b'`
+fn r22<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    match r26(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r27(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    Err(::yaast::Failure::Backtrack)
+}
+
+/// `keywords("true", "truest", "false")`
+fn r23<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    static LITERALS: ::std::sync::OnceLock<::yaast::rule::Literals> = ::std::sync::OnceLock::new();
+    let literals = LITERALS.get_or_init(|| {
+        ::yaast::rule::Literals::new(vec!["true".to_string(), "truest".to_string(), "false".to_string()], true, false)
+    });
+    match literals.find(input) {
+        Some((len, _)) => {
+            let mut node = ::yaast::Node::new(&input[..len], *::yaast::rule::LITERALS_ID, "Literals");
+            node.add_child(::yaast::Node::new(&input[..len], *::yaast::rule::STR_ID, "Str"));
+            Ok(node)
+        }
+        None => Err(backtrack(input, "Literals", false)),
+    }
+}
+
+/// `List <- '[' ^ Space (Value Space (',' Space Value Space)*)? ']'`
+fn r24<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r28(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "List", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[7], "List");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Number <- Digit+`
+fn r25<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r29(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Number", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[8], "Number");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `'a'`
+fn r26<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('a') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `'b'`
+fn r27<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('b') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `'[' ^ Space (Value Space (',' Space Value Space)*)? ']'`
+fn r28<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r30(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r31(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], ids()[1], "Space"))?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r32(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], *::yaast::rule::OPT_ID, "Opt"))?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r33(&input[size..])
+        .map_err(|failure| commit(failure, &input[size..], *::yaast::rule::CHAR_ID, "Char"))?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `Digit+`
+fn r29<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::PLUS_ID, "Plus");
+    let child = r34(input)?;
+    let mut size = child.content.len();
+    node.add_child(child);
+    loop {
+        match r34(&input[size..]) {
+            Ok(child) => {
+                size += child.content.len();
+                node.add_child(child);
+            }
+            Err(::yaast::Failure::Backtrack) => break,
+            Err(failure) => return Err(failure),
+        }
+    }
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `'['`
+fn r30<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('[') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `^`
+fn r31<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    Ok(::yaast::Node::new(&input[..0], *::yaast::rule::CUT_ID, "Cut"))
+}
+
+/// `(Value Space (',' Space Value Space)*)?`
+fn r32<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    match r35(input) {
+        Err(::yaast::Failure::Backtrack) => Ok(::yaast::Node::new_empty(*::yaast::rule::OPT_ID, "Opt")),
+        result => result,
+    }
+}
+
+/// `']'`
+fn r33<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with(']') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `Digit <- '0' / '1' / '2'`
+fn r34<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let child = match r36(input) {
+        Ok(child) => child,
+        Err(::yaast::Failure::Backtrack) => return Err(backtrack(input, "Digit", true)),
+        Err(failure) => return Err(failure),
+    };
+    let mut node = ::yaast::Node::new(child.content, ids()[9], "Digit");
+    node.add_child(child);
+    Ok(node)
+}
+
+/// `Value Space (',' Space Value Space)*`
+fn r35<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r16(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r37(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `'0' / '1' / '2'`
+fn r36<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    match r38(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r39(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    match r40(input) {
+        Ok(child) => {
+            let mut node = ::yaast::Node::new(child.content, *::yaast::rule::SOR_ID, "Sor");
+            node.add_child(child);
+            return Ok(node);
+        }
+        Err(::yaast::Failure::Backtrack) => {}
+        Err(failure) => return Err(failure),
+    }
+    Err(::yaast::Failure::Backtrack)
+}
+
+/// `(',' Space Value Space)*`
+fn r37<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::STAR_ID, "Star");
+    let mut size = 0;
+    loop {
+        match r41(&input[size..]) {
+            Ok(child) => {
+                size += child.content.len();
+                node.add_child(child);
+            }
+            Err(::yaast::Failure::Backtrack) => break,
+            Err(failure) => return Err(failure),
+        }
+    }
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `'0'`
+fn r38<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('0') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `'1'`
+fn r39<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('1') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `'2'`
+fn r40<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with('2') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
+
+/// `',' Space Value Space`
+fn r41<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    let mut node = ::yaast::Node::new_empty(*::yaast::rule::SEQ_ID, "Seq");
+    let mut size = 0;
+    let child = r42(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r16(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    let child = r2(&input[size..])?;
+    size += child.content.len();
+    node.add_child(child);
+    node.content = &input[..size];
+    Ok(node)
+}
+
+/// `','`
+fn r42<'a>(input: &'a str) -> ::yaast::ParseResult<'a> {
+    if input.starts_with(',') {
+        Ok(::yaast::Node::new(&input[..1], *::yaast::rule::CHAR_ID, "Char"))
+    } else {
+        Err(backtrack(input, "Char", false))
+    }
+}
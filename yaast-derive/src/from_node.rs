@@ -0,0 +1,222 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    Attribute, Data, DeriveInput, Expr, ExprLit, Fields, GenericArgument, Lifetime, LifetimeParam,
+    Lit, PathArguments, Type,
+};
+
+use crate::camel_case;
+
+/// The value of a `#[rule = "..."]` attribute.
+fn rule_attribute(attrs: &[Attribute]) -> syn::Result<Option<String>> {
+    for attr in attrs {
+        if !attr.path().is_ident("rule") {
+            continue;
+        }
+        return match &attr.meta {
+            syn::Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(value),
+                    ..
+                }) => Ok(Some(value.value())),
+                other => Err(syn::Error::new(other.span(), "expected a string")),
+            },
+            other => Err(syn::Error::new(other.span(), "expected `rule = \"...\"`")),
+        };
+    }
+    Ok(None)
+}
+
+/// `Option` or `Vec` if the type is one of them, judged by its name, as macros cannot
+/// resolve types.
+fn wrapper(ty: &Type) -> Option<&'static str> {
+    let Type::Path(path) = ty else { return None };
+    let segment = path.path.segments.last()?;
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    if arguments.args.len() != 1 || !matches!(arguments.args[0], GenericArgument::Type(_)) {
+        return None;
+    }
+    if segment.ident == "Option" {
+        Some("Option")
+    } else if segment.ident == "Vec" {
+        Some("Vec")
+    } else {
+        None
+    }
+}
+
+fn struct_body(fields: &Fields) -> syn::Result<TokenStream2> {
+    match fields {
+        Fields::Unit => Ok(quote!(Ok(Self))),
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => Ok(quote! {
+            ::yaast::FromNode::from_node(node).map(Self)
+        }),
+        Fields::Unnamed(unnamed) => Err(syn::Error::new(
+            unnamed.span(),
+            "FromNode needs named fields, or a single unnamed one taking the whole node",
+        )),
+        Fields::Named(named) => {
+            let mut values = Vec::new();
+            for field in &named.named {
+                let ident = field.ident.as_ref().unwrap();
+                let child = match rule_attribute(&field.attrs)? {
+                    Some(child) => child,
+                    None => camel_case(ident.to_string().trim_start_matches("r#")),
+                };
+                let helper = match wrapper(&field.ty) {
+                    Some("Option") => quote!(optional),
+                    Some(_) => quote!(many),
+                    None => quote!(one),
+                };
+                values.push(quote! {
+                    #ident: ::yaast::from_node::#helper(node, &children, #child)?
+                });
+            }
+            Ok(quote! {
+                let children = ::yaast::from_node::named_children(node);
+                Ok(Self { #(#values),* })
+            })
+        }
+    }
+}
+
+fn enum_body(data: &syn::DataEnum, rule: &str, lifetime: &Lifetime) -> syn::Result<TokenStream2> {
+    let mut arms = Vec::new();
+    let mut names = Vec::new();
+    for variant in &data.variants {
+        let ident = &variant.ident;
+        let name = match rule_attribute(&variant.attrs)? {
+            Some(name) => name,
+            None => ident.to_string(),
+        };
+        let value = match &variant.fields {
+            Fields::Unit => quote!(Ok(Self::#ident)),
+            Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => {
+                quote!(::yaast::FromNode::from_node(node).map(Self::#ident))
+            }
+            fields => {
+                return Err(syn::Error::new(
+                    fields.span(),
+                    "FromNode variants hold at most one unnamed field, built from the node",
+                ))
+            }
+        };
+        arms.push(quote!(#name => Some(#value)));
+        names.push(name);
+    }
+    let expected = names.join(", ");
+    Ok(quote! {
+        let variant = |node: &::yaast::Node<#lifetime>| -> Option<Result<Self, ::yaast::FromNodeError>> {
+            match node.type_name.as_str() {
+                #(#arms,)*
+                _ => None,
+            }
+        };
+        if let Some(value) = variant(node) {
+            return value;
+        }
+        if node.type_name == #rule {
+            if let [child] = ::yaast::from_node::named_children(node)[..] {
+                if let Some(value) = variant(child) {
+                    return value.map_err(|error| error.within(node));
+                }
+            }
+            return Err(::yaast::FromNodeError::new(
+                format!("expected one of {}", #expected),
+            )
+            .within(node));
+        }
+        Err(::yaast::FromNodeError::new(format!(
+            "expected {} or one of {}, found {} {:?}",
+            #rule, #expected, node.type_name, node.content
+        )))
+    })
+}
+
+pub fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let rule = match rule_attribute(&input.attrs)? {
+        Some(rule) => rule,
+        None => name.to_string(),
+    };
+    // The lifetime of the input, the first one of the type or a new one.
+    let mut generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'input", Span::call_site());
+            generics
+                .params
+                .insert(0, LifetimeParam::new(lifetime.clone()).into());
+            lifetime
+        }
+    };
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let body = struct_body(&data.fields)?;
+            quote! {
+                ::yaast::from_node::expect_rule(node, #rule)?;
+                #body
+            }
+        }
+        Data::Enum(data) => enum_body(data, &rule, &lifetime)?,
+        Data::Union(_) => {
+            return Err(syn::Error::new(
+                name.span(),
+                "FromNode cannot be derived for unions",
+            ))
+        }
+    };
+
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::yaast::FromNode<#lifetime> for #name #ty_generics #where_clause {
+            fn from_node(
+                node: &::yaast::Node<#lifetime>,
+            ) -> Result<Self, ::yaast::FromNodeError> {
+                #body
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn unsupported_shapes() {
+        assert_eq!(
+            error(syn::parse_quote! { struct Pair(String, String); }),
+            "FromNode needs named fields, or a single unnamed one taking the whole node"
+        );
+        assert_eq!(
+            error(syn::parse_quote! { enum Value { Pair { key: String } } }),
+            "FromNode variants hold at most one unnamed field, built from the node"
+        );
+        assert_eq!(
+            error(syn::parse_quote! { #[rule(List)] struct Items; }),
+            "expected `rule = \"...\"`"
+        );
+    }
+
+    #[test]
+    fn wrappers() {
+        assert_eq!(wrapper(&syn::parse_quote!(Option<Key>)), Some("Option"));
+        assert_eq!(
+            wrapper(&syn::parse_quote!(std::vec::Vec<Key<'a>>)),
+            Some("Vec")
+        );
+        assert_eq!(wrapper(&syn::parse_quote!(Box<Key>)), None);
+        assert_eq!(wrapper(&syn::parse_quote!(Key)), None);
+    }
+}
@@ -0,0 +1,353 @@
+//! `#[derive(Grammar)]` for structs holding a `yaast::Grammar` loaded from PEG notation,
+//! and `#[derive(FromNode)]` for building typed values from the trees it parses.
+//!
+//! ```
+//! use yaast::Grammar;
+//! use yaast_derive::Grammar;
+//!
+//! #[derive(Grammar)]
+//! #[grammar = "tests/list.peg"]
+//! struct List<'a> {
+//!     grammar: Grammar<'a>,
+//! }
+//!
+//! let input = "[1, 2]";
+//! let list = List::new();
+//! let tree = list.list().parse(input).unwrap();
+//! assert_eq!(list.rule_of(&tree), Some(ListRule::List));
+//! assert_eq!(list.rule_of(&tree.children[0]), None);
+//! ```
+//!
+//! The path is relative to the directory of the crate's `Cargo.toml`, the grammar can also
+//! be given directly with `#[grammar_inline = "..."]`. The grammar is parsed and checked
+//! while compiling, so a syntax error, an undefined rule or left recursion is a compile error.
+//!
+//! The derive generates `List::new()`, a method returning each rule, named after the rule
+//! in snake case, and an enum `ListRule` with a variant per rule, which
+//! `List::rule_of(&node)` returns for the nodes of the named rules.
+
+mod from_node;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Expr, ExprLit, Fields, Ident, Lit, LitStr, Member,
+};
+use yaast::check::Severity;
+use yaast::codegen::snake_case;
+use yaast::Grammar;
+
+#[proc_macro_derive(Grammar, attributes(grammar, grammar_inline))]
+pub fn derive_grammar(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Implements `yaast::FromNode`, see its documentation for how nodes are mapped.
+///
+/// A struct is built from a node of the rule named like the struct, or given by
+/// `#[rule = "..."]`. Each named field is taken from the named rules below that node,
+/// the field `key_value` from the rule `KeyValue` unless it has its own `#[rule = "..."]`.
+/// `Option` fields take zero or one node, `Vec` fields any number and other fields exactly one.
+/// A struct with a single unnamed field builds it from the whole node.
+///
+/// An enum is built from a node of the rule of one of its variants, or from a node of the
+/// enum's own rule holding one. A variant has no fields or one unnamed field.
+#[proc_macro_derive(FromNode, attributes(rule))]
+pub fn derive_from_node(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    from_node::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Where the grammar comes from.
+enum Source {
+    /// Absolute path of a file, included so that changing it rebuilds the crate.
+    File(String),
+    Inline(LitStr),
+}
+
+/// The text of the grammar, from the `grammar` or `grammar_inline` attribute.
+fn source(input: &DeriveInput) -> syn::Result<(Source, String, Span)> {
+    for attr in &input.attrs {
+        let inline = attr.path().is_ident("grammar_inline");
+        if !inline && !attr.path().is_ident("grammar") {
+            continue;
+        }
+        let value = match &attr.meta {
+            syn::Meta::NameValue(meta) => match &meta.value {
+                Expr::Lit(ExprLit {
+                    lit: Lit::Str(value),
+                    ..
+                }) => value.clone(),
+                other => return Err(syn::Error::new(other.span(), "expected a string")),
+            },
+            other => return Err(syn::Error::new(other.span(), "expected `= \"...\"`")),
+        };
+        if inline {
+            let text = value.value();
+            return Ok((Source::Inline(value.clone()), text, value.span()));
+        }
+        let directory = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_else(|_| ".".to_string());
+        let path = std::path::Path::new(&directory).join(value.value());
+        let text = std::fs::read_to_string(&path).map_err(|error| {
+            syn::Error::new(
+                value.span(),
+                format!("cannot read {}: {}", path.display(), error),
+            )
+        })?;
+        return Ok((
+            Source::File(path.to_string_lossy().into_owned()),
+            text,
+            value.span(),
+        ));
+    }
+    Err(syn::Error::new(
+        Span::call_site(),
+        "expected a #[grammar = \"file.peg\"] or #[grammar_inline = \"...\"] attribute",
+    ))
+}
+
+/// The field holding the grammar, the only field of the struct.
+fn field(input: &DeriveInput) -> syn::Result<Member> {
+    let fields = match &input.data {
+        Data::Struct(data) => &data.fields,
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "Grammar can only be derived for structs",
+            ))
+        }
+    };
+    match fields {
+        Fields::Named(named) if named.named.len() == 1 => {
+            Ok(Member::Named(named.named[0].ident.clone().unwrap()))
+        }
+        Fields::Unnamed(unnamed) if unnamed.unnamed.len() == 1 => Ok(Member::Unnamed(0.into())),
+        _ => Err(syn::Error::new(
+            fields.span(),
+            "expected a single field holding the yaast::Grammar",
+        )),
+    }
+}
+
+/// Parses and checks the grammar, returning the names of its rules with their
+/// definitions, in the order of `Grammar::rules`.
+fn validate(text: &str) -> Result<Vec<(String, String)>, String> {
+    let grammar = Grammar::from_peg(text).map_err(|error| format!("invalid grammar: {}", error))?;
+    let errors: Vec<String> = grammar
+        .check()
+        .iter()
+        .filter(|lint| lint.severity == Severity::Error)
+        .map(ToString::to_string)
+        .collect();
+    if !errors.is_empty() {
+        return Err(errors.join("\n"));
+    }
+    Ok(grammar
+        .rules
+        .iter()
+        .map(|rule| (rule.name.to_string(), Grammar::expression(rule)))
+        .collect())
+}
+
+/// `json_value` and `JsonValue` become `JsonValue`.
+fn camel_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap();
+            first.to_uppercase().chain(chars).collect::<String>()
+        })
+        .collect()
+}
+
+/// An identifier for the name, a raw one if it is a keyword.
+fn ident(name: &str, span: Span) -> syn::Result<Ident> {
+    if syn::parse_str::<Ident>(name).is_ok() {
+        return Ok(Ident::new(name, span));
+    }
+    let starts_well = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_');
+    if starts_well && !matches!(name, "_" | "self" | "Self" | "super" | "crate") {
+        return Ok(Ident::new_raw(name, span));
+    }
+    Err(syn::Error::new(
+        span,
+        format!("`{}` cannot be used as a Rust identifier", name),
+    ))
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let (source, text, span) = source(input)?;
+    let member = field(input)?;
+    let rules = validate(&text).map_err(|message| syn::Error::new(span, message))?;
+
+    let name = &input.ident;
+    let vis = &input.vis;
+    let kind = format_ident!("{}Rule", name);
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => syn::Lifetime::new("'static", Span::call_site()),
+    };
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let mut methods = Vec::new();
+    let mut variants = Vec::new();
+    let mut taken = vec!["new".to_string(), "rule".to_string(), "rule_of".to_string()];
+    for (rule, _) in &rules {
+        let method = snake_case(rule);
+        if taken.contains(&method) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "rule {} would need the method name {}, which is taken",
+                    rule, method
+                ),
+            ));
+        }
+        taken.push(method.clone());
+        methods.push(ident(&method, span)?);
+        let variant = camel_case(rule);
+        if variants.iter().any(|other: &Ident| *other == variant) {
+            return Err(syn::Error::new(
+                span,
+                format!(
+                    "rule {} would need the variant name {}, which is taken",
+                    rule, variant
+                ),
+            ));
+        }
+        variants.push(ident(&variant, span)?);
+    }
+
+    let text = match source {
+        Source::File(path) => quote!(include_str!(#path)),
+        Source::Inline(text) => quote!(#text),
+    };
+    let indices = 0..rules.len();
+    let count = rules.len();
+    let docs = rules
+        .iter()
+        .map(|(rule, expression)| format!("`{} <- {}`", rule, expression));
+    let names = rules.iter().map(|(rule, _)| rule);
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Builds the grammar, which was checked when it was compiled.
+            pub fn new() -> Self {
+                Self {
+                    #member: ::yaast::Grammar::from_peg(#text)
+                        .expect("the grammar was checked when it was compiled"),
+                }
+            }
+
+            #(
+                #[doc = #docs]
+                pub fn #methods(&self) -> &::yaast::rule::Rule<#lifetime> {
+                    &self.#member.rules[#indices]
+                }
+            )*
+
+            /// The rule of the grammar for a kind.
+            pub fn rule(&self, kind: #kind) -> &::yaast::rule::Rule<#lifetime> {
+                &self.#member.rules[kind as usize]
+            }
+
+            /// The kind of rule that produced the node, `None` for nodes of unnamed
+            /// rules like `Seq` and of rules of other grammars.
+            pub fn rule_of(&self, node: &::yaast::Node) -> Option<#kind> {
+                #kind::ALL
+                    .into_iter()
+                    .find(|kind| self.#member.rules[*kind as usize].id == node.type_id)
+            }
+        }
+
+        /// The rules of the grammar.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #vis enum #kind {
+            #(#variants),*
+        }
+
+        impl #kind {
+            /// Every kind, in the order the rules are defined.
+            pub const ALL: [#kind; #count] = [#(#kind::#variants),*];
+
+            /// The name of the rule in the grammar.
+            pub fn name(self) -> &'static str {
+                [#(#names),*][self as usize]
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(&input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn case_conversion() {
+        assert_eq!(camel_case("key_value"), "KeyValue");
+        assert_eq!(camel_case("JsonValue"), "JsonValue");
+        assert_eq!(
+            ident("type", Span::call_site()).unwrap().to_string(),
+            "r#type"
+        );
+        assert!(ident("1st", Span::call_site()).is_err());
+    }
+
+    #[test]
+    fn compile_errors() {
+        assert_eq!(
+            error(syn::parse_quote! {
+                #[grammar_inline = "A <- 'a' "]
+                struct G<'a> { grammar: yaast::Grammar<'a> }
+            }),
+            "invalid grammar: 1:10: expected ';'"
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                #[grammar_inline = "A <- A 'a' ;"]
+                struct G<'a> { grammar: yaast::Grammar<'a> }
+            }),
+            "error in A: left recursion A -> A"
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                #[grammar_inline = "A <- 'a' ; a <- 'b' ;"]
+                struct G<'a> { grammar: yaast::Grammar<'a> }
+            }),
+            "rule a would need the method name a, which is taken"
+        );
+        assert_eq!(
+            error(syn::parse_quote! {
+                #[grammar_inline = "A <- 'a' ;"]
+                struct G<'a> { grammar: yaast::Grammar<'a>, other: usize }
+            }),
+            "expected a single field holding the yaast::Grammar"
+        );
+        assert!(error(syn::parse_quote! {
+            #[grammar = "missing.peg"]
+            struct G<'a>(yaast::Grammar<'a>);
+        })
+        .starts_with("cannot read "));
+        assert_eq!(
+            error(syn::parse_quote! {
+                struct G<'a>(yaast::Grammar<'a>);
+            }),
+            "expected a #[grammar = \"file.peg\"] or #[grammar_inline = \"...\"] attribute"
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use yaast::{Grammar, Node};
+use yaast_derive::Grammar;
+
+#[derive(Grammar)]
+#[grammar = "tests/list.peg"]
+struct List<'a> {
+    grammar: Grammar<'a>,
+}
+
+#[derive(Grammar)]
+#[grammar_inline = "KeyValue <- Key '=' Value ; Key <- 'k' ; Value <- 'v' / Type ; Type <- 't' ;"]
+pub struct Pair<'a>(Grammar<'a>);
+
+fn kinds(list: &List, node: &Node, found: &mut Vec<ListRule>) {
+    found.extend(list.rule_of(node));
+    for child in &node.children {
+        kinds(list, child, found);
+    }
+}
+
+#[test]
+fn rules_and_kinds() {
+    let input = "[1, [2]]";
+    let list = List::new();
+    assert_eq!(list.list().name, "List");
+    assert_eq!(list.grammar.start.id, list.list().id);
+    assert!(list.digit().parse("2").is_some());
+
+    let tree = list.list().parse(input).unwrap();
+    assert_eq!(list.rule_of(&tree), Some(ListRule::List));
+    let mut found = Vec::new();
+    kinds(&list, &tree, &mut found);
+    let names: Vec<_> = found.iter().map(|kind| kind.name()).collect();
+    assert_eq!(
+        names,
+        [
+            "List", "Space", "Item", "Digit", "Space", "Item", "List", "Space", "Item", "Digit",
+            "Space", "Space"
+        ]
+    );
+    assert_eq!(list.rule(ListRule::Space).name, "Space");
+    assert_eq!(ListRule::ALL.len(), 4);
+}
+
+#[test]
+fn tuple_struct_and_names() {
+    let input = "k=t";
+    let pair = Pair::new();
+    let tree = pair.key_value().parse(input).unwrap();
+    assert_eq!(pair.rule_of(&tree), Some(PairRule::KeyValue));
+    assert_eq!(pair.r#type().name, "Type");
+    assert_eq!(PairRule::Type.name(), "Type");
+    assert_eq!(pair.rule_of(&tree.children[0]), None);
+}
@@ -0,0 +1,121 @@
+use yaast::{FromNode, Grammar};
+use yaast_derive::FromNode;
+
+const ASSIGNMENTS: &str = r#"
+    Program <- (Assignment ';' ' '?)* ;
+    Assignment <- Name " = " Expression ;
+    Expression <- Number / Call / Name ;
+    Call <- Name '(' (Expression (", " Expression)*)? ')' ;
+    Name <- ('a' / 'b' / 'f' / 'g')+ ;
+    Number <- ('0' / '1' / '2')+ ;
+"#;
+
+#[derive(FromNode, Debug, PartialEq)]
+struct Program<'a> {
+    assignment: Vec<Assignment<'a>>,
+}
+
+#[derive(FromNode, Debug, PartialEq)]
+struct Assignment<'a> {
+    #[rule = "Name"]
+    target: &'a str,
+    expression: Expression<'a>,
+}
+
+#[derive(FromNode, Debug, PartialEq)]
+enum Expression<'a> {
+    Number(Number),
+    Call(Call<'a>),
+    #[rule = "Name"]
+    Variable(&'a str),
+}
+
+#[derive(FromNode, Debug, PartialEq)]
+struct Number(String);
+
+#[derive(FromNode, Debug, PartialEq)]
+struct Call<'a> {
+    name: &'a str,
+    #[rule = "Expression"]
+    arguments: Vec<Expression<'a>>,
+    #[rule = "Number"]
+    never: Option<Number>,
+}
+
+#[test]
+fn typed_tree() {
+    let input = "a = 12; b = f(a, g(0)); f = ba;";
+    let grammar = Grammar::from_peg(ASSIGNMENTS).unwrap();
+    let tree = grammar.start.parse(input).unwrap();
+    let program = Program::from_node(&tree).unwrap();
+    let call = |name, arguments| {
+        Expression::Call(Call {
+            name,
+            arguments,
+            never: None,
+        })
+    };
+    assert_eq!(
+        program.assignment,
+        [
+            Assignment {
+                target: "a",
+                expression: Expression::Number(Number("12".to_string())),
+            },
+            Assignment {
+                target: "b",
+                expression: call(
+                    "f",
+                    vec![
+                        Expression::Variable("a"),
+                        call("g", vec![Expression::Number(Number("0".to_string()))]),
+                    ]
+                ),
+            },
+            Assignment {
+                target: "f",
+                expression: Expression::Variable("ba"),
+            },
+        ]
+    );
+}
+
+#[test]
+fn shape_errors() {
+    let (call, name) = ("f(1)", "ab");
+    let grammar = Grammar::from_peg(ASSIGNMENTS).unwrap();
+    let tree = grammar.get("Call").unwrap().parse(call).unwrap();
+    assert_eq!(
+        Assignment::from_node(&tree).err().unwrap().to_string(),
+        "expected Assignment, found Call \"f(1)\""
+    );
+    assert_eq!(
+        Number::from_node(&tree).err().unwrap().to_string(),
+        "expected Number, found Call \"f(1)\""
+    );
+
+    // Parsing an expression directly leaves out the Expression node around the call.
+    let tree = grammar.get("Call").unwrap().parse(call).unwrap();
+    assert!(matches!(
+        Expression::from_node(&tree),
+        Ok(Expression::Call(_))
+    ));
+
+    let tree = grammar.get("Name").unwrap().parse(name).unwrap();
+    assert_eq!(
+        Call::from_node(&tree).err().unwrap().to_string(),
+        "expected Call, found Name \"ab\""
+    );
+
+    let grammar = Grammar::from_peg("Expression <- Other ; Other <- 'x' ;").unwrap();
+    let tree = grammar.start.parse("x").unwrap();
+    assert_eq!(
+        Expression::from_node(&tree).err().unwrap().to_string(),
+        "in Expression: expected one of Number, Call, Name"
+    );
+    let tree = grammar.get("Other").unwrap().parse("x").unwrap();
+    assert_eq!(
+        Expression::from_node(&tree).err().unwrap().to_string(),
+        "expected Expression or one of Number, Call, Name, found Other \"x\""
+    );
+}